@@ -0,0 +1,80 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Gives the path of a shader relative to the root of the crate invoking the macro, so that it is the same on every
+/// machine building the crate.
+pub(crate) fn crate_relative_path(shader_path: &str) -> PathBuf {
+    let root = env::var("CARGO_MANIFEST_DIR").expect("proc macros should be run using cargo");
+
+    let shader_path = Path::new(shader_path);
+    let relative = shader_path.strip_prefix(&root).unwrap_or(shader_path);
+    relative.strip_prefix("/").unwrap_or(relative).to_path_buf()
+}
+
+/// Gives the directory within `OUT_DIR` that files describing shaders are written to.
+pub(crate) fn artifacts_dir() -> Result<PathBuf, String> {
+    let out_dir = env::var("OUT_DIR").map_err(|_| {
+        "`OUT_DIR` is not set - add a `build.rs` file to your crate so that cargo provides one"
+            .to_owned()
+    })?;
+
+    Ok(Path::new(&out_dir).join("include-wgsl-oil"))
+}
+
+/// Writes a file describing the shader at `shader_path` into `OUT_DIR`, mirroring the location of the shader within
+/// the crate, e.g. `$OUT_DIR/include-wgsl-oil/src/shaders/foo.snap` for `src/shaders/foo.wgsl`.
+pub(crate) fn write_artifact(
+    shader_path: &str,
+    extension: &str,
+    contents: &str,
+) -> Result<PathBuf, String> {
+    let path = artifacts_dir()
+        .map_err(|e| format!("could not write `.{extension}` file for `{shader_path}`: {e}"))?
+        .join(crate_relative_path(shader_path))
+        .with_extension(extension);
+
+    let parent = path
+        .parent()
+        .expect("artifact paths have a parent directory");
+    fs::create_dir_all(parent)
+        .and_then(|_| fs::write(&path, contents))
+        .map_err(|e| format!("could not write `{}`: {e}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Writes a file requested at `path`, relative to the root of the crate invoking the macro, creating any missing
+/// directories.
+pub(crate) fn write_crate_file(path: &Path, contents: &str) -> Result<PathBuf, String> {
+    let root = env::var("CARGO_MANIFEST_DIR").expect("proc macros should be run using cargo");
+    let path = Path::new(&root).join(path);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("could not create `{}`: {e}", parent.display()))?;
+    }
+    fs::write(&path, contents).map_err(|e| format!("could not write `{}`: {e}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Escapes a path for a Makefile rule, where spaces and `#` would otherwise end the path or start a comment.
+fn escape_make_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('$', "$$")
+        .replace('#', "\\#")
+        .replace(' ', "\\ ")
+}
+
+/// Formats a Makefile-style depfile, as read by `make`, `ninja` and others, giving a rule for `target` listing every
+/// file it depends on.
+pub(crate) fn depfile(target: &Path, dependencies: &[PathBuf]) -> String {
+    let mut rule = escape_make_path(target) + ":";
+    for dependency in dependencies {
+        rule += " \\\n  ";
+        rule += &escape_make_path(dependency);
+    }
+    rule + "\n"
+}
@@ -0,0 +1,725 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+
+use crate::{reflect, structs};
+
+/// A resource bound with `@group` and `@binding`, i.e. a buffer, texture or sampler, or a binding array of them.
+#[derive(Clone, Copy)]
+pub(crate) struct Binding<'a> {
+    pub(crate) handle: naga::Handle<naga::GlobalVariable>,
+    pub(crate) global: &'a naga::GlobalVariable,
+    pub(crate) group: u32,
+    pub(crate) binding: u32,
+}
+
+impl Binding<'_> {
+    pub(crate) fn ident(&self) -> syn::Ident {
+        reflect::item_ident(self.global.name.as_deref().unwrap_or("_"))
+    }
+
+    /// The type of each bound resource, looking through binding arrays.
+    pub(crate) fn resource_ty(&self, module: &naga::Module) -> naga::Handle<naga::Type> {
+        match module.types[self.global.ty].inner {
+            naga::TypeInner::BindingArray { base, .. } => base,
+            _ => self.global.ty,
+        }
+    }
+
+    /// For binding arrays, the number of resources in the array, or `None` if it is runtime-sized.
+    pub(crate) fn array_size(&self, module: &naga::Module) -> Option<Option<u32>> {
+        match module.types[self.global.ty].inner {
+            naga::TypeInner::BindingArray {
+                size: naga::ArraySize::Constant(size),
+                ..
+            } => Some(Some(size.get())),
+            naga::TypeInner::BindingArray { .. } => Some(None),
+            _ => None,
+        }
+    }
+
+    /// The stages of the entry points which use the resource.
+    pub(crate) fn stages(
+        &self,
+        module: &naga::Module,
+        info: &naga::valid::ModuleInfo,
+    ) -> Vec<naga::ShaderStage> {
+        let mut stages = Vec::new();
+        for (index, entry_point) in module.entry_points.iter().enumerate() {
+            let used = !info.get_entry_point(index)[self.handle].is_empty();
+            if used && !stages.contains(&entry_point.stage) {
+                stages.push(entry_point.stage);
+            }
+        }
+        stages
+    }
+}
+
+/// Finds the names of the structs of storage buffers ending in a runtime-sized array, which are generated as their
+/// fixed-size header even if they aren't exported.
+pub(crate) fn runtime_sized_structs(module: &naga::Module) -> Vec<String> {
+    bindings(module)
+        .into_iter()
+        .filter(|binding| matches!(binding.global.space, naga::AddressSpace::Storage { .. }))
+        .filter_map(|binding| {
+            let ty = binding.resource_ty(module);
+            let is_struct = matches!(module.types[ty].inner, naga::TypeInner::Struct { .. });
+            (is_struct && structs::runtime_array(module, ty).is_some())
+                .then(|| module.types[ty].name.clone())
+                .flatten()
+        })
+        .collect()
+}
+
+/// Finds every resource bound by the module, ordered by group and then binding.
+pub(crate) fn bindings(module: &naga::Module) -> Vec<Binding<'_>> {
+    let mut bindings = module
+        .global_variables
+        .iter()
+        .filter_map(|(handle, global)| {
+            let binding = global.binding.as_ref()?;
+            Some(Binding {
+                handle,
+                global,
+                group: binding.group,
+                binding: binding.binding,
+            })
+        })
+        .collect::<Vec<_>>();
+    bindings.sort_by_key(|binding| (binding.group, binding.binding));
+    bindings
+}
+
+/// Finds every texture and the samplers it is sampled with by any entry point, directly or through the functions it
+/// calls, as `(texture, sampler)` pairs ordered by the group and binding of the texture and then of the sampler.
+pub(crate) fn sampling_pairs<'a>(
+    module: &'a naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Vec<(Binding<'a>, Binding<'a>)> {
+    let bindings = bindings(module);
+    let find = |handle| bindings.iter().find(|binding| binding.handle == handle);
+
+    let mut pairs = Vec::new();
+    for index in 0..module.entry_points.len() {
+        for key in &info.get_entry_point(index).sampling_set {
+            if let (Some(image), Some(sampler)) = (find(key.image), find(key.sampler)) {
+                pairs.push((*image, *sampler));
+            }
+        }
+    }
+    pairs.sort_by_key(|(image, sampler)| {
+        (image.group, image.binding, sampler.group, sampler.binding)
+    });
+    pairs.dedup_by_key(|(image, sampler)| (image.handle, sampler.handle));
+    pairs
+}
+
+/// Gives a `wgpu::ShaderStages` expression covering the given stages.
+pub(crate) fn shader_stages_tokens(stages: &[naga::ShaderStage]) -> TokenStream {
+    let mut stages = stages.iter().map(|stage| {
+        let stage = match stage {
+            naga::ShaderStage::Vertex => format_ident!("VERTEX"),
+            naga::ShaderStage::Fragment => format_ident!("FRAGMENT"),
+            naga::ShaderStage::Compute => format_ident!("COMPUTE"),
+        };
+        quote! { ::wgpu::ShaderStages::#stage }
+    });
+    let Some(first) = stages.next() else {
+        return quote! { ::wgpu::ShaderStages::NONE };
+    };
+    stages.fold(first, |stages, stage| quote! { #stages.union(#stage) })
+}
+
+fn view_dimension_tokens(dim: naga::ImageDimension, arrayed: bool) -> TokenStream {
+    let dimension = match (dim, arrayed) {
+        (naga::ImageDimension::D1, _) => format_ident!("D1"),
+        (naga::ImageDimension::D2, false) => format_ident!("D2"),
+        (naga::ImageDimension::D2, true) => format_ident!("D2Array"),
+        (naga::ImageDimension::D3, _) => format_ident!("D3"),
+        (naga::ImageDimension::Cube, false) => format_ident!("Cube"),
+        (naga::ImageDimension::Cube, true) => format_ident!("CubeArray"),
+    };
+    quote! { ::wgpu::TextureViewDimension::#dimension }
+}
+
+/// Gives the `wgpu::TextureSampleType` of a sampled or depth texture.
+fn sample_type_tokens(class: naga::ImageClass) -> Option<TokenStream> {
+    let sample_type = match class {
+        naga::ImageClass::Sampled { kind, multi } => {
+            // Multisampled textures can't be filtered
+            let filterable = !multi;
+            match kind {
+                naga::ScalarKind::Sint => quote! { ::wgpu::TextureSampleType::Sint },
+                naga::ScalarKind::Uint => quote! { ::wgpu::TextureSampleType::Uint },
+                _ => quote! { ::wgpu::TextureSampleType::Float { filterable: #filterable } },
+            }
+        }
+        naga::ImageClass::Depth { .. } => quote! { ::wgpu::TextureSampleType::Depth },
+        naga::ImageClass::Storage { .. } => return None,
+    };
+    Some(sample_type)
+}
+
+/// Generates constants describing a texture binding: its WGSL type, whether it is multisampled, and with the `wgpu`
+/// feature its view dimension and sample type, or for storage textures their format and access mode, for hand-written
+/// `wgpu::BindingType` entries.
+fn texture_tokens(module: &naga::Module, binding: &Binding<'_>) -> Option<TokenStream> {
+    let ty = binding.resource_ty(module);
+    let naga::TypeInner::Image {
+        dim,
+        arrayed,
+        class,
+    } = module.types[ty].inner
+    else {
+        return None;
+    };
+    let texture_type = reflect::type_name(module, ty);
+    let multisampled = match class {
+        naga::ImageClass::Sampled { multi, .. } | naga::ImageClass::Depth { multi } => {
+            Some(quote! {
+                /// Whether the texture is multisampled.
+                pub const MULTISAMPLED: bool = #multi;
+            })
+        }
+        naga::ImageClass::Storage { .. } => None,
+    };
+    let wgpu = cfg!(feature = "wgpu").then(|| {
+        let view_dimension = view_dimension_tokens(dim, arrayed);
+        let sample_type = sample_type_tokens(class).map(|sample_type| {
+            quote! {
+                /// The type of the texture's samples.
+                pub const SAMPLE_TYPE: ::wgpu::TextureSampleType = #sample_type;
+            }
+        });
+        let storage = match class {
+            naga::ImageClass::Storage { format, access } => {
+                // Storage formats are named the same in both crates
+                let format = format_ident!("{}", format!("{format:?}"));
+                let access = storage_access_tokens(access);
+                Some(quote! {
+                    /// The texel format that the storage texture is declared with.
+                    pub const FORMAT: ::wgpu::TextureFormat = ::wgpu::TextureFormat::#format;
+                    /// The access mode that the storage texture is declared with.
+                    pub const ACCESS: ::wgpu::StorageTextureAccess = #access;
+                })
+            }
+            _ => None,
+        };
+        quote! {
+            /// The dimension of the views that can be bound to the texture.
+            pub const VIEW_DIMENSION: ::wgpu::TextureViewDimension = #view_dimension;
+            #sample_type
+            #storage
+        }
+    });
+
+    Some(quote! {
+        /// The texture's type, as written in WGSL.
+        pub const TEXTURE_TYPE: &str = #texture_type;
+        #multisampled
+        #wgpu
+    })
+}
+
+fn storage_access_tokens(access: naga::StorageAccess) -> TokenStream {
+    #[cfg(feature = "naga-24")]
+    if access.contains(naga::StorageAccess::ATOMIC) {
+        return quote! { ::wgpu::StorageTextureAccess::Atomic };
+    }
+
+    let access = match (
+        access.contains(naga::StorageAccess::LOAD),
+        access.contains(naga::StorageAccess::STORE),
+    ) {
+        (true, true) => format_ident!("ReadWrite"),
+        (true, false) => format_ident!("ReadOnly"),
+        _ => format_ident!("WriteOnly"),
+    };
+    quote! { ::wgpu::StorageTextureAccess::#access }
+}
+
+/// Gives the variant of the generated `ResourceKind` enum describing each resource of the binding, or `None` if the
+/// resource is of a kind which can't be bound.
+fn resource_kind(module: &naga::Module, binding: &Binding<'_>) -> Option<syn::Ident> {
+    let kind = match binding.global.space {
+        naga::AddressSpace::Uniform => "UniformBuffer",
+        naga::AddressSpace::Storage { access } if access.contains(naga::StorageAccess::STORE) => {
+            "StorageBuffer"
+        }
+        naga::AddressSpace::Storage { .. } => "ReadOnlyStorageBuffer",
+        naga::AddressSpace::Handle => match module.types[binding.resource_ty(module)].inner {
+            naga::TypeInner::Image {
+                class: naga::ImageClass::Storage { .. },
+                ..
+            } => "StorageTexture",
+            naga::TypeInner::Image { .. } => "Texture",
+            naga::TypeInner::Sampler { comparison: false } => "Sampler",
+            naga::TypeInner::Sampler { comparison: true } => "ComparisonSampler",
+            naga::TypeInner::AccelerationStructure => "AccelerationStructure",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(format_ident!("{}", kind))
+}
+
+/// Gives the name of the marker type of a binding, e.g. `CameraBinding` for `camera`.
+fn marker_ident(binding: &Binding<'_>) -> syn::Ident {
+    let mut name = String::new();
+    for word in binding.ident().unraw().to_string().split('_') {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase().chain(chars));
+        }
+    }
+    format_ident!("{}Binding", name)
+}
+
+/// Generates a marker type for a binding, giving its group, binding and kind as associated constants, and with the
+/// `wgpu` feature an `entry` method taking the kind of resource that the shader expects, so that bind groups assembled
+/// from the markers are checked against the shader's interface.
+fn marker_tokens(module: &naga::Module, binding: &Binding<'_>) -> TokenStream {
+    let ident = binding.ident();
+    let marker = marker_ident(binding);
+    let doc = format!(
+        " Marks the `{}` binding.",
+        reflect::display_name(binding.global.name.as_deref().unwrap_or_default())
+    );
+    let kind = resource_kind(module, binding).map(|_| {
+        quote! {
+            pub const KIND: ResourceKind = #ident::KIND;
+        }
+    });
+
+    let array = binding.array_size(module).is_some();
+    let resource = match (
+        binding.global.space,
+        &module.types[binding.resource_ty(module)].inner,
+    ) {
+        (naga::AddressSpace::Uniform | naga::AddressSpace::Storage { .. }, _) if array => Some((
+            quote! { &'a [::wgpu::BufferBinding<'a>] },
+            quote! { BufferArray },
+        )),
+        (naga::AddressSpace::Uniform | naga::AddressSpace::Storage { .. }, _) => {
+            Some((quote! { ::wgpu::BufferBinding<'a> }, quote! { Buffer }))
+        }
+        (_, naga::TypeInner::Image { .. }) if array => Some((
+            quote! { &'a [&'a ::wgpu::TextureView] },
+            quote! { TextureViewArray },
+        )),
+        (_, naga::TypeInner::Image { .. }) => {
+            Some((quote! { &'a ::wgpu::TextureView }, quote! { TextureView }))
+        }
+        (_, naga::TypeInner::Sampler { .. }) if array => Some((
+            quote! { &'a [&'a ::wgpu::Sampler] },
+            quote! { SamplerArray },
+        )),
+        (_, naga::TypeInner::Sampler { .. }) => {
+            Some((quote! { &'a ::wgpu::Sampler }, quote! { Sampler }))
+        }
+        _ => None,
+    };
+    let entry = resource
+        .filter(|_| cfg!(feature = "wgpu"))
+        .map(|(ty, variant)| {
+            quote! {
+                /// The entry binding a resource of the kind the shader expects.
+                pub fn entry<'a>(self, resource: #ty) -> ::wgpu::BindGroupEntry<'a> {
+                    ::wgpu::BindGroupEntry {
+                        binding: Self::BINDING,
+                        resource: ::wgpu::BindingResource::#variant(resource),
+                    }
+                }
+            }
+        });
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        pub struct #marker;
+
+        impl #marker {
+            pub const GROUP: u32 = #ident::GROUP;
+            pub const BINDING: u32 = #ident::BINDING;
+            #kind
+            #entry
+        }
+    }
+}
+
+/// Whether the layout of a group can be created up front, i.e. with the `wgpu` feature when all of its bindings have
+/// layout entries.
+pub(crate) fn group_has_layout(
+    module: &naga::Module,
+    layouter: &naga::proc::Layouter,
+    in_group: &[&Binding<'_>],
+) -> bool {
+    cfg!(feature = "wgpu")
+        && in_group.iter().all(|binding| {
+            binding_type_tokens(module, layouter, binding).is_some()
+                && binding.array_size(module) != Some(None)
+        })
+}
+
+/// Gives a `wgpu::BindingType` expression describing each resource of the binding, or `None` if `wgpu` has no
+/// equivalent.
+fn binding_type_tokens(
+    module: &naga::Module,
+    layouter: &naga::proc::Layouter,
+    binding: &Binding<'_>,
+) -> Option<TokenStream> {
+    let ty = binding.resource_ty(module);
+    let buffer = |buffer_type: TokenStream| {
+        let size = u64::from(layouter[ty].size);
+        quote! {
+            ::wgpu::BindingType::Buffer {
+                ty: #buffer_type,
+                has_dynamic_offset: false,
+                min_binding_size: ::wgpu::BufferSize::new(#size),
+            }
+        }
+    };
+
+    let tokens = match binding.global.space {
+        naga::AddressSpace::Uniform => buffer(quote! { ::wgpu::BufferBindingType::Uniform }),
+        naga::AddressSpace::Storage { access } => {
+            let read_only = !access.contains(naga::StorageAccess::STORE);
+            buffer(quote! { ::wgpu::BufferBindingType::Storage { read_only: #read_only } })
+        }
+        naga::AddressSpace::Handle => match &module.types[ty].inner {
+            naga::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                let view_dimension = view_dimension_tokens(*dim, *arrayed);
+                match class {
+                    naga::ImageClass::Sampled { multi, .. } | naga::ImageClass::Depth { multi } => {
+                        let sample_type = sample_type_tokens(*class)?;
+                        quote! {
+                            ::wgpu::BindingType::Texture {
+                                sample_type: #sample_type,
+                                view_dimension: #view_dimension,
+                                multisampled: #multi,
+                            }
+                        }
+                    }
+                    // Given by the `FORMAT` and `ACCESS` constants next to the layout entry
+                    naga::ImageClass::Storage { .. } => quote! {
+                        ::wgpu::BindingType::StorageTexture {
+                            access: ACCESS,
+                            format: FORMAT,
+                            view_dimension: #view_dimension,
+                        }
+                    },
+                }
+            }
+            naga::TypeInner::Sampler { comparison: true } => quote! {
+                ::wgpu::BindingType::Sampler(::wgpu::SamplerBindingType::Comparison)
+            },
+            naga::TypeInner::Sampler { comparison: false } => quote! {
+                ::wgpu::BindingType::Sampler(::wgpu::SamplerBindingType::Filtering)
+            },
+            naga::TypeInner::AccelerationStructure => {
+                quote! { ::wgpu::BindingType::AccelerationStructure }
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(tokens)
+}
+
+/// Generates a `bindings` module with a submodule for every resource bound by the shader, named after its global
+/// variable, giving its group, binding and kind, the size of binding arrays, the type of textures, the strides of buffers
+/// which are arrays and the sizes of those ending in runtime-sized arrays, and with the `wgpu` feature the stages which use it and its bind group layout
+/// entry. Each group gets a `group_<n>` submodule listing the resources bound in it as constants and as a `Binding`
+/// enum, and with the `wgpu` feature creating its bind group layout, and each binding gets a marker type. A `BINDINGS` constant maps the resources' names to their groups and bindings.
+pub(crate) fn binding_items(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Vec<syn::Item> {
+    let bindings = bindings(module);
+    if bindings.is_empty() {
+        return Vec::new();
+    }
+
+    let layouter = reflect::layouter(module);
+    let pairs = sampling_pairs(module, info);
+    let binding_modules = bindings.iter().map(|binding| {
+        let ident = binding.ident();
+        let group = binding.group;
+        let index = binding.binding;
+        let array_size = binding.array_size(module);
+        let (count, layout_count) = match array_size.flatten() {
+            Some(count) => (
+                quote! { Some(#count) },
+                quote! { ::core::num::NonZeroU32::new(#count) },
+            ),
+            None => (quote! { None }, quote! { None }),
+        };
+
+        // Runtime-sized binding arrays are given their size when the layout is created
+        let layout_entry = binding_type_tokens(module, &layouter, binding)
+            .filter(|_| cfg!(feature = "wgpu") && array_size != Some(None))
+            .map(|ty| {
+                quote! {
+                    /// The entry describing this binding in its bind group layout.
+                    pub const LAYOUT_ENTRY: ::wgpu::BindGroupLayoutEntry = ::wgpu::BindGroupLayoutEntry {
+                        binding: #index,
+                        visibility: STAGES,
+                        ty: #ty,
+                        count: #layout_count,
+                    };
+                }
+            });
+
+        let stages = cfg!(feature = "wgpu").then(|| {
+            let stages = shader_stages_tokens(&binding.stages(module, info));
+            quote! {
+                /// The stages of the entry points which use the resource, directly or through the functions they call.
+                pub const STAGES: ::wgpu::ShaderStages = #stages;
+            }
+        });
+
+        // Textures and samplers list what they are sampled with, for engines which generate combined bindings
+        let sampling = match module.types[binding.resource_ty(module)].inner {
+            naga::TypeInner::Image { .. } => {
+                let samplers = pairs
+                    .iter()
+                    .filter(|(image, _)| image.handle == binding.handle)
+                    .map(|(_, sampler)| (sampler.group, sampler.binding))
+                    .map(|(group, binding)| quote! { (#group, #binding) });
+                Some(quote! {
+                    /// The `(group, binding)` pairs of the samplers that the texture is sampled with.
+                    pub const SAMPLERS: &[(u32, u32)] = &[#(#samplers),*];
+                })
+            }
+            naga::TypeInner::Sampler { .. } => {
+                let textures = pairs
+                    .iter()
+                    .filter(|(_, sampler)| sampler.handle == binding.handle)
+                    .map(|(image, _)| (image.group, image.binding))
+                    .map(|(group, binding)| quote! { (#group, #binding) });
+                Some(quote! {
+                    /// The `(group, binding)` pairs of the textures that the sampler samples.
+                    pub const TEXTURES: &[(u32, u32)] = &[#(#textures),*];
+                })
+            }
+            _ => None,
+        };
+
+        // Storage buffers ending in a runtime-sized array are sized by the number of elements they hold, and other
+        // buffers which are arrays give their stride
+        let ty = binding.resource_ty(module);
+        let buffer_array = match (
+            binding.global.space,
+            structs::runtime_array(module, ty),
+            &module.types[ty].inner,
+        ) {
+            (naga::AddressSpace::Storage { .. }, Some(array), _) => Some(
+                structs::runtime_size_tokens(array, layouter[ty].alignment.round_up(1)),
+            ),
+            (
+                naga::AddressSpace::Uniform | naga::AddressSpace::Storage { .. },
+                _,
+                naga::TypeInner::Array { stride, .. },
+            ) => {
+                let stride = u64::from(*stride);
+                Some(quote! {
+                    /// The stride of the elements of the array, in bytes.
+                    pub const ELEMENT_STRIDE: u64 = #stride;
+                })
+            }
+            _ => None,
+        };
+        let texture = texture_tokens(module, binding);
+        let kind = resource_kind(module, binding).map(|kind| {
+            quote! {
+                /// The kind of each resource bound.
+                pub const KIND: super::ResourceKind = super::ResourceKind::#kind;
+            }
+        });
+
+        quote! {
+            pub mod #ident {
+                pub const GROUP: u32 = #group;
+                pub const BINDING: u32 = #index;
+                #kind
+                /// The number of resources in the binding array, or `None` for single resources and runtime-sized
+                /// binding arrays.
+                pub const COUNT: Option<u32> = #count;
+                #sampling
+                #texture
+                #buffer_array
+                #stages
+                #layout_entry
+            }
+        }
+    });
+    let named_pairs = pairs.iter().map(|(image, sampler)| {
+        let image = image.ident().unraw().to_string();
+        let sampler = sampler.ident().unraw().to_string();
+        quote! { (#image, #sampler) }
+    });
+
+    let mut groups = bindings
+        .iter()
+        .map(|binding| binding.group)
+        .collect::<Vec<_>>();
+    groups.dedup();
+    let group_modules = groups.iter().map(|group| {
+        let ident = format_ident!("group_{}", group);
+        let in_group = bindings
+            .iter()
+            .filter(|binding| binding.group == *group)
+            .collect::<Vec<_>>();
+        let entries = in_group.iter().filter_map(|binding| {
+            let index = binding.binding;
+            let kind = resource_kind(module, binding)?;
+            Some(quote! { (#index, super::ResourceKind::#kind) })
+        });
+        let idents = in_group
+            .iter()
+            .map(|binding| binding.ident())
+            .collect::<Vec<_>>();
+
+        let layout = group_has_layout(module, &layouter, &in_group).then(|| {
+            quote! {
+                /// The entries of the group's bind group layout, in ascending order of binding.
+                pub const LAYOUT_ENTRIES: &[::wgpu::BindGroupLayoutEntry] = &[#(super::#idents::LAYOUT_ENTRY),*];
+
+                /// Creates the group's bind group layout, labelled with the path of this module.
+                pub fn create_bind_group_layout(device: &::wgpu::Device) -> ::wgpu::BindGroupLayout {
+                    device.create_bind_group_layout(&::wgpu::BindGroupLayoutDescriptor {
+                        label: Some(module_path!()),
+                        entries: LAYOUT_ENTRIES,
+                    })
+                }
+            }
+        });
+        let doc = format!(" The resources bound in group {group}.");
+        quote! {
+            #[doc = #doc]
+            pub mod #ident {
+                pub const GROUP: u32 = #group;
+                /// The binding index and kind of each resource in the group, in ascending order of binding.
+                pub const ENTRIES: &[(u32, super::ResourceKind)] = &[#(#entries),*];
+                pub use super::{#(#idents),*};
+
+                /// The resources bound in the group, named after their global variables, for exhaustive matches when
+                /// filling in a bind group's entries.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+                pub enum Binding {
+                    #(#idents),*
+                }
+
+                impl Binding {
+                    /// Every resource in the group, in ascending order of binding.
+                    pub const ALL: &[Self] = &[#(Self::#idents),*];
+
+                    /// The binding index of the resource.
+                    pub const fn binding(self) -> u32 {
+                        match self {
+                            #(Self::#idents => #idents::BINDING),*
+                        }
+                    }
+                }
+                #layout
+            }
+        }
+    });
+
+    let markers = bindings
+        .iter()
+        .map(|binding| marker_tokens(module, binding));
+    let named = bindings.iter().map(|binding| {
+        let name =
+            reflect::display_name(binding.global.name.as_deref().unwrap_or_default()).into_owned();
+        let (group, index) = (binding.group, binding.binding);
+        quote! { (#name, #group, #index) }
+    });
+
+    vec![
+        syn::parse_quote! {
+            /// The `(name, group, binding)` of every resource bound by the shader, ordered by group and then binding.
+            pub const BINDINGS: &[(&str, u32, u32)] = &[#(#named),*];
+        },
+        syn::parse_quote! {
+            /// The resources bound by the shader, named after their global variables.
+            pub mod bindings {
+                /// The kinds of resources that can be bound.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+                pub enum ResourceKind {
+                    UniformBuffer,
+                    StorageBuffer,
+                    ReadOnlyStorageBuffer,
+                    Texture,
+                    StorageTexture,
+                    Sampler,
+                    ComparisonSampler,
+                    AccelerationStructure,
+                }
+
+                /// The bind groups that the shader uses, in ascending order.
+                pub const GROUPS: &[u32] = &[#(#groups),*];
+                /// Every texture that the shader samples paired with each sampler it is sampled with, by name.
+                pub const TEXTURE_SAMPLER_PAIRS: &[(&str, &str)] = &[#(#named_pairs),*];
+                #(#group_modules)*
+                #(#binding_modules)*
+                #(#markers)*
+            }
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture_type(source: &str) -> String {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let binding = &bindings(&module)[0];
+        let tokens = texture_tokens(&module, binding).unwrap().to_string();
+        let start = tokens.find("TEXTURE_TYPE").unwrap();
+        let literal = &tokens[start..];
+        let literal = &literal[literal.find('"').unwrap() + 1..];
+        literal[..literal.find('"').unwrap()].to_owned()
+    }
+
+    #[test]
+    fn storage_texture_type() {
+        let texture_type = texture_type(
+            "@group(0) @binding(0) var t: texture_storage_2d<rgba8unorm, write>;
+            @compute @workgroup_size(1) fn main() { textureStore(t, vec2(0), vec4(1.0)); }",
+        );
+        assert_eq!(texture_type, "texture_storage_2d<rgba8unorm, write>");
+
+        // The type is valid WGSL
+        naga::front::wgsl::parse_str(&format!("@group(0) @binding(0) var t: {texture_type};"))
+            .unwrap();
+    }
+
+    #[test]
+    fn storage_texture_array_access() {
+        assert_eq!(
+            texture_type(
+                "@group(0) @binding(0) var t: texture_storage_2d_array<r32float, read_write>;"
+            ),
+            "texture_storage_2d_array<r32float, read_write>"
+        );
+        assert_eq!(
+            texture_type("@group(0) @binding(0) var t: texture_storage_3d<rgba16float, read>;"),
+            "texture_storage_3d<rgba16float, read>"
+        );
+    }
+
+    #[test]
+    fn sampled_texture_type() {
+        assert_eq!(
+            texture_type("@group(0) @binding(0) var t: texture_2d<f32>;"),
+            "texture_2d<f32>"
+        );
+    }
+}
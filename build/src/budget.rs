@@ -0,0 +1,66 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use regex::Regex;
+
+use crate::reflect;
+
+lazy_static::lazy_static! {
+    static ref DECLARATION_REGEX: Regex =
+        Regex::new(r"^(?:fn|struct|const|override|alias|var(?:<[^>]*>)?)\s+(\w+)").unwrap();
+}
+
+/// Splits a composed source into its top-level declarations, along with any attributes written before them. `naga`
+/// separates declarations with blank lines, which within functions are instead followed by indented lines.
+fn declarations(source: &str) -> Vec<&str> {
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    for (index, _) in source.match_indices("\n\n") {
+        let next = source[index + 2..].chars().next();
+        if next.is_some_and(|next| !next.is_whitespace()) {
+            declarations.push(&source[start..index + 2]);
+            start = index + 2;
+        }
+    }
+    declarations.push(&source[start..]);
+    declarations
+}
+
+/// Gives how many bytes of a composed source each module contributes, as `(module, bytes)` from the largest
+/// contribution. Declarations are attributed to the module that declared them by the `naga_oil` decoration of their
+/// names, and everything else to the shader itself.
+fn contributions(source: &str, shader: &str) -> Vec<(String, usize)> {
+    let mut contributions = BTreeMap::<String, usize>::new();
+    for declaration in declarations(source) {
+        let name = declaration
+            .lines()
+            .find(|line| !line.starts_with('@'))
+            .and_then(|line| DECLARATION_REGEX.captures(line))
+            .map(|captures| reflect::display_name(&captures[1]).into_owned());
+        let module = name
+            .as_deref()
+            .and_then(|name| name.rsplit_once("::"))
+            .map_or(shader, |(module, _)| module);
+        *contributions.entry(module.to_owned()).or_default() += declaration.len();
+    }
+
+    let mut contributions = contributions.into_iter().collect::<Vec<_>>();
+    contributions.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+    contributions
+}
+
+/// Checks that a composed source is within the `max_source_bytes` budget, otherwise giving an error listing how much
+/// each module contributes.
+pub(crate) fn check_source_budget(source: &str, shader: &str, budget: usize) -> Result<(), String> {
+    if source.len() <= budget {
+        return Ok(());
+    }
+
+    let mut message = format!(
+        "the composed shader is {} bytes, over the `max_source_bytes` budget of {budget} bytes:",
+        source.len()
+    );
+    for (module, bytes) in contributions(source, shader) {
+        write!(message, "\n    {bytes} bytes from `{module}`").unwrap();
+    }
+    Err(message)
+}
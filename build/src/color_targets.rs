@@ -0,0 +1,102 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{docs::DocComments, entry_points, reflect};
+
+/// Gives the blend state selected by a `blend` hint.
+fn blend_state(value: &str) -> Result<TokenStream, String> {
+    let state = match value {
+        "replace" => quote! { ::wgpu::BlendState::REPLACE },
+        "alpha" => quote! { ::wgpu::BlendState::ALPHA_BLENDING },
+        "premultiplied" => quote! { ::wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING },
+        "additive" => {
+            let component = quote! {
+                ::wgpu::BlendComponent {
+                    src_factor: ::wgpu::BlendFactor::One,
+                    dst_factor: ::wgpu::BlendFactor::One,
+                    operation: ::wgpu::BlendOperation::Add,
+                }
+            };
+            quote! {
+                ::wgpu::BlendState {
+                    color: #component,
+                    alpha: #component,
+                }
+            }
+        }
+        _ => {
+            return Err(format!(
+                "unknown blend hint `{value}`, expected one of `replace`, `alpha`, `premultiplied` or `additive`"
+            ))
+        }
+    };
+    Ok(state)
+}
+
+/// Generates a `color_targets` function for a fragment entry point, giving the color target states of a render
+/// pipeline from the formats of its outputs, with the blend states selected by `blend` hints. In debug builds the
+/// formats are checked against the types of the outputs.
+pub(crate) fn color_target_tokens(
+    module: &naga::Module,
+    docs: &DocComments,
+    entry_point: &naga::EntryPoint,
+) -> TokenStream {
+    let outputs = entry_points::fragment_outputs(module, docs, entry_point);
+    let output_count = outputs.len();
+    let target_count = outputs
+        .last()
+        .map_or(0, |output| output.location as usize + 1);
+
+    let mut targets = vec![quote! { None }; target_count];
+    let mut checks = Vec::new();
+    for (index, output) in outputs.iter().enumerate() {
+        let blend = match output.hints.iter().find(|(key, _)| key == "blend") {
+            Some((_, value)) => match blend_state(value) {
+                Ok(state) => quote! { Some(#state) },
+                Err(message) => quote! { compile_error!(#message) },
+            },
+            None => quote! { None },
+        };
+        targets[output.location as usize] = quote! {
+            Some(::wgpu::ColorTargetState {
+                format: formats[#index],
+                blend: #blend,
+                write_mask: ::wgpu::ColorWrites::ALL,
+            })
+        };
+
+        let scalar = match module.types[output.ty].inner {
+            naga::TypeInner::Scalar(scalar) | naga::TypeInner::Vector { scalar, .. } => scalar,
+            _ => continue,
+        };
+        let sample_type = match scalar.kind {
+            naga::ScalarKind::Float => quote! { ::wgpu::TextureSampleType::Float { .. } },
+            naga::ScalarKind::Sint => quote! { ::wgpu::TextureSampleType::Sint },
+            naga::ScalarKind::Uint => quote! { ::wgpu::TextureSampleType::Uint },
+            _ => continue,
+        };
+        let message = format!(
+            "the format of the color target at location {} doesn't match its output type, `{}`",
+            output.location,
+            reflect::type_name(module, output.ty)
+        );
+        checks.push(quote! {
+            debug_assert!(
+                matches!(formats[#index].sample_type(None, None), Some(#sample_type)),
+                #message
+            );
+        });
+    }
+
+    quote! {
+        /// The color target states of a render pipeline using the entry point, given the formats of its outputs in
+        /// order of location. Targets are indexed by location, with `None` for locations without an output, and
+        /// blended as selected by `//! wgsl-oil: blend=...` hints.
+        pub fn color_targets(
+            formats: [::wgpu::TextureFormat; #output_count],
+        ) -> [Option<::wgpu::ColorTargetState>; #target_count] {
+            #(#checks)*
+            [#(#targets),*]
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use quote::quote;
+
+/// Compresses a source with DEFLATE, as a byte string literal.
+fn deflate(source: &str) -> syn::LitByteStr {
+    let bytes = miniz_oxide::deflate::compress_to_vec(source.as_bytes(), 10);
+    syn::LitByteStr::new(&bytes, proc_macro2::Span::call_site())
+}
+
+/// Gives an expression which decompresses a source embedded compressed the first time it is evaluated, giving it as a
+/// `&'static str`.
+pub(crate) fn lazy_source(source: &str) -> proc_macro2::TokenStream {
+    let bytes = deflate(source);
+    quote! {
+        {
+            static SOURCE: ::std::sync::OnceLock<::std::string::String> = ::std::sync::OnceLock::new();
+            SOURCE.get_or_init(|| ::include_wgsl_oil_runtime::decompress_source(#bytes))
+        }
+    }
+}
+
+/// Replaces each `SOURCE` constant among `items` with a `SOURCE_DEFLATE` constant holding it compressed, under the same
+/// `cfg`, and adds a `decompressed_source` function decompressing it once.
+pub(crate) fn replace_source(items: &mut Vec<syn::Item>) {
+    let Some(index) = items.iter().position(
+        |item| matches!(item, syn::Item::Const(item_const) if item_const.ident == "SOURCE"),
+    ) else {
+        return;
+    };
+
+    let mut compressed = Vec::new();
+    items.retain(|item| {
+        let syn::Item::Const(item_const) = item else {
+            return true;
+        };
+        let syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(source),
+            ..
+        }) = &*item_const.expr
+        else {
+            return true;
+        };
+        if item_const.ident != "SOURCE" {
+            return true;
+        }
+
+        let bytes = deflate(&source.value());
+        let cfgs = item_const
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("cfg"));
+        compressed.push(syn::parse_quote! {
+            /// The composed shader, compressed with DEFLATE.
+            #(#cfgs)*
+            pub const SOURCE_DEFLATE: &[u8] = #bytes;
+        });
+        false
+    });
+    let decompressed = syn::parse_quote! {
+        /// The composed shader, decompressed from `SOURCE_DEFLATE` the first time it is used.
+        pub fn decompressed_source() -> &'static str {
+            static SOURCE: ::std::sync::OnceLock<::std::string::String> = ::std::sync::OnceLock::new();
+            SOURCE.get_or_init(|| ::include_wgsl_oil_runtime::decompress_source(SOURCE_DEFLATE))
+        }
+    };
+
+    items.splice(
+        index..index,
+        compressed.into_iter().chain(std::iter::once(decompressed)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use quote::ToTokens;
+
+    use crate::testing;
+
+    const SHADER: &str = "
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            var color = vec4(0.5);
+        #ifdef BRIGHT
+            color *= 2.0;
+        #endif
+        #ifdef FOG
+            color = mix(color, vec4(0.8), 0.25);
+        #endif
+            return color;
+        }
+    ";
+
+    /// Generates the items for [`SHADER`], permuted with `BRIGHT` and `FOG`.
+    fn items(compress_source: bool) -> Vec<syn::Item> {
+        testing::compose(SHADER, |shader| {
+            shader
+                .permutation("BRIGHT")
+                .permutation("FOG")
+                .compress_source(compress_source)
+        })
+        .unwrap_or_else(|errors| panic!("{}", errors.join("\n")))
+        .items()
+    }
+
+    /// Gives the source held by a string literal, or decompressed from a byte string literal.
+    fn source(tokens: proc_macro2::TokenStream) -> Option<String> {
+        tokens.into_iter().find_map(|tree| match tree {
+            proc_macro2::TokenTree::Group(group) => source(group.stream()),
+            proc_macro2::TokenTree::Literal(literal) => match syn::Lit::new(literal) {
+                syn::Lit::Str(source) => Some(source.value()),
+                syn::Lit::ByteStr(bytes) => {
+                    let bytes = miniz_oxide::inflate::decompress_to_vec(&bytes.value()).unwrap();
+                    Some(String::from_utf8(bytes).unwrap())
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Gives the composed source, from `SOURCE` or `SOURCE_DEFLATE`.
+    fn composed_source(items: &[syn::Item]) -> String {
+        items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Const(item)
+                    if item.ident == "SOURCE" || item.ident == "SOURCE_DEFLATE" =>
+                {
+                    source(item.expr.to_token_stream())
+                }
+                _ => None,
+            })
+            .expect("the source is generated")
+    }
+
+    /// Gives the source of each arm of `source_for`, by its pattern, with the empty permutation as the composed source.
+    fn permuted_sources(items: &[syn::Item]) -> BTreeMap<String, String> {
+        let source_for = items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Fn(item) if item.sig.ident == "source_for" => Some(item),
+                _ => None,
+            })
+            .expect("`source_for` is generated");
+        let Some(syn::Stmt::Expr(syn::Expr::Match(source_match), _)) =
+            source_for.block.stmts.first()
+        else {
+            panic!("`source_for` is a `match`");
+        };
+        source_match
+            .arms
+            .iter()
+            .filter(|arm| !matches!(arm.pat, syn::Pat::Wild(_)))
+            .map(|arm| {
+                let source =
+                    source(arm.body.to_token_stream()).unwrap_or_else(|| composed_source(items));
+                (arm.pat.to_token_stream().to_string(), source)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compressed_source_matches() {
+        let compressed = items(true);
+        let uncompressed = items(false);
+        assert!(compressed
+            .iter()
+            .any(|item| matches!(item, syn::Item::Const(item) if item.ident == "SOURCE_DEFLATE")));
+        assert_eq!(composed_source(&compressed), composed_source(&uncompressed));
+
+        let permutations = permuted_sources(&compressed);
+        assert_eq!(permutations.len(), 4);
+        assert_eq!(permutations, permuted_sources(&uncompressed));
+        assert_eq!(
+            permutations
+                .values()
+                .collect::<std::collections::HashSet<_>>()
+                .len(),
+            4
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{
+    reflect,
+    structs::Math,
+    values::{self, ValueWriter},
+};
+
+/// Generates a Rust constant for every `const` declared in the shader itself, rather than in the modules it imports,
+/// with vectors and matrices given as arrays. Constants whose type has no Rust equivalent, such as structs, are
+/// skipped, as are those with the same name as other generated items.
+pub(crate) fn constant_items(module: &naga::Module) -> Vec<syn::Item> {
+    let writer = ValueWriter {
+        module,
+        math: Math::Arrays,
+        layout: None,
+    };
+
+    module
+        .constants
+        .iter()
+        .filter_map(|(_, constant)| {
+            let name = constant.name.as_deref()?;
+            if reflect::display_name(name) != name {
+                return None;
+            }
+
+            let ident = reflect::item_ident(name);
+            let ty = values::rust_type(module, constant.ty).ok()?;
+            let value = writer.value(constant.init, constant.ty).ok()?;
+            Some(syn::parse_quote! {
+                pub const #ident: #ty = #value;
+            })
+        })
+        .collect()
+}
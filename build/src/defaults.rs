@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use quote::format_ident;
+
+use crate::{
+    structs::{self, HostLayout, Math},
+    values::ValueWriter,
+};
+
+/// Gives the name of the `const` holding the default value of a struct, e.g. `DEFAULT_LIGHT_PARAMS` for
+/// `LightParams`.
+fn default_const_name(struct_name: &str) -> String {
+    let mut name = String::from("DEFAULT_");
+    let mut previous_lowercase = false;
+    for c in struct_name.chars() {
+        if c.is_uppercase() && previous_lowercase {
+            name.push('_');
+        }
+        previous_lowercase = c.is_lowercase() || c.is_ascii_digit();
+        name.extend(c.to_uppercase());
+    }
+    name
+}
+
+/// Generates `impl Default` for every exported struct, using the default value declared in the shader as a `const` of
+/// the struct's type named `DEFAULT_<STRUCT NAME>`, e.g. `const DEFAULT_LIGHT_PARAMS: LightParams = LightParams(...);`,
+/// or otherwise the struct's zero value, as WGSL gives variables declared without an initializer. Zero values aren't
+/// generated for structs ending in runtime-sized arrays, or when `Default` is derived with `type_derives`.
+pub(crate) fn default_impls(
+    module: &naga::Module,
+    layout: &HostLayout,
+    exported: &HashSet<String>,
+    math: Math,
+    derives: &[syn::Path],
+) -> Result<Vec<syn::Item>, String> {
+    let derives_default = derives.iter().any(|derive| {
+        derive
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Default")
+    });
+    let writer = ValueWriter {
+        module,
+        math,
+        layout: Some(layout),
+    };
+
+    let mut items = Vec::new();
+    for (handle, ty) in module.types.iter() {
+        let Some(name) = ty.name.as_deref() else {
+            continue;
+        };
+        if !exported.contains(name) {
+            continue;
+        }
+
+        let const_name = default_const_name(name);
+        let value = match module
+            .constants
+            .iter()
+            .find(|(_, constant)| constant.name.as_deref() == Some(&const_name))
+        {
+            Some((_, constant)) => {
+                if constant.ty != handle {
+                    return Err(format!(
+                        "`{const_name}` should have the type `{name}` to be used as its default value"
+                    ));
+                }
+                writer
+                    .value(constant.init, handle)
+                    .map_err(|e| format!("could not generate a default for `{name}`: {e}"))?
+            }
+            None => {
+                if derives_default
+                    || !matches!(ty.inner, naga::TypeInner::Struct { .. })
+                    || structs::runtime_array(module, handle).is_some()
+                {
+                    continue;
+                }
+                match writer.zero_value(handle) {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                }
+            }
+        };
+        let ident = format_ident!("{}", name);
+        items.push(syn::parse_quote! {
+            impl ::core::default::Default for types::#ident {
+                fn default() -> Self {
+                    #value
+                }
+            }
+        });
+    }
+
+    Ok(items)
+}
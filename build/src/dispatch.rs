@@ -0,0 +1,68 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Gives the workgroup size of a compute entry point, or `None` if it is set by pipeline-overridable constants so
+/// isn't known until the pipeline is created.
+pub(crate) fn workgroup_size(entry_point: &naga::EntryPoint) -> Option<[u32; 3]> {
+    #[cfg(feature = "naga-24")]
+    if entry_point.workgroup_size_overrides.is_some() {
+        return None;
+    }
+    let size = entry_point.workgroup_size;
+    (entry_point.stage == naga::ShaderStage::Compute && !size.contains(&0)).then_some(size)
+}
+
+/// Generates the workgroup size of a compute entry point, along with a `WorkgroupCount` calculator and helpers for
+/// dispatching it indirectly over a number of elements.
+pub(crate) fn dispatch_tokens(entry_point: &naga::EntryPoint) -> Option<TokenStream> {
+    let [x, y, z] = workgroup_size(entry_point)?;
+
+    Some(quote! {
+        /// The workgroup size of the entry point, as declared with `@workgroup_size`.
+        pub const WORKGROUP_SIZE: [u32; 3] = [#x, #y, #z];
+        /// The workgroup size of the entry point along the `x` axis.
+        pub const WORKGROUP_SIZE_X: u32 = #x;
+        /// The workgroup size of the entry point along the `y` axis.
+        pub const WORKGROUP_SIZE_Y: u32 = #y;
+        /// The workgroup size of the entry point along the `z` axis.
+        pub const WORKGROUP_SIZE_Z: u32 = #z;
+
+        /// Calculates the number of workgroups to dispatch to cover a number of elements with the entry point's
+        /// workgroup size, rounding up.
+        #[derive(Clone, Copy, Debug)]
+        pub struct WorkgroupCount;
+
+        impl WorkgroupCount {
+            /// The workgroups covering `elements` invocations along the `x` axis.
+            pub const fn for_elements(elements: u32) -> [u32; 3] {
+                [elements.div_ceil(WORKGROUP_SIZE_X), 1, 1]
+            }
+
+            /// The workgroups covering a `width` by `height` grid of invocations.
+            pub const fn for_elements_2d(width: u32, height: u32) -> [u32; 3] {
+                [width.div_ceil(WORKGROUP_SIZE_X), height.div_ceil(WORKGROUP_SIZE_Y), 1]
+            }
+
+            /// The workgroups covering a `width` by `height` by `depth` grid of invocations.
+            pub const fn for_elements_3d(width: u32, height: u32, depth: u32) -> [u32; 3] {
+                [width.div_ceil(WORKGROUP_SIZE_X), height.div_ceil(WORKGROUP_SIZE_Y), depth.div_ceil(WORKGROUP_SIZE_Z)]
+            }
+        }
+
+        /// The arguments of an indirect dispatch covering `elements` invocations along the `x` axis, laid out as
+        /// `wgpu::ComputePass::dispatch_workgroups_indirect` reads them.
+        pub const fn dispatch_indirect_args(elements: u32) -> [u32; 3] {
+            WorkgroupCount::for_elements(elements)
+        }
+
+        /// The arguments of an indirect dispatch covering `elements` invocations, as bytes to be written to the
+        /// indirect buffer.
+        pub const fn dispatch_indirect_bytes(elements: u32) -> [u8; 12] {
+            let [x, y, z] = dispatch_indirect_args(elements);
+            let [x, y, z] = [x.to_le_bytes(), y.to_le_bytes(), z.to_le_bytes()];
+            [
+                x[0], x[1], x[2], x[3], y[0], y[1], y[2], y[3], z[0], z[1], z[2], z[3],
+            ]
+        }
+    })
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+lazy_static::lazy_static! {
+    static ref DOC_LINE_REGEX: Regex = Regex::new(r"^\s*///(?:[^/]|$) ?(.*)$").unwrap();
+    static ref DOC_BLOCK_START_REGEX: Regex = Regex::new(r"^\s*/\*\*([^*/].*|)$").unwrap();
+    static ref HINT_LINE_REGEX: Regex = Regex::new(r"^\s*//!\s*wgsl-oil\s*:(.*)$").unwrap();
+    static ref HINT_REGEX: Regex = Regex::new(r"^([A-Za-z_][\w-]*)\s*=\s*([^\s,=]+)$").unwrap();
+    static ref ATTRIBUTES_ONLY_REGEX: Regex = Regex::new(r"^\s*(?:@\w+(?:\([^)]*\))?\s*)+$").unwrap();
+    static ref DECLARATION_REGEX: Regex = Regex::new(
+        r"^\s*(?:@\w+(?:\([^)]*\))?\s*)*(struct|var(?:\s*<[^>]*>)?|fn|const|override|alias)\s+(\w+)"
+    )
+    .unwrap();
+    static ref MEMBER_REGEX: Regex = Regex::new(r"^\s*(?:@\w+(?:\([^)]*\))?\s*)*(\w+)\s*:").unwrap();
+}
+
+/// Codegen hints, as `key=value` pairs in the order written.
+pub(crate) type Hints = Vec<(String, String)>;
+
+/// The `///` and `/** */` comments written before declarations in a WGSL file, and before the members of its structs
+/// and the arguments of its functions, along with any `//! wgsl-oil: key=value` hints written in the same places.
+#[derive(Default)]
+pub(crate) struct DocComments {
+    /// Keyed by the name of the declaration.
+    items: HashMap<String, String>,
+    /// Keyed by the names of the struct or function and the member or argument.
+    members: HashMap<(String, String), String>,
+    /// Keyed by the name of the declaration.
+    item_hints: HashMap<String, Hints>,
+    /// Keyed by the names of the struct or function and the member or argument.
+    member_hints: HashMap<(String, String), Hints>,
+    /// Descriptions of malformed hints.
+    errors: Vec<String>,
+}
+
+/// Parses the `key=value` pairs of a hint line, separated by commas or whitespace.
+fn parse_hints(line: &str) -> Result<Hints, String> {
+    line.split([',', ' ', '\t'])
+        .filter(|hint| !hint.is_empty())
+        .map(|hint| {
+            let captures = HINT_REGEX.captures(hint).ok_or_else(|| {
+                format!("malformed `wgsl-oil` hint `{hint}`, expected `key=value`")
+            })?;
+            Ok((captures[1].to_owned(), captures[2].to_owned()))
+        })
+        .collect()
+}
+
+/// Strips the leading `*` which the lines of a `/** */` comment are usually written with, and the space after it.
+fn block_doc_line(line: &str) -> &str {
+    let line = line.trim_start();
+    match line.strip_prefix('*') {
+        Some(line) => line.strip_prefix(' ').unwrap_or(line),
+        None => line,
+    }
+    .trim_end()
+}
+
+/// The number of parentheses a line opens, less the number it closes.
+fn paren_depth(line: &str) -> i32 {
+    line.chars()
+        .map(|c| match c {
+            '(' => 1,
+            ')' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+impl DocComments {
+    pub(crate) fn parse(source: &str) -> Self {
+        let mut docs = Self::default();
+
+        let mut pending = Vec::<&str>::new();
+        let mut pending_hints = Hints::new();
+        let mut current_struct = None::<String>;
+        let mut current_function = None::<(String, i32)>;
+        let mut block = None::<Vec<&str>>;
+        for line in source.lines() {
+            // `/** */` comments are taken line by line, leaving out blank lines at their start and end
+            if let Some(lines) = &mut block {
+                match line.find("*/") {
+                    Some(end) => {
+                        lines.push(block_doc_line(&line[..end]));
+                        while lines.last().is_some_and(|line| line.is_empty()) {
+                            lines.pop();
+                        }
+                        pending.extend(lines.drain(..).skip_while(|line| line.is_empty()));
+                        block = None;
+                    }
+                    None => lines.push(block_doc_line(line)),
+                }
+                continue;
+            }
+            // Comments closed on the line they start on may be followed by what they document
+            let mut line = line;
+            if let Some(captures) = DOC_BLOCK_START_REGEX.captures(line) {
+                let rest = captures.get(1).unwrap().as_str();
+                let Some(end) = rest.find("*/") else {
+                    block = Some(vec![block_doc_line(rest)]);
+                    continue;
+                };
+                pending.push(rest[..end].trim());
+                line = &rest[end + 2..];
+                if line.trim().is_empty() {
+                    continue;
+                }
+            }
+            if let Some(captures) = DOC_LINE_REGEX.captures(line) {
+                pending.push(captures.get(1).unwrap().as_str());
+                continue;
+            }
+            if let Some(captures) = HINT_LINE_REGEX.captures(line) {
+                match parse_hints(captures.get(1).unwrap().as_str()) {
+                    Ok(hints) => pending_hints.extend(hints),
+                    Err(e) => docs.errors.push(e),
+                }
+                continue;
+            }
+
+            // Attributes may be written on their own lines, between the comment and the declaration
+            if ATTRIBUTES_ONLY_REGEX.is_match(line) {
+                continue;
+            }
+
+            let doc = (!pending.is_empty()).then(|| pending.join("\n"));
+            pending.clear();
+            let hints = std::mem::take(&mut pending_hints);
+
+            if let Some(struct_name) = &current_struct {
+                if line.contains('}') {
+                    current_struct = None;
+                } else if let Some(captures) = MEMBER_REGEX.captures(line) {
+                    let member = captures.get(1).unwrap().as_str();
+                    docs.add_member(struct_name, member, doc, hints);
+                }
+                continue;
+            }
+
+            // Function arguments written on their own lines are treated like struct members, until the argument
+            // list's parentheses are balanced
+            if let Some((function_name, depth)) = &mut current_function {
+                if let Some(captures) = MEMBER_REGEX.captures(line) {
+                    let argument = captures.get(1).unwrap().as_str();
+                    docs.add_member(function_name, argument, doc, hints);
+                }
+                *depth += paren_depth(line);
+                if *depth <= 0 {
+                    current_function = None;
+                }
+                continue;
+            }
+
+            let Some(captures) = DECLARATION_REGEX.captures(line) else {
+                continue;
+            };
+            let kind = captures.get(1).unwrap().as_str();
+            let name = captures.get(2).unwrap().as_str().to_owned();
+            if kind == "struct" && !line.contains('}') {
+                current_struct = Some(name.clone());
+            }
+            if kind == "fn" && paren_depth(line) > 0 {
+                current_function = Some((name.clone(), paren_depth(line)));
+            }
+            if !hints.is_empty() {
+                docs.item_hints.insert(name.clone(), hints);
+            }
+            if let Some(doc) = doc {
+                docs.items.insert(name, doc);
+            }
+        }
+
+        docs
+    }
+
+    fn add_member(&mut self, parent: &str, member: &str, doc: Option<String>, hints: Hints) {
+        let key = (parent.to_owned(), member.to_owned());
+        if !hints.is_empty() {
+            self.member_hints.insert(key.clone(), hints);
+        }
+        if let Some(doc) = doc {
+            self.members.insert(key, doc);
+        }
+    }
+
+    /// Takes the descriptions of any malformed hints.
+    pub(crate) fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub(crate) fn item_hints(&self, name: &str) -> &[(String, String)] {
+        self.item_hints.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn member_hints(&self, struct_name: &str, member: &str) -> &[(String, String)] {
+        self.member_hints
+            .get(&(struct_name.to_owned(), member.to_owned()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn item(&self, name: &str) -> Option<&str> {
+        self.items.get(name).map(String::as_str)
+    }
+
+    pub(crate) fn member(&self, struct_name: &str, member: &str) -> Option<&str> {
+        self.members
+            .get(&(struct_name.to_owned(), member.to_owned()))
+            .map(String::as_str)
+    }
+}
+
+/// Gives the `#[doc]` attributes for a comment, one per line.
+pub(crate) fn doc_attributes(doc: &str) -> Vec<syn::Attribute> {
+    doc.lines()
+        .map(|line| {
+            let line = format!(" {line}");
+            syn::parse_quote! { #[doc = #line] }
+        })
+        .collect()
+}
+
+/// Attaches the comments written in the shader to the generated structs, struct fields, constants, type aliases and the
+/// modules describing bindings, functions and entry points, recursing into generated submodules.
+pub(crate) fn attach(items: &mut [syn::Item], docs: &DocComments) {
+    for item in items {
+        match item {
+            syn::Item::Struct(item_struct) => {
+                let struct_name = item_struct.ident.to_string();
+                if let Some(doc) = docs.item(&struct_name) {
+                    item_struct.attrs.extend(doc_attributes(doc));
+                }
+                for field in item_struct.fields.iter_mut() {
+                    let Some(ident) = &field.ident else {
+                        continue;
+                    };
+                    if let Some(doc) = docs.member(&struct_name, &ident.to_string()) {
+                        field.attrs.extend(doc_attributes(doc));
+                    }
+                }
+            }
+            syn::Item::Const(item_const) => {
+                if let Some(doc) = docs.item(&item_const.ident.to_string()) {
+                    item_const.attrs.extend(doc_attributes(doc));
+                }
+            }
+            syn::Item::Type(item_type) => {
+                if let Some(doc) = docs.item(&item_type.ident.to_string()) {
+                    item_type.attrs.extend(doc_attributes(doc));
+                }
+            }
+            syn::Item::Mod(item_mod) => {
+                if let Some(doc) = docs.item(&item_mod.ident.to_string()) {
+                    item_mod.attrs.extend(doc_attributes(doc));
+                }
+                if let Some((_, items)) = &mut item_mod.content {
+                    attach(items, docs);
+                }
+            }
+            _ => {}
+        }
+    }
+}
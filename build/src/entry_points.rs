@@ -0,0 +1,274 @@
+use quote::{format_ident, quote};
+
+use crate::{bindings, color_targets, dispatch, docs::DocComments, reflect, vertex};
+
+/// Finds the resources used by an entry point, directly or through the functions it calls, as `(group, binding)`
+/// pairs ordered by group and then binding.
+pub(crate) fn used_bindings(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    index: usize,
+) -> Vec<(u32, u32)> {
+    let entry_point_info = info.get_entry_point(index);
+    bindings::bindings(module)
+        .into_iter()
+        .filter(|binding| !entry_point_info[binding.handle].is_empty())
+        .map(|binding| (binding.group, binding.binding))
+        .collect()
+}
+
+/// A `@location` output of a fragment entry point.
+pub(crate) struct FragmentOutput<'a> {
+    pub(crate) location: u32,
+    pub(crate) ty: naga::Handle<naga::Type>,
+    /// The hints written before the function for outputs returned directly, or before the member for outputs which
+    /// are members of a returned struct.
+    pub(crate) hints: &'a [(String, String)],
+}
+
+/// Finds the `@location` outputs of a fragment entry point, ordered by location.
+pub(crate) fn fragment_outputs<'a>(
+    module: &naga::Module,
+    docs: &'a DocComments,
+    entry_point: &naga::EntryPoint,
+) -> Vec<FragmentOutput<'a>> {
+    let Some(result) = &entry_point.function.result else {
+        return Vec::new();
+    };
+
+    let mut outputs = Vec::new();
+    if let Some(naga::Binding::Location { location, .. }) = result.binding {
+        outputs.push(FragmentOutput {
+            location,
+            ty: result.ty,
+            hints: docs.item_hints(&entry_point.name),
+        });
+    } else if let naga::TypeInner::Struct { members, .. } = &module.types[result.ty].inner {
+        let struct_name = module.types[result.ty].name.as_deref().unwrap_or_default();
+        for member in members {
+            if let (Some(naga::Binding::Location { location, .. }), Some(name)) =
+                (&member.binding, &member.name)
+            {
+                outputs.push(FragmentOutput {
+                    location: *location,
+                    ty: member.ty,
+                    hints: docs.member_hints(struct_name, name),
+                });
+            }
+        }
+    }
+    outputs.sort_by_key(|output| output.location);
+
+    outputs
+}
+
+/// Finds the hints written on the outputs of a fragment entry point, as `(location, key, value)`.
+fn output_hints<'a>(
+    module: &naga::Module,
+    docs: &'a DocComments,
+    entry_point: &naga::EntryPoint,
+) -> Vec<(u32, &'a str, &'a str)> {
+    fragment_outputs(module, docs, entry_point)
+        .into_iter()
+        .flat_map(|output| {
+            output
+                .hints
+                .iter()
+                .map(move |(key, value)| (output.location, key.as_str(), value.as_str()))
+        })
+        .collect()
+}
+
+/// Generates an `entry_points` module with an `EntryPoint` enum and a submodule for every entry point of the shader,
+/// giving its name and stage, the resources and bind groups that it uses, for fragment entry points the hints written
+/// on their outputs, with the `wgpu` feature its `ShaderStages`, the vertex buffer layouts of vertex entry points and
+/// color targets of fragment entry points, and dispatch helpers for compute entry points.
+pub(crate) fn entry_point_items(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    docs: &DocComments,
+) -> Vec<syn::Item> {
+    if module.entry_points.is_empty() {
+        return Vec::new();
+    }
+
+    let entry_point_modules = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(index, entry_point)| {
+            let ident = reflect::item_ident(&entry_point.name);
+            let used = used_bindings(module, info, index);
+            let mut groups = used.iter().map(|(group, _)| *group).collect::<Vec<_>>();
+            groups.dedup();
+            let resources = used
+                .iter()
+                .map(|(group, binding)| quote! { (#group, #binding) });
+            let hints = (entry_point.stage == naga::ShaderStage::Fragment).then(|| {
+                let hints = output_hints(module, docs, entry_point)
+                    .into_iter()
+                    .map(|(location, key, value)| quote! { (#location, #key, #value) });
+                quote! {
+                    /// The hints written on the entry point's outputs with `//! wgsl-oil: key=value` comments, as
+                    /// `(location, key, value)`.
+                    pub const OUTPUT_HINTS: &[(u32, &str, &str)] = &[#(#hints),*];
+                }
+            });
+
+            let vertex_buffers = (entry_point.stage == naga::ShaderStage::Vertex
+                && cfg!(feature = "wgpu"))
+            .then(|| vertex::vertex_buffer_tokens(module, docs, entry_point))
+            .flatten();
+            let color_targets = (entry_point.stage == naga::ShaderStage::Fragment
+                && cfg!(feature = "wgpu"))
+            .then(|| color_targets::color_target_tokens(module, docs, entry_point));
+            let dispatch = dispatch::dispatch_tokens(entry_point);
+            let name = &entry_point.name;
+            let stage = stage_ident(entry_point.stage);
+
+            let shader_stages = cfg!(feature = "wgpu").then(|| {
+                let stages = bindings::shader_stages_tokens(&[entry_point.stage]);
+                quote! {
+                    /// The stage of the entry point, for a bind group layout's `visibility`.
+                    pub const STAGES: ::wgpu::ShaderStages = #stages;
+                }
+            });
+
+            quote! {
+                pub mod #ident {
+                    /// The name of the entry point, as given when creating a pipeline.
+                    pub const NAME: &str = #name;
+                    /// The stage of the entry point.
+                    pub const STAGE: super::Stage = super::Stage::#stage;
+                    #shader_stages
+                    /// The `(group, binding)` pairs of the resources that the entry point uses, ordered by group and
+                    /// then binding.
+                    pub const RESOURCES: &[(u32, u32)] = &[#(#resources),*];
+                    /// The bind groups that the entry point uses, in ascending order.
+                    pub const GROUPS: &[u32] = &[#(#groups),*];
+                    #hints
+                    #vertex_buffers
+                    #color_targets
+                    #dispatch
+                }
+            }
+        });
+
+    let idents = module
+        .entry_points
+        .iter()
+        .map(|entry_point| reflect::item_ident(&entry_point.name))
+        .collect::<Vec<_>>();
+    let shader_stages = cfg!(feature = "wgpu").then(|| {
+        quote! {
+            /// The stage of the entry point, for a bind group layout's `visibility`.
+            pub const fn shader_stages(self) -> ::wgpu::ShaderStages {
+                match self {
+                    #(Self::#idents => #idents::STAGES),*
+                }
+            }
+        }
+    });
+
+    let mut items = vec![syn::parse_quote! {
+        /// The entry points of the shader, named after their functions.
+        pub mod entry_points {
+            /// The pipeline stages that entry points belong to.
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            pub enum Stage {
+                Vertex,
+                Fragment,
+                Compute,
+            }
+
+            /// The entry points of the shader, named after their functions.
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            pub enum EntryPoint {
+                #(#idents),*
+            }
+
+            impl EntryPoint {
+                /// Every entry point, in the order they are declared.
+                pub const ALL: &[Self] = &[#(Self::#idents),*];
+
+                /// The name of the entry point, as given when creating a pipeline.
+                pub const fn name(self) -> &'static str {
+                    match self {
+                        #(Self::#idents => #idents::NAME),*
+                    }
+                }
+
+                /// The stage of the entry point.
+                pub const fn stage(self) -> Stage {
+                    match self {
+                        #(Self::#idents => #idents::STAGE),*
+                    }
+                }
+
+                #shader_stages
+            }
+
+            #(#entry_point_modules)*
+        }
+    }];
+    items.extend(stage_items(module));
+    items
+}
+
+/// Gives the variant of the generated `Stage` enum for a stage.
+fn stage_ident(stage: naga::ShaderStage) -> syn::Ident {
+    match stage {
+        naga::ShaderStage::Vertex => format_ident!("Vertex"),
+        naga::ShaderStage::Fragment => format_ident!("Fragment"),
+        naga::ShaderStage::Compute => format_ident!("Compute"),
+    }
+}
+
+/// Generates a `vertex`, `fragment` and `compute` module for each stage with entry points, re-exporting their
+/// reflection. The reflection of a stage's only entry point is also re-exported directly from the stage's module, so
+/// that files with one entry point per stage can be used as `my_shader::vertex::VERTEX_BUFFERS`.
+fn stage_items(module: &naga::Module) -> Vec<syn::Item> {
+    [
+        (naga::ShaderStage::Vertex, "vertex"),
+        (naga::ShaderStage::Fragment, "fragment"),
+        (naga::ShaderStage::Compute, "compute"),
+    ]
+    .into_iter()
+    .filter_map(|(stage, stage_name)| {
+        let entry_points = module
+            .entry_points
+            .iter()
+            .filter(|entry_point| entry_point.stage == stage)
+            .collect::<Vec<_>>();
+        let names = entry_points.iter().map(|entry_point| &entry_point.name);
+        let idents = entry_points
+            .iter()
+            .map(|entry_point| reflect::item_ident(&entry_point.name))
+            .collect::<Vec<_>>();
+        let only = match idents.as_slice() {
+            [] => return None,
+            [ident] => {
+                let name = &entry_points[0].name;
+                Some(quote! {
+                    /// The name of the stage's only entry point.
+                    pub const ENTRY_POINT: &str = #name;
+                    pub use super::entry_points::#ident::*;
+                })
+            }
+            _ => None,
+        };
+
+        let doc = format!(" The {stage_name} entry points of the shader.");
+        let ident = format_ident!("{}", stage_name);
+        Some(syn::parse_quote! {
+            #[doc = #doc]
+            pub mod #ident {
+                /// The names of the stage's entry points, in the order they are declared.
+                pub const ENTRY_POINTS: &[&str] = &[#(#names),*];
+                pub use super::entry_points::{#(#idents),*};
+                #only
+            }
+        })
+    })
+    .collect()
+}
@@ -0,0 +1,527 @@
+use std::{collections::HashMap, ops::Range};
+
+use quote::format_ident;
+use regex::Regex;
+use syn::{parse::Parser, punctuated::Punctuated, visit_mut::VisitMut};
+
+use crate::{
+    reflect,
+    structs::Math,
+    values::{self, ValueWriter},
+};
+
+lazy_static::lazy_static! {
+    static ref DECLARATION_REGEX: Regex =
+        Regex::new(r"^(?:\s|@\s*[A-Za-z_]\w*(?:\s*\([^)]*\))?)*(struct|fn|const|alias)\s+([A-Za-z_]\w*)").unwrap();
+    static ref ALIAS_REGEX: Regex = Regex::new(r"(?m)^\s*alias\s+([A-Za-z_]\w*)\s*=\s*([^;]+?)\s*;").unwrap();
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[non_exhaustive]
+pub(crate) enum Export {
+    Struct { struct_name: String },
+    Function { function_name: String },
+    Constant { constant_name: String },
+    Alias { alias_name: String },
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Finds the byte ranges of every export attribute in the source, `@export` unless renamed with `export_directive`,
+/// including any parenthesised argument list, along with the text of those arguments. Comments are skipped, and the
+/// attribute name must match exactly, so `@exported` or `// @export` are left alone.
+fn export_attributes(source: &str, directive: &str) -> Vec<(Range<usize>, String)> {
+    let bytes = source.as_bytes();
+    let mut attributes = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // Block comments nest in WGSL
+                let mut depth = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        depth += 1;
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            b'@' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                let name_start = i;
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+                if &source[name_start..i] != directive {
+                    continue;
+                }
+
+                // Optional argument list, which may contain string literals
+                let mut arguments = String::new();
+                let mut end = i;
+                while end < bytes.len() && bytes[end].is_ascii_whitespace() {
+                    end += 1;
+                }
+                if bytes.get(end) == Some(&b'(') {
+                    let open = end;
+                    let mut close = bytes.len();
+                    let mut depth = 0;
+                    let mut in_string = false;
+                    while end < bytes.len() {
+                        match bytes[end] {
+                            b'"' => in_string = !in_string,
+                            b'(' if !in_string => depth += 1,
+                            b')' if !in_string => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    close = end;
+                                    end += 1;
+                                    break;
+                                }
+                            }
+                            _ => {}
+                        }
+                        end += 1;
+                    }
+                    arguments = source[open + 1..close].to_owned();
+                    i = end;
+                }
+
+                attributes.push((start..i, arguments));
+            }
+            byte if is_ident_byte(byte) => {
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    attributes
+}
+
+/// Removes export attributes, replacing them with an equivalent number of spaces so as to not disrupt spans.
+/// Attributes that aren't followed by a struct, function, constant or alias declaration are left in place for naga to
+/// report. Each export is returned with the unparsed text of its arguments. With no directive, exports are disabled
+/// and the source is left as it is.
+pub(crate) fn strip_exports(
+    source: &str,
+    directive: Option<&str>,
+) -> (String, HashMap<Export, String>) {
+    let mut exports = HashMap::new();
+    let Some(directive) = directive else {
+        return (source.to_owned(), exports);
+    };
+    let mut new_src = String::with_capacity(source.len());
+    let mut last = 0;
+
+    for (attribute, arguments) in export_attributes(source, directive) {
+        let Some(declaration) = DECLARATION_REGEX.captures(&source[attribute.end..]) else {
+            continue;
+        };
+
+        let name = declaration.get(2).unwrap().as_str().to_owned();
+        let export = match declaration.get(1).unwrap().as_str() {
+            "struct" => Export::Struct { struct_name: name },
+            "fn" => Export::Function {
+                function_name: name,
+            },
+            "alias" => Export::Alias { alias_name: name },
+            _ => Export::Constant {
+                constant_name: name,
+            },
+        };
+        exports.insert(export, arguments);
+
+        new_src.push_str(&source[last..attribute.start]);
+        for c in source[attribute.clone()].chars() {
+            match c {
+                '\n' => new_src.push('\n'),
+                c => new_src.push_str(&" ".repeat(c.len_utf8())),
+            }
+        }
+        last = attribute.end;
+    }
+    new_src.push_str(&source[last..]);
+
+    (new_src, exports)
+}
+
+/// Finds the type given to every `alias` declared in the source, by the name of the alias.
+pub(crate) fn alias_targets(source: &str) -> HashMap<String, String> {
+    ALIAS_REGEX
+        .captures_iter(source)
+        .map(|captures| (captures[1].to_owned(), captures[2].to_owned()))
+        .collect()
+}
+
+/// Gives the Rust type of an exported alias. Aliases of exported structs refer to the generated struct, and other
+/// types are reflected as they would be for constants, parsing the alias on its own as naga doesn't keep aliases which
+/// the shader doesn't use.
+fn alias_type(
+    module: &naga::Module,
+    exports: &HashMap<Export, ExportOptions>,
+    name: &str,
+    target: &str,
+) -> Result<proc_macro2::TokenStream, String> {
+    let aliases_struct = module.types.iter().any(|(_, ty)| {
+        ty.name.as_deref() == Some(target) && matches!(ty.inner, naga::TypeInner::Struct { .. })
+    });
+    if aliases_struct {
+        if !exports.contains_key(&Export::Struct {
+            struct_name: target.to_owned(),
+        }) {
+            return Err(format!(
+                "could not export `{name}`: the struct `{target}` it aliases must also be exported"
+            ));
+        }
+        let ident = reflect::item_ident(target);
+        return Ok(quote::quote! { super::types::#ident });
+    }
+
+    let alias = naga::front::wgsl::parse_str(&format!("alias {name} = {target};"))
+        .map_err(|e| format!("could not export `{name}`: {}", e.message()))?;
+    let (ty, _) = alias
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(name))
+        .ok_or_else(|| format!("could not export `{name}`: `{target}` is not a type"))?;
+    values::rust_type(&alias, ty).map_err(|e| format!("could not export `{name}`: {e}"))
+}
+
+/// Arguments given to an `@export` attribute, e.g. `@export(rename = "Particle", visibility = "pub(crate)")`, with
+/// `name` accepted in place of `rename`.
+#[derive(Default, Clone)]
+pub(crate) struct ExportOptions {
+    rename: Option<syn::Ident>,
+    visibility: Option<syn::Visibility>,
+}
+
+impl ExportOptions {
+    pub(crate) fn parse(arguments: &str) -> Result<Self, String> {
+        let mut options = Self::default();
+
+        let arguments = Punctuated::<syn::MetaNameValue, syn::Token![,]>::parse_terminated
+            .parse_str(arguments)
+            .map_err(|e| format!("invalid `@export` arguments `{arguments}`: {e}"))?;
+        for argument in arguments {
+            let key = argument
+                .path
+                .get_ident()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(value),
+                ..
+            }) = &argument.value
+            else {
+                return Err(format!("`@export` argument `{key}` must be a string"));
+            };
+
+            match key.as_str() {
+                // `name` is accepted as another spelling of `rename`
+                "rename" | "name" => {
+                    if options.rename.is_some() {
+                        return Err("`@export` was given both `rename` and `name`".to_owned());
+                    }
+                    let ident = syn::parse_str::<syn::Ident>(&value.value()).map_err(|_| {
+                        format!(
+                            "`@export` rename `{}` is not a valid Rust identifier",
+                            value.value()
+                        )
+                    })?;
+                    options.rename = Some(ident);
+                }
+                "visibility" => {
+                    let visibility =
+                        syn::parse_str::<syn::Visibility>(&value.value()).map_err(|_| {
+                            format!(
+                                "`@export` visibility `{}` is not a valid Rust visibility",
+                                value.value()
+                            )
+                        })?;
+                    options.visibility = Some(visibility);
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown `@export` argument `{key}`, expected `rename`, `name` or \
+                        `visibility`"
+                    ))
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn apply(&self, ident: &mut syn::Ident, vis: &mut syn::Visibility) {
+        if let Some(rename) = &self.rename {
+            *ident = rename.clone();
+        }
+        if let Some(visibility) = &self.visibility {
+            *vis = visibility.clone();
+        }
+    }
+}
+
+/// Renames exported structs, and any paths referring to them, and sets their visibility.
+struct StructOptions<'a> {
+    exports: &'a HashMap<Export, ExportOptions>,
+}
+
+impl StructOptions<'_> {
+    fn options(&self, ident: &syn::Ident) -> Option<&ExportOptions> {
+        self.exports.get(&Export::Struct {
+            struct_name: ident.to_string(),
+        })
+    }
+}
+
+impl VisitMut for StructOptions<'_> {
+    fn visit_item_struct_mut(&mut self, item: &mut syn::ItemStruct) {
+        if let Some(options) = self.options(&item.ident) {
+            options.apply(&mut item.ident, &mut item.vis);
+        }
+        syn::visit_mut::visit_item_struct_mut(self, item);
+    }
+
+    fn visit_item_mod_mut(&mut self, item: &mut syn::ItemMod) {
+        // The modules giving the offsets and strides of each struct's members are named after the struct
+        if item.ident == "offsets" || item.ident == "strides" {
+            for offsets in item.content.iter_mut().flat_map(|(_, items)| items) {
+                if let syn::Item::Mod(offsets) = offsets {
+                    if let Some(options) = self.options(&offsets.ident) {
+                        options.apply(&mut offsets.ident, &mut offsets.vis);
+                    }
+                }
+            }
+        }
+        syn::visit_mut::visit_item_mod_mut(self, item);
+    }
+
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        // Only sibling references and those through the `types` module, leaving paths into other crates alone
+        let len = path.segments.len();
+        if len == 1 || (len >= 2 && path.segments[len - 2].ident == "types") {
+            let last = &mut path.segments[len - 1];
+            if let Some(rename) = self.options(&last.ident).and_then(|o| o.rename.clone()) {
+                last.ident = rename;
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+/// Applies the `rename` and `visibility` arguments of each `@export` to the generated items. This runs after doc
+/// comments have been attached, as those are looked up by their name in the shader.
+pub(crate) fn apply_options(items: &mut [syn::Item], exports: &HashMap<Export, ExportOptions>) {
+    for item in items.iter_mut() {
+        StructOptions { exports }.visit_item_mut(item);
+
+        let syn::Item::Mod(item_mod) = item else {
+            continue;
+        };
+        if item_mod.ident != "exports" {
+            continue;
+        }
+        let Some((_, exported)) = &mut item_mod.content else {
+            continue;
+        };
+        for exported in exported {
+            match exported {
+                syn::Item::Const(item_const) => {
+                    let export = Export::Constant {
+                        constant_name: item_const.ident.to_string(),
+                    };
+                    if let Some(options) = exports.get(&export) {
+                        options.apply(&mut item_const.ident, &mut item_const.vis);
+                    }
+                }
+                syn::Item::Mod(item_mod) => {
+                    let export = Export::Function {
+                        function_name: item_mod.ident.to_string(),
+                    };
+                    if let Some(options) = exports.get(&export) {
+                        options.apply(&mut item_mod.ident, &mut item_mod.vis);
+                    }
+                }
+                syn::Item::Type(item_type) => {
+                    let export = Export::Alias {
+                        alias_name: item_type.ident.to_string(),
+                    };
+                    if let Some(options) = exports.get(&export) {
+                        options.apply(&mut item_type.ident, &mut item_type.vis);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Generates an `exports` module reflecting the functions, constants and aliases marked with `@export`. Constants are
+/// given as values, aliases as Rust type aliases, and functions as their name in the composed source along with their
+/// signature.
+pub(crate) fn export_items(
+    module: &naga::Module,
+    exports: &HashMap<Export, ExportOptions>,
+    alias_targets: &HashMap<String, String>,
+) -> Result<Vec<syn::Item>, String> {
+    let writer = ValueWriter {
+        module,
+        math: Math::Arrays,
+        layout: None,
+    };
+
+    let mut constants = module
+        .constants
+        .iter()
+        .filter_map(|(_, constant)| {
+            let name = constant.name.as_ref()?;
+            exports
+                .contains_key(&Export::Constant {
+                    constant_name: name.clone(),
+                })
+                .then_some((name, constant))
+        })
+        .collect::<Vec<_>>();
+    constants.sort_by_key(|(name, _)| *name);
+
+    let mut functions = module
+        .functions
+        .iter()
+        .filter_map(|(_, function)| {
+            let name = function.name.as_ref()?;
+            exports
+                .contains_key(&Export::Function {
+                    function_name: name.clone(),
+                })
+                .then_some((name, function))
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by_key(|(name, _)| *name);
+
+    let mut aliases = exports
+        .keys()
+        .filter_map(|export| match export {
+            Export::Alias { alias_name } => Some(alias_name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    aliases.sort();
+
+    if constants.is_empty() && functions.is_empty() && aliases.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut items = Vec::<syn::Item>::new();
+    for (name, constant) in constants {
+        let ident = format_ident!("{}", name);
+        let ty = values::rust_type(module, constant.ty)
+            .map_err(|e| format!("could not export `{name}`: {e}"))?;
+        let value = writer
+            .value(constant.init, constant.ty)
+            .map_err(|e| format!("could not export `{name}`: {e}"))?;
+        items.push(syn::parse_quote! {
+            pub const #ident: #ty = #value;
+        });
+    }
+
+    for name in aliases {
+        let ident = format_ident!("{}", name);
+        let target = alias_targets
+            .get(name)
+            .ok_or_else(|| format!("could not export `{name}`: its declaration wasn't found"))?;
+        let ty = alias_type(module, exports, name, target)?;
+        items.push(syn::parse_quote! {
+            pub type #ident = #ty;
+        });
+    }
+
+    let mut signatures = Vec::new();
+    for (name, function) in functions {
+        let ident = format_ident!("{}", name);
+        let argument_types = function
+            .arguments
+            .iter()
+            .map(|argument| {
+                (
+                    argument.name.as_deref().unwrap_or("_"),
+                    reflect::type_name(module, argument.ty),
+                )
+            })
+            .collect::<Vec<_>>();
+        let result_type = function
+            .result
+            .as_ref()
+            .map(|result| reflect::type_name(module, result.ty));
+
+        let mut signature = format!(
+            "fn {name}({})",
+            argument_types
+                .iter()
+                .map(|(name, ty)| format!("{name}: {ty}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if let Some(ty) = &result_type {
+            signature += &format!(" -> {ty}");
+        }
+
+        let arguments = argument_types
+            .iter()
+            .map(|(name, ty)| quote::quote! { (#name, #ty) });
+        let result = match &result_type {
+            Some(ty) => quote::quote! { Some(#ty) },
+            None => quote::quote! { None },
+        };
+        items.push(syn::parse_quote! {
+            pub mod #ident {
+                /// The name of the function in the composed source.
+                pub const NAME: &str = #name;
+                /// The name and WGSL type of each argument.
+                pub const ARGUMENTS: &[(&str, &str)] = &[#(#arguments),*];
+                /// The WGSL type returned, if any.
+                pub const RESULT: Option<&str> = #result;
+                /// The signature of the function as it would be declared in WGSL.
+                pub const SIGNATURE: &str = #signature;
+            }
+        });
+        signatures.push(quote::quote! { (#name, #signature) });
+    }
+    if !signatures.is_empty() {
+        items.push(syn::parse_quote! {
+            /// The name and signature of every exported function, sorted by name, e.g. to check that the API of a
+            /// shader library stays stable.
+            pub const FUNCTIONS: &[(&str, &str)] = &[#(#signatures),*];
+        });
+    }
+
+    Ok(vec![syn::parse_quote! {
+        /// Functions, constants and aliases marked with `@export` in the shader.
+        pub mod exports {
+            #(#items)*
+        }
+    }])
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+
+use quote::{format_ident, quote};
+
+use crate::{bindings, input::WGSL_BASELINES, reflect};
+
+/// The `wgpu` feature enabling each validator capability that the shader may require, by name.
+const CAPABILITY_FEATURES: &[(&str, &str)] = &[
+    ("PUSH_CONSTANT", "PUSH_CONSTANTS"),
+    ("FLOAT64", "SHADER_F64"),
+    ("PRIMITIVE_INDEX", "SHADER_PRIMITIVE_INDEX"),
+    ("EARLY_DEPTH_TEST", "SHADER_EARLY_DEPTH_TEST"),
+    ("DUAL_SOURCE_BLENDING", "DUAL_SOURCE_BLENDING"),
+    (
+        "STORAGE_TEXTURE_16BIT_NORM_FORMATS",
+        "TEXTURE_FORMAT_16BIT_NORM",
+    ),
+    ("SHADER_FLOAT32_ATOMIC", "SHADER_FLOAT32_ATOMIC"),
+    ("TEXTURE_ATOMIC", "TEXTURE_ATOMIC"),
+    ("TEXTURE_INT64_ATOMIC", "TEXTURE_INT64_ATOMIC"),
+    ("MULTIVIEW", "MULTIVIEW"),
+    ("SHADER_INT64", "SHADER_INT64"),
+    ("SHADER_INT64_ATOMIC_MIN_MAX", "SHADER_INT64_ATOMIC_MIN_MAX"),
+    ("SHADER_INT64_ATOMIC_ALL_OPS", "SHADER_INT64_ATOMIC_ALL_OPS"),
+    ("RAY_QUERY", "EXPERIMENTAL_RAY_QUERY"),
+    (
+        "RAY_QUERY",
+        "EXPERIMENTAL_RAY_TRACING_ACCELERATION_STRUCTURE",
+    ),
+    ("SUBGROUP", "SUBGROUP"),
+    ("SUBGROUP_BARRIER", "SUBGROUP_BARRIER"),
+    ("SUBGROUP_VERTEX_STAGE", "SUBGROUP_VERTEX"),
+    (
+        "SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING",
+        "SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING",
+    ),
+    (
+        "UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING",
+        "UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING",
+    ),
+    (
+        "SAMPLER_NON_UNIFORM_INDEXING",
+        "SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING",
+    ),
+];
+
+/// Finds the features needed to bind the binding arrays of the module, which don't need any validator capabilities.
+fn binding_array_features(module: &naga::Module, features: &mut BTreeSet<&'static str>) {
+    for binding in bindings::bindings(module) {
+        if binding.array_size(module).is_none() {
+            continue;
+        }
+
+        match binding.global.space {
+            naga::AddressSpace::Uniform => {
+                features.insert("BUFFER_BINDING_ARRAY");
+            }
+            naga::AddressSpace::Storage { .. } => {
+                features.insert("BUFFER_BINDING_ARRAY");
+                features.insert("STORAGE_RESOURCE_BINDING_ARRAY");
+            }
+            _ => {
+                features.insert("TEXTURE_BINDING_ARRAY");
+                if let naga::TypeInner::Image {
+                    class: naga::ImageClass::Storage { .. },
+                    ..
+                } = module.types[binding.resource_ty(module)].inner
+                {
+                    features.insert("STORAGE_RESOURCE_BINDING_ARRAY");
+                }
+            }
+        }
+    }
+}
+
+/// Finds the `wgpu` features that a device needs to create pipelines from the module, by name, e.g.
+/// `TEXTURE_BINDING_ARRAY`, given the validator capabilities that the module requires.
+pub(crate) fn required_features(
+    module: &naga::Module,
+    capabilities: &[&str],
+) -> BTreeSet<&'static str> {
+    let mut features = BTreeSet::new();
+
+    for capability in capabilities {
+        features.extend(
+            CAPABILITY_FEATURES
+                .iter()
+                .filter(|(required, _)| required == capability)
+                .map(|(_, feature)| *feature),
+        );
+    }
+    binding_array_features(module, &mut features);
+
+    // Half precision floats need no validator capability
+    if reflect::uses_scalar(module, naga::ScalarKind::Float, 2) {
+        features.insert("SHADER_F16");
+    }
+
+    features
+}
+
+/// Describes everything the module uses which is newer than the given WGSL baseline: validator capabilities beyond
+/// those of the baseline, and functionality needing optional features such as binding arrays and `f16`.
+pub(crate) fn baseline_violations(module: &naga::Module, baseline: &str) -> Vec<String> {
+    let allowed = WGSL_BASELINES
+        .iter()
+        .find(|(name, _)| *name == baseline)
+        .map_or(&[][..], |(_, capabilities)| *capabilities);
+
+    let mut violations = reflect::required_capabilities(module)
+        .into_iter()
+        .filter(|capability| !allowed.contains(capability))
+        .map(|capability| format!("the `{capability}` capability"))
+        .collect::<Vec<_>>();
+    violations.extend(
+        required_features(module, &[])
+            .into_iter()
+            .map(|feature| format!("the `{feature}` feature")),
+    );
+    violations
+}
+
+/// Generates constants describing the optional functionality used by the shader, and with the `wgpu` feature a
+/// `REQUIRED_FEATURES` constant with the `wgpu` features needed by the shader.
+pub(crate) fn feature_items(module: &naga::Module) -> Vec<syn::Item> {
+    let uses_multiview = module.entry_points.iter().any(|entry_point| {
+        reflect::input_built_ins(module, entry_point).contains(&naga::BuiltIn::ViewIndex)
+    });
+    let capabilities = reflect::required_capabilities(module);
+    let uses_subgroups = capabilities
+        .iter()
+        .any(|capability| capability.starts_with("SUBGROUP"));
+    let uses_ray_queries = capabilities.contains(&"RAY_QUERY");
+
+    let mut items: Vec<syn::Item> = vec![
+        syn::parse_quote! {
+            /// Whether the shader reads `@builtin(view_index)`, so must be used in multiview render passes.
+            pub const USES_MULTIVIEW: bool = #uses_multiview;
+        },
+        syn::parse_quote! {
+            /// Whether the shader uses subgroup operations or barriers.
+            pub const USES_SUBGROUPS: bool = #uses_subgroups;
+        },
+        syn::parse_quote! {
+            /// Whether the shader uses ray queries or acceleration structures.
+            pub const USES_RAY_QUERIES: bool = #uses_ray_queries;
+        },
+    ];
+    if !cfg!(feature = "wgpu") {
+        return items;
+    }
+
+    let features = required_features(module, &capabilities)
+        .into_iter()
+        .map(|feature| {
+            let feature = format_ident!("{}", feature);
+            quote! { .union(::wgpu::Features::#feature) }
+        });
+
+    items.push(syn::parse_quote! {
+        /// The `wgpu` features that a device needs to create pipelines from this shader.
+        pub const REQUIRED_FEATURES: ::wgpu::Features = ::wgpu::Features::empty() #(#features)*;
+    });
+
+    items
+}
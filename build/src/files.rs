@@ -1,4 +1,36 @@
-use std::{ffi::OsStr, ops::Deref, path::PathBuf};
+use std::{
+    env,
+    ffi::OsStr,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// Checks that a shader file is within the directory of the crate being compiled, after resolving `..` and symlinks,
+/// unless `allow_external_paths` was given.
+pub(crate) fn check_within_crate(path: &Path, allow_external_paths: bool) -> Result<(), String> {
+    if allow_external_paths {
+        return Ok(());
+    }
+
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+        "`CARGO_MANIFEST_DIR` is not set - pass `allow_external_paths = true` to use shaders outside of cargo"
+            .to_owned()
+    })?);
+    let root = root.canonicalize().unwrap_or(root);
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("could not resolve `{}`: {e}", path.display()))?;
+    if path.starts_with(&root) {
+        return Ok(());
+    }
+
+    Err(format!(
+        "`{}` is outside of the crate directory `{}` - pass `allow_external_paths = true` to use shaders from \
+        outside of the crate, such as from sibling checkouts",
+        path.display(),
+        root.display()
+    ))
+}
 
 /// A PathBuf that is absolute, exists and points to a folder that is the root of a Rust module/test/example/executable.
 pub(crate) struct AbsoluteRustRootPathBuf {
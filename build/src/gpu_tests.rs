@@ -0,0 +1,96 @@
+use quote::{format_ident, quote};
+
+/// Generates a `#[cfg(test)]` module which builds the shader on a headless `wgpu` device, so that driver-level shader
+/// failures are caught by `cargo test`. Requires the invoking crate to have `wgpu` and `pollster` as dev-dependencies.
+///
+/// Compute entry points are also built into pipelines, using the layout that `wgpu` derives from the shader. Render
+/// pipelines need targets that can't be inferred from the shader alone, so are not tested.
+///
+/// Blocking on a device isn't possible on the web, so the tests are not generated for `wasm32` targets.
+pub(crate) fn gpu_test_items(module: &naga::Module, label: &str) -> Vec<syn::Item> {
+    let compute_tests = module
+        .entry_points
+        .iter()
+        .filter(|entry_point| entry_point.stage == naga::ShaderStage::Compute)
+        .map(|entry_point| {
+            let test_name = format_ident!("compute_pipeline_{}", entry_point.name);
+            let entry_point_name = &entry_point.name;
+            let pipeline_label = format!("{label}::{entry_point_name}");
+
+            quote! {
+                #[test]
+                fn #test_name() {
+                    let Some(device) = device() else {
+                        return;
+                    };
+                    let module = shader_module(&device);
+
+                    device.push_error_scope(::wgpu::ErrorFilter::Validation);
+                    let _pipeline = device.create_compute_pipeline(&::wgpu::ComputePipelineDescriptor {
+                        label: Some(#pipeline_label),
+                        layout: None,
+                        module: &module,
+                        entry_point: Some(#entry_point_name),
+                        compilation_options: ::core::default::Default::default(),
+                        cache: None,
+                    });
+                    if let Some(error) = ::pollster::block_on(device.pop_error_scope()) {
+                        panic!("failed to create compute pipeline `{}`: {}", #entry_point_name, error);
+                    }
+                }
+            }
+        });
+
+    vec![syn::parse_quote! {
+        #[cfg(all(test, not(target_arch = "wasm32")))]
+        mod gpu_tests {
+            /// Creates a headless device, or gives `None` if no adapter is available on this machine.
+            fn device() -> Option<::wgpu::Device> {
+                let instance = ::wgpu::Instance::default();
+                let adapter = ::pollster::block_on(
+                    instance.request_adapter(&::wgpu::RequestAdapterOptions::default()),
+                );
+                let Some(adapter) = adapter else {
+                    eprintln!("skipping gpu test for `{}`: no adapter available", #label);
+                    return None;
+                };
+
+                let (device, _queue) = ::pollster::block_on(adapter.request_device(
+                    &::wgpu::DeviceDescriptor {
+                        label: Some(#label),
+                        required_features: adapter.features(),
+                        required_limits: adapter.limits(),
+                        ..::core::default::Default::default()
+                    },
+                    None,
+                ))
+                .expect("adapter should provide a device with its own features and limits");
+
+                Some(device)
+            }
+
+            fn shader_module(device: &::wgpu::Device) -> ::wgpu::ShaderModule {
+                device.push_error_scope(::wgpu::ErrorFilter::Validation);
+                let module = device.create_shader_module(::wgpu::ShaderModuleDescriptor {
+                    label: Some(#label),
+                    source: ::wgpu::ShaderSource::Wgsl(super::source()),
+                });
+                if let Some(error) = ::pollster::block_on(device.pop_error_scope()) {
+                    panic!("failed to create shader module `{}`: {}", #label, error);
+                }
+
+                module
+            }
+
+            #[test]
+            fn shader_module_compiles() {
+                let Some(device) = device() else {
+                    return;
+                };
+                shader_module(&device);
+            }
+
+            #( #compute_tests )*
+        }
+    }]
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+    fs,
+};
+
+use crate::{imports::ImportOrder, includes::Include, summary::display_path};
+
+/// Escapes a label for a quoted DOT string.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Formats the graph of files that a shader imports as Graphviz DOT. Files imported by path, relative to the importing
+/// file or the crate's source root, are drawn as boxes with solid edges, and modules imported by name, from `includes`
+/// or the built-in library, as ellipses with dashed edges, labelled with their name and file. The shader itself is
+/// drawn in bold.
+pub(crate) fn import_graph(
+    import_order: &ImportOrder,
+    includes: &HashMap<String, Include>,
+) -> String {
+    // Nodes are keyed by their label, so that they are numbered in a stable order
+    let root = import_order.root().path();
+    let root_name = display_path(&root);
+    let mut files = BTreeMap::from([(root_name.clone(), root)]);
+    let mut file_edges = BTreeSet::new();
+    for (importer, imported) in import_order.edges() {
+        let (importer, imported) = (importer.path(), imported.path());
+        file_edges.insert((display_path(&importer), display_path(&imported)));
+        files.insert(display_path(&importer), importer);
+        files.insert(display_path(&imported), imported);
+    }
+
+    // Includes can be required by any file, and by other includes
+    let mut modules = files
+        .iter()
+        .filter_map(|(file, path)| {
+            let source = fs::read_to_string(&**path).ok()?;
+            let (_, reqs, _) = naga_oil::compose::get_preprocessor_data(&source);
+            Some((
+                file.clone(),
+                reqs.into_iter().map(|req| req.import).collect(),
+            ))
+        })
+        .collect::<Vec<(String, Vec<String>)>>();
+    let mut used = BTreeMap::new();
+    let mut include_edges = BTreeSet::new();
+    while let Some((importer, reqs)) = modules.pop() {
+        for req in reqs {
+            let Some((subreqs, path, _)) = includes.get(&req) else {
+                continue;
+            };
+            include_edges.insert((importer.clone(), req.clone()));
+            if used.insert(req.clone(), display_path(path)).is_none() {
+                modules.push((req, subreqs.clone()));
+            }
+        }
+    }
+
+    let mut ids = HashMap::new();
+    let mut out = String::from("digraph imports {\n    node [shape=box];\n");
+    for file in files.keys() {
+        let id = format!("n{}", ids.len());
+        let style = if *file == root_name {
+            ", style=bold"
+        } else {
+            ""
+        };
+        writeln!(out, "    {id} [label=\"{}\"{style}];", escape(file)).unwrap();
+        ids.insert(file.clone(), id);
+    }
+    for (name, path) in &used {
+        let id = format!("n{}", ids.len());
+        writeln!(
+            out,
+            "    {id} [label=\"{}\\n{}\", shape=ellipse];",
+            escape(name),
+            escape(path)
+        )
+        .unwrap();
+        ids.insert(name.clone(), id);
+    }
+    for (importer, imported) in &file_edges {
+        writeln!(out, "    {} -> {};", ids[importer], ids[imported]).unwrap();
+    }
+    for (importer, imported) in &include_edges {
+        writeln!(
+            out,
+            "    {} -> {} [style=dashed];",
+            ids[importer], ids[imported]
+        )
+        .unwrap();
+    }
+    out + "}\n"
+}
@@ -0,0 +1,70 @@
+use quote::quote;
+
+use crate::{
+    mode,
+    source::{ModuleFile, Sourcecode},
+};
+
+fn shader_file(file: &ModuleFile) -> proc_macro2::TokenStream {
+    let name = &file.name;
+    let path = file.path.to_string_lossy();
+    let (requests, names): (Vec<_>, Vec<_>) = file.imports.iter().cloned().unzip();
+    let source = match &file.source {
+        Some(source) => quote! { Some(#source) },
+        None => quote! { None },
+    };
+    quote! {
+        ::include_wgsl_oil_runtime::ShaderFile {
+            name: #name,
+            path: #path,
+            imports: &[#((#requests, #names)),*],
+            source: #source,
+        }
+    }
+}
+
+/// Generates, with the `hot-reload` feature, a `load_source` function which in debug builds reads the shader and the
+/// files it imports from disk again and recomposes them with `include-wgsl-oil-runtime`, and in release builds gives
+/// the composed shader, which `default` evaluates to.
+pub(crate) fn load_source_items(
+    source: &Sourcecode,
+    default: proc_macro2::TokenStream,
+) -> Vec<syn::Item> {
+    if !cfg!(feature = "hot-reload") {
+        return Vec::new();
+    }
+    let Some((root, modules)) = source.module_files().split_last() else {
+        return Vec::new();
+    };
+
+    let root = shader_file(root);
+    let modules = modules.iter().map(shader_file);
+    let export_directive = match source.options().export_directive() {
+        Some(directive) => quote! { Some(#directive) },
+        None => quote! { None },
+    };
+    let (names, values): (Vec<_>, Vec<_>) = source
+        .default_shader_defs()
+        .into_iter()
+        .map(|(name, value)| (name, mode::shader_def_value(value)))
+        .unzip();
+
+    vec![syn::parse_quote! {
+        /// In debug builds, reads the shader and the files it imports from disk again and recomposes them, so that
+        /// edits are picked up without recompiling, giving `SOURCE` and printing the error if that fails. In release
+        /// builds, gives `SOURCE`.
+        pub fn load_source() -> ::std::borrow::Cow<'static, str> {
+            if cfg!(debug_assertions) {
+                const FILES: ::include_wgsl_oil_runtime::ShaderFiles = ::include_wgsl_oil_runtime::ShaderFiles {
+                    modules: &[#(#modules),*],
+                    root: #root,
+                    export_directive: #export_directive,
+                    shader_defs: &[#((#names, #values)),*],
+                };
+                ::include_wgsl_oil_runtime::load_source(&FILES, #default)
+            } else {
+                ::std::borrow::Cow::Borrowed(#default)
+            }
+        }
+    }]
+}
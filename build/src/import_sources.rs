@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+
+use quote::quote;
+
+/// Gives the identifier of the module holding an import's source, from the name it is imported as, e.g. `wgsl_oil_color`
+/// for `wgsl_oil::color`, or `shaders_utils_wgsl` for `"shaders/utils.wgsl"`.
+fn module_ident(name: &str, taken: &mut HashSet<String>) -> syn::Ident {
+    let mut ident = name
+        .trim_matches('"')
+        .split("::")
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+
+    // Names which only differ in punctuation are numbered
+    let base = ident.clone();
+    let mut i = 1;
+    while !taken.insert(ident.clone()) {
+        ident = format!("{base}_{i}");
+        i += 1;
+    }
+    syn::parse_str::<syn::Ident>(&ident)
+        .unwrap_or_else(|_| syn::Ident::new_raw(&ident, proc_macro2::Span::call_site()))
+}
+
+/// Generates an `imports` module with a submodule for every module that the shader imports, by path or from
+/// `includes`, giving the name it is added to the composer as and its source, after `@export` directives are removed
+/// and imports by path are renamed. `MODULES` lists them in the order they need to be added to a composer.
+pub(crate) fn import_source_items(module_sources: &[(String, String)]) -> Vec<syn::Item> {
+    let mut taken = HashSet::new();
+    let idents = module_sources
+        .iter()
+        .map(|(name, _)| module_ident(name, &mut taken))
+        .collect::<Vec<_>>();
+    let modules = module_sources
+        .iter()
+        .zip(&idents)
+        .map(|((name, source), ident)| {
+            quote! {
+                pub mod #ident {
+                    /// The name that the module is added to the composer as, and imported by.
+                    pub const NAME: &str = #name;
+                    /// The module's source, as added to the composer.
+                    pub const SOURCE: &str = #source;
+                }
+            }
+        });
+
+    vec![syn::parse_quote! {
+        /// The modules that the shader imports, as they were added to the composer, so that the shader can be
+        /// recomposed without access to the files it was composed from.
+        pub mod imports {
+            /// The name and source of every module, in the order that they need to be added to a composer.
+            pub const MODULES: &[(&str, &str)] = &[#((#idents::NAME, #idents::SOURCE)),*];
+            #(#modules)*
+        }
+    }]
+}
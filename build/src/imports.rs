@@ -86,6 +86,27 @@ pub(crate) fn replace_imports_in_source(
     })
 }
 
+/// Gives the name that each import by path in a source is renamed to, as `(requested path, name)` pairs sorted by path.
+pub(crate) fn import_renames(
+    source: &str,
+    importing: &Module,
+    source_root: Option<&AbsoluteRustRootPathBuf>,
+    module_names: &HashMap<Module, String>,
+) -> Vec<(String, String)> {
+    let mut renames = all_imports_in_source(source)
+        .into_iter()
+        .filter_map(|request_string| {
+            let import = Module::resolve_module(importing, source_root, request_string).ok()?;
+            Some((
+                request_string.to_owned(),
+                module_names.get(&import)?.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
+    renames.sort();
+    renames
+}
+
 pub(crate) enum ImportResolutionError {
     Cycle {
         cycle_path: Vec<Module>,
@@ -275,6 +296,44 @@ impl ImportOrder {
         forwards
     }
 
+    /// Gives the files imported by the node of interest as a tree, depth first with the imports of each file sorted by
+    /// path, as `(depth, path)` pairs starting at a depth of 0. Files imported from several places appear under each.
+    pub(crate) fn import_tree(&self) -> Vec<(usize, AbsoluteWGSLFilePathBuf)> {
+        let mut tree = Vec::new();
+        let mut stack = vec![(0, self.node_of_interest)];
+        while let Some((depth, node)) = stack.pop() {
+            if node != self.node_of_interest {
+                tree.push((depth - 1, self.dag[node].path()));
+            }
+
+            let mut children = self
+                .dag
+                .children(node)
+                .iter(&self.dag)
+                .map(|(_, child)| child)
+                .collect::<Vec<_>>();
+            // Files reached through several importers have their own imports recorded once for each
+            children.sort_by_key(|child| std::cmp::Reverse(self.dag[*child].path().to_path_buf()));
+            children.dedup();
+            stack.extend(children.into_iter().map(|child| (depth + 1, child)));
+        }
+        tree
+    }
+
+    /// Gives the file which imports every other file.
+    pub(crate) fn root(&self) -> &Module {
+        &self.dag[self.node_of_interest]
+    }
+
+    /// Gives every import between the files, as `(importer, imported)` pairs.
+    pub(crate) fn edges(&self) -> Vec<(&Module, &Module)> {
+        self.dag
+            .raw_edges()
+            .iter()
+            .map(|edge| (&self.dag[edge.source()], &self.dag[edge.target()]))
+            .collect()
+    }
+
     /// Gives a vector containing every file that needs to be imported, in order of import from leaf to the node of interest,
     /// and the root module.
     pub(crate) fn modules(self) -> (Vec<Module>, Module) {
@@ -0,0 +1,51 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+/// A module made available to `#import` by name: the import paths it requires, the file it was read from, and its
+/// source.
+pub type Include = (Vec<String>, PathBuf, String);
+
+/// Reads every WGSL file in the given files and directories, keyed by the name given by its `#define_import_path`, or
+/// by its quoted path if it has none. Names which are already present in `existing` are reported as duplicates.
+pub fn scan_includes(
+    mut include_paths: Vec<PathBuf>,
+    existing: &HashMap<String, Include>,
+) -> Result<HashMap<String, Include>, String> {
+    let mut new_includes = HashMap::new();
+
+    while let Some(buf) = include_paths.pop() {
+        if buf.is_dir() {
+            let Ok(entries) = fs::read_dir(&buf) else {
+                return Err(format!("Failed to read directory {buf:?}"));
+            };
+
+            include_paths.extend(entries.map(|m| m.unwrap().path()));
+        } else {
+            match fs::read_to_string(&buf) {
+                Err(e) => return Err(format!("Failed to read file {buf:?} to string:{e:?}")),
+                Ok(source) => {
+                    let (name, reqs, _) = naga_oil::compose::get_preprocessor_data(&source);
+
+                    let name = name
+                        .unwrap_or(format!(r#""{}""#, buf.to_string_lossy().replace("\\", "/")));
+
+                    let name = name
+                        .strip_prefix(r#""./"#)
+                        .map(|name| format!(r#""{name}"#))
+                        .unwrap_or(name);
+
+                    let reqs = reqs.into_iter().map(|req| req.import).collect::<Vec<_>>();
+
+                    if new_includes.contains_key(&name) || existing.contains_key(&name) {
+                        eprintln!("warning: duplicate definition for `{name}`");
+                    }
+
+                    eprintln!("Including {name} from {buf:?}");
+
+                    new_includes.insert(name, (reqs, buf, source));
+                }
+            }
+        }
+    }
+
+    Ok(new_includes)
+}
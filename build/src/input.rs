@@ -0,0 +1,559 @@
+use std::{collections::HashMap, env, path::PathBuf};
+
+use naga_oil::compose::ShaderDefValue;
+use syn::{
+    braced, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+    Ident, Token,
+};
+
+use crate::{
+    includes::{self, Include},
+    structs::{Math, StructLayout},
+};
+
+struct Kv<T, K> {
+    key: T,
+    value: K,
+}
+
+impl<T: Parse, K: Parse> Parse for Kv<T, K> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse::<T>()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse::<K>()?;
+
+        Ok(Self { key, value })
+    }
+}
+
+#[derive(Clone)]
+struct TypedValue {
+    ty: syn::Ident,
+    value: syn::Lit,
+}
+
+impl Parse for TypedValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ty = input.parse::<syn::Ident>()?;
+
+        if ty != "Bool" && ty != "Int" && ty != "UInt" {
+            return Err(syn::Error::new(
+                ty.span(),
+                "expected one of `Bool`, `Int`, `UInt`",
+            ));
+        }
+
+        let v;
+        parenthesized!(v in input);
+
+        Ok(Self {
+            ty,
+            value: v.parse()?,
+        })
+    }
+}
+
+impl From<TypedValue> for ShaderDefValue {
+    fn from(value: TypedValue) -> Self {
+        let int = match &value.value {
+            syn::Lit::Int(i) => Some(i),
+            _ => None,
+        };
+        match value.ty.to_string().as_str() {
+            "Bool" => match value.value {
+                syn::Lit::Bool(b) => ShaderDefValue::Bool(b.value),
+                _ => panic!("Expected a boolean literal for Bool() constant"),
+            },
+            "Int" => match int.and_then(|i| i.base10_parse::<i32>().ok()) {
+                Some(num) => ShaderDefValue::Int(num),
+                None => panic!("Expected i32 literal for Int() constant"),
+            },
+            "UInt" => match int.and_then(|i| i.base10_parse::<u32>().ok()) {
+                Some(num) => ShaderDefValue::UInt(num),
+                None => panic!("Expected u32 literal for UInt() constant"),
+            },
+            _ => panic!(),
+        }
+    }
+}
+
+/// Preprocessor definitions, in the order given.
+#[derive(Default)]
+pub(crate) struct Constants {
+    pub(crate) inner: Vec<(String, ShaderDefValue)>,
+}
+
+impl Parse for Constants {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let p = input.parse_terminated(Kv::<syn::Ident, TypedValue>::parse, Token![,])?;
+
+        Ok(Self {
+            inner: p
+                .into_iter()
+                .map(|kv| (kv.key.to_string(), ShaderDefValue::from(kv.value)))
+                .collect(),
+        })
+    }
+}
+
+/// The targets that a shader can be composed for, in addition to the default composition. `__<TARGET>` is defined as
+/// `true` while composing for that target, and `false` otherwise.
+pub(crate) const TARGETS: [&str; 2] = ["native", "web"];
+
+/// Validator capabilities which most devices lack, so must be enabled with `capabilities` for shaders to use them.
+pub(crate) const OPT_IN_CAPABILITIES: &[&str] = &[
+    "FLOAT64",
+    "MULTIVIEW",
+    "RAY_QUERY",
+    "SHADER_INT64",
+    "SHADER_INT64_ATOMIC_MIN_MAX",
+    "SHADER_INT64_ATOMIC_ALL_OPS",
+    "SUBGROUP",
+    "SUBGROUP_BARRIER",
+    "SUBGROUP_VERTEX_STAGE",
+];
+
+/// The WGSL baselines that a shader can be restricted to with `wgsl_baseline`, and the validator capabilities that
+/// every implementation of each baseline has.
+pub(crate) const WGSL_BASELINES: &[(&str, &[&str])] = &[(
+    "webgpu-1.0",
+    &["CUBE_ARRAY_TEXTURES", "MULTISAMPLED_SHADING"],
+)];
+
+/// The `serde` traits that exported structs can derive with `derives`.
+pub(crate) const SERDE_DERIVES: &[&str] = &["Serialize", "Deserialize"];
+
+/// The most definitions that permutations can be generated for, as every combination of them is composed.
+pub(crate) const MAX_PERMUTATION_DEFS: usize = 6;
+
+/// How the shader is provided to the application.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// The shader is composed once, while compiling.
+    #[default]
+    Baked,
+    /// The sources the shader is composed from are embedded too, and a `compose` function recomposes it at runtime
+    /// with other shader defs.
+    Runtime,
+}
+
+impl Mode {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "baked" => Some(Self::Baked),
+            "runtime" => Some(Self::Runtime),
+            _ => None,
+        }
+    }
+}
+
+/// Settings controlling what is generated alongside the composed shader.
+#[derive(Default)]
+pub(crate) struct Options {
+    /// Whether the shader can be recomposed at runtime.
+    pub(crate) mode: Mode,
+    /// Embed the shader file as it was read, before composition, as `RAW_SOURCE`.
+    pub(crate) raw_source: bool,
+    /// Embed the source of every module that the shader imports, under `imports`.
+    pub(crate) import_sources: bool,
+    /// Strip the whitespace from `SOURCE` in release builds, keeping the readable source for debug builds.
+    pub(crate) debug_source: bool,
+    /// Strip `SOURCE` and shorten the names local to its functions.
+    pub(crate) minify: bool,
+    /// Rename the functions, private globals and structs in `SOURCE`, as well as the names local to its functions.
+    pub(crate) obfuscate: bool,
+    /// Embed `SOURCE` compressed, decompressing it the first time it is used.
+    pub(crate) compress_source: bool,
+    /// Write a reflection snapshot of the shader to `OUT_DIR`.
+    pub(crate) snapshot: bool,
+    /// Add the shader's statistics to the crate's compilation report in `OUT_DIR`.
+    pub(crate) report: bool,
+    /// Write a Makefile-style depfile listing the files the shader was composed from to `OUT_DIR`.
+    pub(crate) depfile: bool,
+    /// Write the graph of files that the shader imports, in Graphviz DOT, to `OUT_DIR`.
+    pub(crate) import_graph: bool,
+    /// Write a JSON description of the shader's interface to `OUT_DIR`.
+    pub(crate) reflection_json: bool,
+    /// Where to write the composed shader, relative to the root of the crate, if anywhere.
+    pub(crate) emit_composed: Option<PathBuf>,
+    /// Lints to allow on every generated item.
+    pub(crate) lint_allows: Vec<syn::Path>,
+    /// Opt-in validator capabilities enabled for the shader.
+    pub(crate) capabilities: Vec<String>,
+    /// The WGSL baseline that the shader must not use anything newer than, if any.
+    pub(crate) wgsl_baseline: Option<String>,
+    /// Generate a variant of the shader whose vertex entry points fetch their inputs from storage buffers.
+    pub(crate) vertex_pulling: bool,
+    /// Definitions that the shader is precompiled with every combination of, for selection at runtime.
+    pub(crate) permutations: Vec<String>,
+    /// Allow the shader, its includes and its imports to be outside of the crate's directory.
+    pub(crate) allow_external_paths: bool,
+    /// The most bytes that the composed source may take, if limited.
+    pub(crate) max_source_bytes: Option<usize>,
+    /// How exported structs are laid out, or `None` to choose by the crate's features.
+    pub(crate) layout: Option<StructLayout>,
+    /// How vectors and matrices in exported structs are represented, or `None` to choose by the crate's features.
+    pub(crate) math: Option<Math>,
+    /// The `serde` traits that exported structs derive.
+    pub(crate) derives: Vec<String>,
+    /// The traits that generated structs derive in place of the default set, if given.
+    pub(crate) type_derives: Option<Vec<syn::Path>>,
+    /// The attribute marking exported items in place of `@export`, without the `@`, or `Some(None)` if exports are
+    /// disabled.
+    pub(crate) export_directive: Option<Option<String>>,
+}
+
+impl Options {
+    pub(crate) fn layout(&self) -> StructLayout {
+        self.layout.unwrap_or_else(StructLayout::from_features)
+    }
+
+    pub(crate) fn math(&self) -> Math {
+        self.math.unwrap_or_else(Math::from_features)
+    }
+
+    /// The name of the attribute marking exported items, or `None` if exports are disabled and the attribute is left
+    /// for naga to read.
+    pub(crate) fn export_directive(&self) -> Option<&str> {
+        match &self.export_directive {
+            Some(directive) => directive.as_deref(),
+            None => Some("export"),
+        }
+    }
+
+    /// The traits derived by every generated struct, apart from those derived for its layout.
+    pub(crate) fn struct_derives(&self) -> Vec<syn::Path> {
+        let mut derives = self.type_derives.clone().unwrap_or_else(|| {
+            vec![
+                syn::parse_quote! { Clone },
+                syn::parse_quote! { Copy },
+                syn::parse_quote! { Debug },
+                syn::parse_quote! { PartialEq },
+            ]
+        });
+        for derive in &self.derives {
+            let derive = Ident::new(derive, proc_macro2::Span::call_site());
+            derives.push(syn::parse_quote! { ::serde::#derive });
+        }
+        derives
+    }
+
+    /// The capabilities that the shader is validated with: everything but the opt-in capabilities which weren't
+    /// requested.
+    pub(crate) fn validator_capabilities(&self) -> naga::valid::Capabilities {
+        OPT_IN_CAPABILITIES
+            .iter()
+            .filter(|name| !self.capabilities.iter().any(|enabled| enabled == *name))
+            .filter_map(|name| naga::valid::Capabilities::from_name(name))
+            .fold(naga::valid::Capabilities::all(), |all, capability| {
+                all - capability
+            })
+    }
+}
+
+/// The arguments given to the `include_wgsl_oil` attribute.
+#[doc(hidden)]
+pub struct MacroInput {
+    pub(crate) wgsl_path: String,
+    pub(crate) includes: HashMap<String, Include>,
+    pub(crate) constants: Constants,
+    pub(crate) targets: Vec<(String, Constants)>,
+    pub(crate) options: Options,
+}
+
+impl Parse for MacroInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut wgsl_path = String::new();
+        let mut includes = HashMap::new();
+        let mut constants = Constants::default();
+        let mut targets = Vec::new();
+        let mut options = Options::default();
+
+        while !input.is_empty() {
+            let ident = input.parse::<Ident>()?;
+            match ident.to_string().as_str() {
+                "path" => {
+                    input.parse::<Token![=]>()?;
+                    wgsl_path = input.parse::<syn::LitStr>()?.value();
+                }
+                "includes" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    bracketed!(inner in input);
+                    let p = inner.parse_terminated(<syn::LitStr as Parse>::parse, Token![,])?;
+                    let include_paths: Vec<_> = p
+                        .iter()
+                        .map(|p| {
+                            let path = p.value();
+                            if path.starts_with("/") {
+                                PathBuf::from(path)
+                            } else {
+                                PathBuf::from(format!(
+                                    "{}/{}",
+                                    env::var("CARGO_MANIFEST_DIR").unwrap(),
+                                    path
+                                ))
+                            }
+                        })
+                        .collect();
+
+                    let new_includes = includes::scan_includes(include_paths, &includes)
+                        .map_err(|e| syn::Error::new(p.span(), e))?;
+                    includes.extend(new_includes);
+                }
+                "constants" => {
+                    input.parse::<Token![=]>()?;
+                    constants = input.parse::<Constants>()?;
+                }
+                "targets" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    braced!(inner in input);
+                    let p = inner.parse_terminated(
+                        |input| {
+                            let target = input.parse::<Ident>()?;
+                            if !TARGETS.contains(&target.to_string().as_str()) {
+                                return Err(syn::Error::new(
+                                    target.span(),
+                                    "expected one of `native`, `web`",
+                                ));
+                            }
+                            input.parse::<Token![=]>()?;
+                            let constants;
+                            braced!(constants in input);
+                            Ok((target.to_string(), constants.parse::<Constants>()?))
+                        },
+                        Token![,],
+                    )?;
+                    targets = p.into_iter().collect();
+                }
+                "mode" => {
+                    input.parse::<Token![=]>()?;
+                    let mode = input.parse::<Ident>()?;
+                    options.mode = Mode::from_name(&mode.to_string()).ok_or_else(|| {
+                        syn::Error::new(mode.span(), "expected one of `baked`, `runtime`")
+                    })?;
+                }
+                "raw_source" => {
+                    input.parse::<Token![=]>()?;
+                    options.raw_source = input.parse::<syn::LitBool>()?.value;
+                }
+                "import_sources" => {
+                    input.parse::<Token![=]>()?;
+                    options.import_sources = input.parse::<syn::LitBool>()?.value;
+                }
+                "debug_source" => {
+                    input.parse::<Token![=]>()?;
+                    options.debug_source = input.parse::<syn::LitBool>()?.value;
+                }
+                "minify" => {
+                    input.parse::<Token![=]>()?;
+                    options.minify = input.parse::<syn::LitBool>()?.value;
+                }
+                "obfuscate" => {
+                    input.parse::<Token![=]>()?;
+                    options.obfuscate = input.parse::<syn::LitBool>()?.value;
+                }
+                "compress_source" => {
+                    input.parse::<Token![=]>()?;
+                    options.compress_source = input.parse::<syn::LitBool>()?.value;
+                }
+                "snapshot" => {
+                    input.parse::<Token![=]>()?;
+                    options.snapshot = input.parse::<syn::LitBool>()?.value;
+                }
+                "report" => {
+                    input.parse::<Token![=]>()?;
+                    options.report = input.parse::<syn::LitBool>()?.value;
+                }
+                "depfile" => {
+                    input.parse::<Token![=]>()?;
+                    options.depfile = input.parse::<syn::LitBool>()?.value;
+                }
+                "import_graph" => {
+                    input.parse::<Token![=]>()?;
+                    options.import_graph = input.parse::<syn::LitBool>()?.value;
+                }
+                "reflection_json" => {
+                    input.parse::<Token![=]>()?;
+                    options.reflection_json = input.parse::<syn::LitBool>()?.value;
+                }
+                "emit_composed" => {
+                    input.parse::<Token![=]>()?;
+                    options.emit_composed =
+                        Some(PathBuf::from(input.parse::<syn::LitStr>()?.value()));
+                }
+                "lint_allows" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    bracketed!(inner in input);
+                    let lints = inner.parse_terminated(syn::Path::parse_mod_style, Token![,])?;
+                    options.lint_allows.extend(lints);
+                }
+                "capabilities" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    bracketed!(inner in input);
+                    let capabilities = inner.parse_terminated(Ident::parse, Token![,])?;
+                    for capability in capabilities {
+                        let name = capability.to_string();
+                        if !OPT_IN_CAPABILITIES.contains(&name.as_str()) {
+                            return Err(syn::Error::new(
+                                capability.span(),
+                                format!(
+                                    "expected one of {}",
+                                    OPT_IN_CAPABILITIES
+                                        .iter()
+                                        .map(|name| format!("`{name}`"))
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                            ));
+                        }
+                        options.capabilities.push(name);
+                    }
+                }
+                "wgsl_baseline" => {
+                    input.parse::<Token![=]>()?;
+                    let baseline = input.parse::<syn::LitStr>()?;
+                    let name = baseline.value();
+                    if !WGSL_BASELINES.iter().any(|(baseline, _)| *baseline == name) {
+                        return Err(syn::Error::new(
+                            baseline.span(),
+                            format!(
+                                "expected one of {}",
+                                WGSL_BASELINES
+                                    .iter()
+                                    .map(|(name, _)| format!("`\"{name}\"`"))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            ),
+                        ));
+                    }
+                    options.wgsl_baseline = Some(name);
+                }
+                "vertex_pulling" => {
+                    input.parse::<Token![=]>()?;
+                    options.vertex_pulling = input.parse::<syn::LitBool>()?.value;
+                }
+                "permutations" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    bracketed!(inner in input);
+                    let defs = inner.parse_terminated(Ident::parse, Token![,])?;
+                    if defs.len() > MAX_PERMUTATION_DEFS {
+                        return Err(syn::Error::new(
+                            defs.span(),
+                            format!(
+                                "at most {MAX_PERMUTATION_DEFS} definitions can be permuted, as every \
+                                combination of them is composed"
+                            ),
+                        ));
+                    }
+                    options.permutations = defs.iter().map(Ident::to_string).collect();
+                }
+                "allow_external_paths" => {
+                    input.parse::<Token![=]>()?;
+                    options.allow_external_paths = input.parse::<syn::LitBool>()?.value;
+                }
+                "max_source_bytes" => {
+                    input.parse::<Token![=]>()?;
+                    let bytes = input.parse::<syn::LitInt>()?;
+                    options.max_source_bytes = Some(bytes.base10_parse::<usize>()?);
+                }
+                "layout" => {
+                    input.parse::<Token![=]>()?;
+                    let layout = input.parse::<Ident>()?;
+                    options.layout =
+                        Some(StructLayout::from_name(&layout.to_string()).ok_or_else(|| {
+                            syn::Error::new(layout.span(), "expected one of `repr_c`, `encase`")
+                        })?);
+                }
+                "math" => {
+                    input.parse::<Token![=]>()?;
+                    let math = input.parse::<Ident>()?;
+                    options.math = Some(Math::from_name(&math.to_string()).ok_or_else(|| {
+                        syn::Error::new(
+                            math.span(),
+                            "expected one of `arrays`, `glam`, `nalgebra`, `mint`",
+                        )
+                    })?);
+                }
+                "derives" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    bracketed!(inner in input);
+                    let derives = inner.parse_terminated(Ident::parse, Token![,])?;
+                    for derive in derives {
+                        let name = derive.to_string();
+                        if !SERDE_DERIVES.contains(&name.as_str()) {
+                            return Err(syn::Error::new(
+                                derive.span(),
+                                "expected one of `Serialize`, `Deserialize`",
+                            ));
+                        }
+                        if !cfg!(feature = "serde") {
+                            return Err(syn::Error::new(
+                                derive.span(),
+                                format!("deriving `{name}` requires the `serde` feature"),
+                            ));
+                        }
+                        options.derives.push(name);
+                    }
+                }
+                "type_derives" => {
+                    input.parse::<Token![=]>()?;
+                    let inner;
+                    bracketed!(inner in input);
+                    let derives = inner.parse_terminated(syn::Path::parse_mod_style, Token![,])?;
+                    options
+                        .type_derives
+                        .get_or_insert_with(Vec::new)
+                        .extend(derives);
+                }
+                "export_directive" => {
+                    input.parse::<Token![=]>()?;
+                    if input.peek(syn::LitBool) {
+                        let enabled = input.parse::<syn::LitBool>()?;
+                        options.export_directive = (!enabled.value).then_some(None);
+                    } else {
+                        let directive = input.parse::<syn::LitStr>()?;
+                        if syn::parse_str::<Ident>(&directive.value()).is_err() {
+                            return Err(syn::Error::new(
+                                directive.span(),
+                                "expected the name of a WGSL attribute, without the `@`, or `false`",
+                            ));
+                        }
+                        options.export_directive = Some(Some(directive.value()));
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "expected one of `path`, `includes`, `constants`, `targets`, `mode`, \
+                        `raw_source`, `import_sources`, `debug_source`, `minify`, `obfuscate`, \
+                        `compress_source`, `snapshot`, `report`, `depfile`, `import_graph`, \
+                        `reflection_json`, `emit_composed`, `lint_allows`, `capabilities`, \
+                        `wgsl_baseline`, `vertex_pulling`, `permutations`, `allow_external_paths`, \
+                        `max_source_bytes`, `layout`, `math`, `derives`, `type_derives`, \
+                        `export_directive`",
+                    ));
+                }
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Self {
+            wgsl_path,
+            includes,
+            constants,
+            targets,
+            options,
+        })
+    }
+}
@@ -0,0 +1,243 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use naga_oil::compose::ShaderDefValue;
+
+use crate::{bindings, reflect};
+
+/// A JSON value, written out by hand so that the reflection dump doesn't need `serde`.
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn string(value: impl Into<String>) -> Self {
+        Json::String(value.into())
+    }
+
+    fn number(value: impl ToString) -> Self {
+        Json::Number(value.to_string())
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => write!(out, "{value}").unwrap(),
+            Json::Number(value) => out.push_str(value),
+            Json::String(value) => write_string(out, value),
+            Json::Array(values) if values.is_empty() => out.push_str("[]"),
+            Json::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&pad);
+                    value.write(out, indent + 1);
+                }
+                write!(out, "\n{}]", "  ".repeat(indent)).unwrap();
+            }
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&pad);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                write!(out, "\n{}}}", "  ".repeat(indent)).unwrap();
+            }
+        }
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Gives the value that an override is initialized with in the shader, where it is a literal.
+fn override_default(module: &naga::Module, override_: &naga::Override) -> Json {
+    let Some(init) = override_.init else {
+        return Json::Null;
+    };
+    match module.global_expressions[init] {
+        naga::Expression::Literal(literal) => match literal {
+            naga::Literal::Bool(value) => Json::Bool(value),
+            naga::Literal::F32(value) => Json::number(value),
+            naga::Literal::F64(value) => Json::number(value),
+            naga::Literal::I32(value) => Json::number(value),
+            naga::Literal::U32(value) => Json::number(value),
+            naga::Literal::I64(value) => Json::number(value),
+            naga::Literal::U64(value) => Json::number(value),
+            naga::Literal::AbstractInt(value) => Json::number(value),
+            naga::Literal::AbstractFloat(value) => Json::number(value),
+            #[allow(unreachable_patterns)]
+            _ => Json::Null,
+        },
+        _ => Json::Null,
+    }
+}
+
+/// Gives a JSON description of the module for tools outside of Rust: its entry points, bindings, struct layouts and
+/// overrides, and the shader defs it was composed with. Entry points, structs and shader defs are sorted by name, and
+/// bindings by group and binding, so the file only changes when the shader does.
+pub(crate) fn reflection(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    shader_name: &str,
+    shader_defs: &BTreeMap<String, ShaderDefValue>,
+) -> String {
+    let layouter = reflect::layouter(module);
+
+    let mut entry_points = module.entry_points.iter().collect::<Vec<_>>();
+    entry_points.sort_by(|a, b| a.name.cmp(&b.name));
+    let entry_points = entry_points
+        .into_iter()
+        .map(|entry_point| {
+            let workgroup_size = match entry_point.stage {
+                naga::ShaderStage::Compute => {
+                    Json::Array(entry_point.workgroup_size.map(Json::number).into())
+                }
+                _ => Json::Null,
+            };
+            Json::Object(vec![
+                ("name", Json::string(&entry_point.name)),
+                (
+                    "stage",
+                    Json::string(reflect::stage_name(entry_point.stage)),
+                ),
+                ("workgroup_size", workgroup_size),
+            ])
+        })
+        .collect();
+
+    let bindings = bindings::bindings(module)
+        .into_iter()
+        .map(|binding| {
+            let stages = binding
+                .stages(module, info)
+                .into_iter()
+                .map(|stage| Json::string(reflect::stage_name(stage)))
+                .collect();
+            Json::Object(vec![
+                ("group", Json::number(binding.group)),
+                ("binding", Json::number(binding.binding)),
+                (
+                    "name",
+                    Json::string(reflect::display_name(
+                        binding.global.name.as_deref().unwrap_or("_"),
+                    )),
+                ),
+                (
+                    "declaration",
+                    Json::string(reflect::global_declaration(binding.global.space)),
+                ),
+                (
+                    "type",
+                    Json::string(reflect::type_name(module, binding.global.ty)),
+                ),
+                ("stages", Json::Array(stages)),
+            ])
+        })
+        .collect();
+
+    let mut structs = module
+        .types
+        .iter()
+        .filter_map(|(handle, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, span } => Some((
+                reflect::display_name(ty.name.as_deref()?).into_owned(),
+                handle,
+                members,
+                *span,
+            )),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    structs.sort_by(|a, b| a.0.cmp(&b.0));
+    let structs = structs
+        .into_iter()
+        .map(|(name, handle, members, span)| {
+            let members = members
+                .iter()
+                .map(|member| {
+                    Json::Object(vec![
+                        ("name", Json::string(member.name.as_deref().unwrap_or("_"))),
+                        ("type", Json::string(reflect::type_name(module, member.ty))),
+                        ("offset", Json::number(member.offset)),
+                        ("size", Json::number(layouter[member.ty].size)),
+                    ])
+                })
+                .collect();
+            Json::Object(vec![
+                ("name", Json::String(name)),
+                ("size", Json::number(span)),
+                ("align", Json::number(layouter[handle].alignment)),
+                ("members", Json::Array(members)),
+            ])
+        })
+        .collect();
+
+    let overrides = module
+        .overrides
+        .iter()
+        .filter_map(|(_, override_)| {
+            let name = override_.name.as_deref()?;
+            Some(Json::Object(vec![
+                ("name", Json::string(reflect::display_name(name))),
+                ("id", override_.id.map_or(Json::Null, Json::number)),
+                (
+                    "type",
+                    Json::string(reflect::type_name(module, override_.ty)),
+                ),
+                ("default", override_default(module, override_)),
+            ]))
+        })
+        .collect();
+
+    let shader_defs = shader_defs
+        .iter()
+        .map(|(name, value)| {
+            let (ty, value) = match value {
+                ShaderDefValue::Bool(value) => ("bool", Json::Bool(*value)),
+                ShaderDefValue::Int(value) => ("i32", Json::number(value)),
+                ShaderDefValue::UInt(value) => ("u32", Json::number(value)),
+            };
+            Json::Object(vec![
+                ("name", Json::string(name)),
+                ("type", Json::string(ty)),
+                ("value", value),
+            ])
+        })
+        .collect();
+
+    let reflection = Json::Object(vec![
+        ("shader", Json::string(shader_name)),
+        ("entry_points", Json::Array(entry_points)),
+        ("bindings", Json::Array(bindings)),
+        ("structs", Json::Array(structs)),
+        ("overrides", Json::Array(overrides)),
+        ("shader_defs", Json::Array(shader_defs)),
+    ]);
+
+    let mut out = String::new();
+    reflection.write(&mut out, 0);
+    out + "\n"
+}
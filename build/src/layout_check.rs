@@ -0,0 +1,75 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+/// Gives the path of the module holding the offsets of a generated struct's members, e.g.
+/// `my_shader::types::offsets::Camera` for `my_shader::types::Camera`.
+fn offsets_path(shader_struct: &syn::Path) -> Option<syn::Path> {
+    let len = shader_struct.segments.len();
+    if len < 2 || shader_struct.segments[len - 2].ident != "types" {
+        return None;
+    }
+
+    let mut offsets = shader_struct.clone();
+    let last = offsets.segments.pop()?.into_value();
+    offsets.segments.push(syn::parse_quote! { offsets });
+    offsets.segments.push(last);
+    Some(offsets)
+}
+
+/// Asserts at compile time that a struct has the same size as a struct generated from a shader, and that each of its
+/// members is at the same offset as the member of the same name in the shader. Members starting with an underscore
+/// are taken to be padding, so aren't checked.
+pub(crate) fn layout_check(shader_struct: &syn::Path, item: &syn::ItemStruct) -> TokenStream {
+    let Some(offsets) = offsets_path(shader_struct) else {
+        return quote_spanned! {shader_struct.span()=>
+            compile_error!("expected the path of a struct in a shader's generated `types` module, e.g. `my_shader::types::Camera`");
+        };
+    };
+    if !item.generics.params.is_empty() {
+        return quote_spanned! {item.generics.span()=>
+            compile_error!("structs with generic parameters can't have their layout checked");
+        };
+    }
+    let syn::Fields::Named(fields) = &item.fields else {
+        return quote_spanned! {item.ident.span()=>
+            compile_error!("expected a struct with named fields, to be matched to the members of the shader's struct");
+        };
+    };
+
+    let ident = &item.ident;
+    let shader_name = shader_struct
+        .segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+        .unwrap_or_default();
+    let size_message =
+        format!("`{ident}` doesn't have the same size as `{shader_name}` in the shader");
+    let checks = fields
+        .named
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .filter(|field| !field.to_string().starts_with('_'))
+        .map(|field| {
+            let message = format!(
+                "`{ident}::{field}` isn't at the same offset as `{shader_name}::{field}` in the shader"
+            );
+            quote_spanned! {field.span()=>
+                const _: () = {
+                    let offset = ::core::mem::offset_of!(#ident, #field) as u64;
+                    assert!(offset == #offsets::#field, #message);
+                };
+            }
+        });
+
+    quote! {
+        #item
+
+        // Each check is a separate constant, so that every mismatch is reported
+        const _: () = {
+            let size = ::core::mem::size_of::<#ident>() as u64;
+            assert!(size == #shader_struct::SIZE_BYTES, #size_message);
+        };
+        #(#checks)*
+    }
+}
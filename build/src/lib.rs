@@ -0,0 +1,589 @@
+//! The shader pipeline behind [`include-wgsl-oil`](https://crates.io/crates/include-wgsl-oil), for use from build
+//! scripts. Shaders are composed, validated and reflected exactly as the `include_wgsl_oil` attribute would, so
+//! projects generating other code from their shaders (e.g. C headers) don't need to reimplement the pipeline.
+//!
+//! ```rust ignore
+//! // build.rs
+//! let shader = include_wgsl_oil_build::Shader::new("src/shaders/shader.wgsl")
+//!     .include("src/shaders/common")?
+//!     .compose()
+//!     .unwrap_or_else(|errors| panic!("{}", errors.join("\n")));
+//!
+//! for dependency in shader.dependencies() {
+//!     println!("cargo:rerun-if-changed={}", dependency.display());
+//! }
+//! write_c_header(shader.module());
+//! ```
+
+// Backend selection. Both backends expose the same `naga_oil` API surface that we use, so the rest of the crate
+// refers to `naga` and `naga_oil` without caring which major version is underneath. Features are additive, so if both
+// are enabled `naga-24` is used.
+#[cfg(not(any(feature = "naga-24", feature = "naga-23")))]
+compile_error!("one of the `naga-24` or `naga-23` features must be enabled");
+// These features only change the output of `naga-to-tokenstream`, which the previous backend doesn't use
+#[cfg(all(feature = "naga-23", not(feature = "naga-24"), feature = "minify"))]
+compile_error!(
+    "the `minify` feature needs the `naga-24` backend; use the `minify` argument to minify with the `naga-23` backend"
+);
+#[cfg(all(feature = "naga-23", not(feature = "naga-24"), feature = "naga"))]
+compile_error!("the `naga` feature needs the `naga-24` backend");
+
+#[cfg(all(feature = "naga-23", not(feature = "naga-24")))]
+pub extern crate naga_23 as naga;
+#[cfg(feature = "naga-24")]
+pub extern crate naga_24 as naga;
+#[cfg(all(feature = "naga-23", not(feature = "naga-24")))]
+pub extern crate naga_oil_16 as naga_oil;
+#[cfg(feature = "naga-24")]
+pub extern crate naga_oil_17 as naga_oil;
+
+mod artifacts;
+mod bindings;
+mod budget;
+mod color_targets;
+mod compress;
+mod constants;
+mod defaults;
+mod dispatch;
+mod docs;
+mod entry_points;
+mod error;
+mod exports;
+mod features;
+mod files;
+mod gpu_tests;
+mod graph;
+mod hot_reload;
+mod import_sources;
+mod imports;
+mod includes;
+mod input;
+mod json;
+mod layout_check;
+mod library;
+mod limits;
+mod merge;
+mod minify;
+mod mode;
+mod module;
+mod overrides;
+mod pipelines;
+mod pretty;
+mod pulling;
+mod push_constants;
+mod reflect;
+mod report;
+mod result;
+mod snapshot;
+mod source;
+mod structs;
+mod summary;
+#[cfg(test)]
+mod testing;
+mod values;
+mod vertex;
+mod writer;
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use files::AbsoluteRustFilePathBuf;
+use input::{
+    Constants, Mode, MAX_PERMUTATION_DEFS, OPT_IN_CAPABILITIES, SERDE_DERIVES, TARGETS,
+    WGSL_BASELINES,
+};
+use naga_oil::compose::ShaderDefValue;
+use quote::quote;
+use result::ShaderResult;
+use source::Sourcecode;
+use structs::{Math, StructLayout};
+
+pub use includes::{scan_includes, Include};
+#[doc(hidden)]
+pub use input::MacroInput;
+pub use reflect::display_name;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("should be run using cargo"))
+}
+
+/// Expands the `include_wgsl_oil` attribute invoked in the Rust file at `invocation_path`, giving the doc comment
+/// summarizing the shader, to add to the annotated module, and the items to inject into it.
+#[doc(hidden)]
+pub fn expand(invocation_path: PathBuf, mut input: MacroInput) -> (Option<String>, Vec<syn::Item>) {
+    input.wgsl_path = manifest_dir()
+        .join(&input.wgsl_path)
+        .to_string_lossy()
+        .into_owned();
+
+    let sourcecode = Sourcecode::new(AbsoluteRustFilePathBuf::new(invocation_path), input);
+    let mut result = sourcecode.complete();
+
+    result.validate();
+    result.write_artifacts();
+
+    (result.module_doc(), result.items())
+}
+
+/// Expands the `wgsl_layout_check` attribute, which checks that the annotated struct has the same layout as a struct
+/// generated from a shader.
+#[doc(hidden)]
+pub fn expand_layout_check(
+    shader_struct: &syn::Path,
+    item: &syn::ItemStruct,
+) -> proc_macro2::TokenStream {
+    layout_check::layout_check(shader_struct, item)
+}
+
+/// A shader to compose from a build script, configured in the same way as the arguments to the `include_wgsl_oil`
+/// attribute. Invalid arguments are reported by [`Shader::compose`].
+pub struct Shader {
+    input: MacroInput,
+    root: PathBuf,
+    errors: Vec<String>,
+}
+
+impl Shader {
+    /// Starts configuring the shader at `path`, relative to the root of the crate being built.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        match env::var("CARGO_MANIFEST_DIR") {
+            Ok(root) => Self::with_root(root, path),
+            Err(_) => {
+                let mut shader = Self::with_root(PathBuf::new(), path);
+                shader.errors.push(
+                    "`CARGO_MANIFEST_DIR` is not set - use `Shader::with_root` outside of cargo"
+                        .to_owned(),
+                );
+                shader
+            }
+        }
+    }
+
+    /// Starts configuring the shader at `path`, relative to `root`, the directory of a crate. Absolute imports are
+    /// resolved from its `src/lib.rs` or `src/main.rs`.
+    pub fn with_root(root: impl Into<PathBuf>, path: impl AsRef<Path>) -> Self {
+        let root = root.into();
+        let root = std::path::absolute(&root).unwrap_or(root);
+        Self {
+            input: MacroInput {
+                wgsl_path: root.join(path).to_string_lossy().into_owned(),
+                includes: Default::default(),
+                constants: Default::default(),
+                targets: Vec::new(),
+                options: Default::default(),
+            },
+            root,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Makes the modules in a file or directory, relative to the root of the crate, available to `#import`.
+    /// Equivalent to the `includes` argument.
+    pub fn include(mut self, path: impl AsRef<Path>) -> Result<Self, String> {
+        let new_includes = scan_includes(vec![self.root.join(path)], &self.input.includes)?;
+        self.input.includes.extend(new_includes);
+
+        Ok(self)
+    }
+
+    /// Defines a value for the preprocessor. Equivalent to an entry in the `constants` argument.
+    pub fn constant(mut self, name: impl Into<String>, value: ShaderDefValue) -> Self {
+        self.input.constants.inner.push((name.into(), value));
+        self
+    }
+
+    /// Also composes the shader for `target`, one of `native` or `web`, with additional definitions. Equivalent to an
+    /// entry in the `targets` argument.
+    pub fn target(
+        mut self,
+        target: &str,
+        constants: impl IntoIterator<Item = (String, ShaderDefValue)>,
+    ) -> Self {
+        if !TARGETS.contains(&target) {
+            self.errors.push(format!(
+                "unknown target `{target}` - expected one of `native`, `web`"
+            ));
+            return self;
+        }
+
+        let constants = Constants {
+            inner: constants.into_iter().collect(),
+        };
+        self.input.targets.push((target.to_owned(), constants));
+        self
+    }
+
+    /// Composes the shader once while compiling, with `baked`, or also embeds the sources it is composed from and
+    /// generates a `compose` function recomposing it at runtime, with `runtime`. Equivalent to the `mode` argument.
+    pub fn mode(mut self, mode: &str) -> Self {
+        match Mode::from_name(mode) {
+            Some(mode) => self.input.options.mode = mode,
+            None => self.errors.push(format!(
+                "unknown mode `{mode}` - expected `baked` or `runtime`"
+            )),
+        }
+        self
+    }
+
+    /// Embeds the shader file as it was read, before composition, as `RAW_SOURCE`. Equivalent to the `raw_source`
+    /// argument.
+    pub fn raw_source(mut self, raw_source: bool) -> Self {
+        self.input.options.raw_source = raw_source;
+        self
+    }
+
+    /// Embeds the source of every module that the shader imports, under `imports`. Equivalent to the `import_sources`
+    /// argument.
+    pub fn import_sources(mut self, import_sources: bool) -> Self {
+        self.input.options.import_sources = import_sources;
+        self
+    }
+
+    /// Strips the whitespace from `SOURCE` in release builds, keeping the readable source for debug builds. Equivalent
+    /// to the `debug_source` argument.
+    pub fn debug_source(mut self, debug_source: bool) -> Self {
+        self.input.options.debug_source = debug_source;
+        self
+    }
+
+    /// Strips `SOURCE` and shortens the names local to its functions. Equivalent to the `minify` argument.
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.input.options.minify = minify;
+        self
+    }
+
+    /// Renames the functions, private globals and structs in `SOURCE`, as well as the names local to its functions.
+    /// Equivalent to the `obfuscate` argument.
+    pub fn obfuscate(mut self, obfuscate: bool) -> Self {
+        self.input.options.obfuscate = obfuscate;
+        self
+    }
+
+    /// Embeds `SOURCE` compressed, decompressing it the first time it is used. Equivalent to the `compress_source`
+    /// argument.
+    pub fn compress_source(mut self, compress_source: bool) -> Self {
+        self.input.options.compress_source = compress_source;
+        self
+    }
+
+    /// Writes a reflection snapshot of the shader to `OUT_DIR`. Equivalent to the `snapshot` argument.
+    pub fn snapshot(mut self, snapshot: bool) -> Self {
+        self.input.options.snapshot = snapshot;
+        self
+    }
+
+    /// Adds the shader's statistics to the crate's compilation report in `OUT_DIR`. Equivalent to the `report`
+    /// argument.
+    pub fn report(mut self, report: bool) -> Self {
+        self.input.options.report = report;
+        self
+    }
+
+    /// Writes a Makefile-style depfile listing the files the shader is composed from to `OUT_DIR`. Equivalent to the
+    /// `depfile` argument.
+    pub fn depfile(mut self, depfile: bool) -> Self {
+        self.input.options.depfile = depfile;
+        self
+    }
+
+    /// Writes the graph of files that the shader imports, in Graphviz DOT, to `OUT_DIR`. Equivalent to the
+    /// `import_graph` argument.
+    pub fn import_graph(mut self, import_graph: bool) -> Self {
+        self.input.options.import_graph = import_graph;
+        self
+    }
+
+    /// Writes a JSON description of the shader's entry points, bindings, struct layouts, overrides and shader defs to
+    /// `OUT_DIR`. Equivalent to the `reflection_json` argument.
+    pub fn reflection_json(mut self, reflection_json: bool) -> Self {
+        self.input.options.reflection_json = reflection_json;
+        self
+    }
+
+    /// Writes the composed shader to a file, relative to the root of the crate. Equivalent to the `emit_composed`
+    /// argument.
+    pub fn emit_composed(mut self, path: impl AsRef<Path>) -> Self {
+        self.input.options.emit_composed = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Allows a lint, such as `clippy::too_many_lines`, on every generated item. Equivalent to an entry in the
+    /// `lint_allows` argument.
+    pub fn lint_allow(mut self, lint: &str) -> Self {
+        match syn::parse_str::<syn::Path>(lint) {
+            Ok(lint) => self.input.options.lint_allows.push(lint),
+            Err(e) => self
+                .errors
+                .push(format!("`{lint}` is not a lint name: {e}")),
+        }
+        self
+    }
+
+    /// Enables an opt-in validator capability, such as `MULTIVIEW`. Equivalent to an entry in the `capabilities`
+    /// argument.
+    pub fn capability(mut self, capability: &str) -> Self {
+        if !OPT_IN_CAPABILITIES.contains(&capability) {
+            self.errors.push(format!(
+                "unknown capability `{capability}` - expected one of {OPT_IN_CAPABILITIES:?}"
+            ));
+            return self;
+        }
+
+        self.input.options.capabilities.push(capability.to_owned());
+        self
+    }
+
+    /// Rejects anything newer than a WGSL baseline, such as `webgpu-1.0`. Equivalent to the `wgsl_baseline` argument.
+    pub fn wgsl_baseline(mut self, baseline: &str) -> Self {
+        if !WGSL_BASELINES.iter().any(|(name, _)| *name == baseline) {
+            self.errors.push(format!(
+                "unknown WGSL baseline `{baseline}` - expected `webgpu-1.0`"
+            ));
+            return self;
+        }
+
+        self.input.options.wgsl_baseline = Some(baseline.to_owned());
+        self
+    }
+
+    /// Generates a variant of the shader whose vertex entry points fetch their inputs from storage buffers.
+    /// Equivalent to the `vertex_pulling` argument.
+    pub fn vertex_pulling(mut self, vertex_pulling: bool) -> Self {
+        self.input.options.vertex_pulling = vertex_pulling;
+        self
+    }
+
+    /// Precompiles the shader with every combination of a definition and those previously permuted. Equivalent to an
+    /// entry in the `permutations` argument.
+    pub fn permutation(mut self, def: impl Into<String>) -> Self {
+        if self.input.options.permutations.len() >= MAX_PERMUTATION_DEFS {
+            self.errors.push(format!(
+                "at most {MAX_PERMUTATION_DEFS} definitions can be permuted"
+            ));
+            return self;
+        }
+
+        self.input.options.permutations.push(def.into());
+        self
+    }
+
+    /// Allows the shader, its includes and its imports to be outside of the crate's directory. Equivalent to the
+    /// `allow_external_paths` argument.
+    pub fn allow_external_paths(mut self, allow_external_paths: bool) -> Self {
+        self.input.options.allow_external_paths = allow_external_paths;
+        self
+    }
+
+    /// Fails if the composed source takes more than a number of bytes. Equivalent to the `max_source_bytes` argument.
+    pub fn max_source_bytes(mut self, max_source_bytes: usize) -> Self {
+        self.input.options.max_source_bytes = Some(max_source_bytes);
+        self
+    }
+
+    /// Lays exported structs out with `repr_c` or `encase`. Equivalent to the `layout` argument.
+    pub fn layout(mut self, layout: &str) -> Self {
+        match StructLayout::from_name(layout) {
+            Some(layout) => self.input.options.layout = Some(layout),
+            None => self.errors.push(format!(
+                "unknown layout `{layout}` - expected `repr_c` or `encase`"
+            )),
+        }
+        self
+    }
+
+    /// Represents vectors and matrices in exported structs as `arrays`, `glam`, `nalgebra` or `mint` types. Equivalent
+    /// to the `math` argument.
+    pub fn math(mut self, math: &str) -> Self {
+        match Math::from_name(math) {
+            Some(math) => self.input.options.math = Some(math),
+            None => self.errors.push(format!(
+                "unknown math library `{math}` - expected `arrays`, `glam`, `nalgebra` or `mint`"
+            )),
+        }
+        self
+    }
+
+    /// Derives a `serde` trait, `Serialize` or `Deserialize`, for exported structs. Equivalent to an entry in the
+    /// `derives` argument.
+    pub fn derive(mut self, derive: &str) -> Self {
+        if !SERDE_DERIVES.contains(&derive) {
+            self.errors.push(format!(
+                "unknown derive `{derive}` - expected `Serialize` or `Deserialize`"
+            ));
+            return self;
+        }
+        if !cfg!(feature = "serde") {
+            self.errors
+                .push(format!("deriving `{derive}` requires the `serde` feature"));
+            return self;
+        }
+
+        self.input.options.derives.push(derive.to_owned());
+        self
+    }
+
+    /// Derives a trait, such as `Hash`, for generated structs, in place of the default set of `Clone`, `Copy`, `Debug`
+    /// and `PartialEq`. Equivalent to an entry in the `type_derives` argument.
+    pub fn type_derive(mut self, derive: &str) -> Self {
+        match syn::parse_str::<syn::Path>(derive) {
+            Ok(derive) => self
+                .input
+                .options
+                .type_derives
+                .get_or_insert_with(Vec::new)
+                .push(derive),
+            Err(e) => self
+                .errors
+                .push(format!("`{derive}` is not a trait name: {e}")),
+        }
+        self
+    }
+
+    /// Marks exported items with a different attribute than `@export`, given without the `@`, or disables exports with
+    /// `None`. Equivalent to the `export_directive` argument.
+    pub fn export_directive(mut self, directive: Option<&str>) -> Self {
+        self.input.options.export_directive = Some(directive.map(str::to_owned));
+        self
+    }
+
+    /// Composes and validates the shader, giving every error encountered, including invalid arguments, if it is
+    /// invalid.
+    pub fn compose(self) -> Result<ComposedShader, Vec<String>> {
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        // Absolute imports are resolved relative to the crate's source root, as they would be from the macro
+        let Some(invocation_path) = ["src/lib.rs", "src/main.rs"]
+            .into_iter()
+            .map(|path| self.root.join(path))
+            .find(|path| path.is_file())
+        else {
+            return Err(vec![format!(
+                "`{}` has no `src/lib.rs` or `src/main.rs` file to resolve absolute imports from - expected the \
+                root of a crate",
+                self.root.display()
+            )]);
+        };
+
+        let sourcecode = Sourcecode::new(AbsoluteRustFilePathBuf::new(invocation_path), self.input);
+        let mut result = sourcecode.complete();
+
+        result.validate();
+        result.write_artifacts();
+
+        let errors = result.source().errors().cloned().collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(ComposedShader { result })
+    }
+}
+
+/// A shader which has been composed and validated.
+pub struct ComposedShader {
+    result: ShaderResult,
+}
+
+impl ComposedShader {
+    /// The composed module, with the names of imported items decorated by `naga_oil`. Use [`display_name`] to recover
+    /// the names as written in the shader.
+    pub fn module(&self) -> &naga::Module {
+        self.result.module()
+    }
+
+    /// The validation info of the composed module, giving the types of its expressions, as needed to write it back out
+    /// with `naga`'s backends.
+    pub fn info(&self) -> &naga::valid::ModuleInfo {
+        self.result
+            .info()
+            .expect("composed shaders have been validated")
+    }
+
+    /// The composed shader, written as a single WGSL source.
+    pub fn wgsl(&self) -> String {
+        self.result
+            .wgsl_source()
+            .expect("composed shaders have been validated")
+    }
+
+    /// Every file read while composing the shader, so that build scripts can be re-run when any of them change.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        self.result.source().files()
+    }
+
+    /// A canonical textual description of the types, bindings and entry points of the shader, as written by the
+    /// `snapshot` option.
+    pub fn snapshot(&self) -> String {
+        let shader_name = artifacts::crate_relative_path(self.result.source().requested_path());
+        snapshot::snapshot(self.module(), &shader_name.to_string_lossy())
+    }
+
+    /// A JSON description of the entry points, bindings, struct layouts, overrides and shader defs of the shader, as
+    /// written by the `reflection_json` option.
+    pub fn reflection_json(&self) -> String {
+        self.result
+            .reflection_json()
+            .expect("composed shaders have been validated")
+    }
+
+    /// The items that the `include_wgsl_oil` attribute would generate for the shader.
+    pub fn items(&self) -> Vec<syn::Item> {
+        self.result.generated_items()
+    }
+
+    /// The items that the `include_wgsl_oil` attribute would generate for the shader, ready to be written to a file and
+    /// `include!`d.
+    pub fn to_token_stream(&self) -> proc_macro2::TokenStream {
+        let items = self.items();
+        quote! { #(#items)* }
+    }
+
+    /// The items that the `include_wgsl_oil` attribute would generate for the shader, formatted as a Rust source file
+    /// so that generated interfaces can be committed and reviewed.
+    pub fn to_pretty_string(&self) -> String {
+        pretty::unparse(self.items())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing, Shader};
+
+    #[test]
+    fn invalid_arguments_are_returned() {
+        let result = testing::compose(
+            "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(1.0); }",
+            |shader| shader.layout("packed").math("cgmath"),
+        );
+        let Err(errors) = result else {
+            panic!("shaders are composed with invalid arguments");
+        };
+        assert_eq!(
+            errors,
+            [
+                "unknown layout `packed` - expected `repr_c` or `encase`",
+                "unknown math library `cgmath` - expected `arrays`, `glam`, `nalgebra` or `mint`",
+            ]
+        );
+    }
+
+    #[test]
+    fn root_without_crate() {
+        let root = std::env::temp_dir();
+        let Err(errors) = Shader::with_root(&root, "shader.wgsl").compose() else {
+            panic!("shaders are composed outside of a crate");
+        };
+        assert_eq!(
+            errors,
+            [format!(
+                "`{}` has no `src/lib.rs` or `src/main.rs` file to resolve absolute imports from - expected the \
+                root of a crate",
+                root.display()
+            )]
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use crate::includes::Include;
+
+/// WGSL modules shipped with this crate, which any shader can import as `#import wgsl_oil::<name>`.
+const LIBRARY: &[(&str, &str)] = &[
+    ("color", include_str!("library/color.wgsl")),
+    ("fullscreen", include_str!("library/fullscreen.wgsl")),
+    ("noise", include_str!("library/noise.wgsl")),
+    ("tonemapping", include_str!("library/tonemapping.wgsl")),
+];
+
+/// Gives every built-in module in the same form as the modules found through `includes`, keyed by import path.
+pub(crate) fn library_includes() -> impl Iterator<Item = (String, Include)> {
+    LIBRARY.iter().map(|(name, source)| {
+        let (_, reqs, _) = naga_oil::compose::get_preprocessor_data(source);
+        let reqs = reqs.into_iter().map(|req| req.import).collect();
+
+        (
+            format!("wgsl_oil::{name}"),
+            (
+                reqs,
+                PathBuf::from(format!("wgsl_oil/{name}.wgsl")),
+                source.to_string(),
+            ),
+        )
+    })
+}
@@ -0,0 +1,37 @@
+#define_import_path wgsl_oil::color
+
+// Converts an sRGB encoded color to linear color.
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let lower = color / 12.92;
+    let higher = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, color <= vec3<f32>(0.04045));
+}
+
+// Converts a linear color to an sRGB encoded color.
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let lower = color * 12.92;
+    let higher = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, lower, color <= vec3<f32>(0.0031308));
+}
+
+// The relative luminance of a linear color, using the Rec. 709 coefficients.
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Converts an RGB color to hue, saturation and value, each in the range `[0, 1]`.
+fn rgb_to_hsv(color: vec3<f32>) -> vec3<f32> {
+    let k = vec4<f32>(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    let p = select(vec4<f32>(color.bg, k.wz), vec4<f32>(color.gb, k.xy), color.b <= color.g);
+    let q = select(vec4<f32>(p.xyw, color.r), vec4<f32>(color.r, p.yzx), p.x <= color.r);
+    let d = q.x - min(q.w, q.y);
+    let e = 1.0e-10;
+    return vec3<f32>(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+// Converts a hue, saturation and value color, each in the range `[0, 1]`, to RGB.
+fn hsv_to_rgb(color: vec3<f32>) -> vec3<f32> {
+    let k = vec4<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    let p = abs(fract(color.xxx + k.xyz) * 6.0 - k.www);
+    return color.z * mix(k.xxx, saturate(p - k.xxx), color.y);
+}
@@ -0,0 +1,17 @@
+#define_import_path wgsl_oil::fullscreen
+
+struct FullscreenVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Gives a triangle covering the whole screen when drawn with 3 vertices and no vertex buffers, with
+// `uv` ranging over `[0, 1]` across the screen.
+fn fullscreen_triangle(vertex_index: u32) -> FullscreenVertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: FullscreenVertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
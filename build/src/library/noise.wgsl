@@ -0,0 +1,55 @@
+#define_import_path wgsl_oil::noise
+
+// PCG hash of a single value.
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Three dimensional PCG hash, from "Hash Functions for GPU Rendering" (Jarzynski & Olano, 2020).
+fn pcg3d(value: vec3<u32>) -> vec3<u32> {
+    var v = value * 1664525u + 1013904223u;
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    v ^= v >> vec3<u32>(16u);
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    return v;
+}
+
+// Maps a hash to a float in the range `[0, 1]`.
+fn hash_to_unit(hash: u32) -> f32 {
+    return f32(hash) / 4294967295.0;
+}
+
+// Hashes a float to a float in the range `[0, 1]`.
+fn hash11(p: f32) -> f32 {
+    return hash_to_unit(pcg(bitcast<u32>(p)));
+}
+
+// Hashes a 2D point to a float in the range `[0, 1]`.
+fn hash21(p: vec2<f32>) -> f32 {
+    return hash_to_unit(pcg(bitcast<u32>(p.x) ^ pcg(bitcast<u32>(p.y))));
+}
+
+// Hashes a 3D point to a vector with each component in the range `[0, 1]`.
+fn hash33(p: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(pcg3d(bitcast<vec3<u32>>(p))) / 4294967295.0;
+}
+
+// Smoothly interpolated value noise in the range `[0, 1]`.
+fn value_noise2(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+
+    let a = hash21(i);
+    let b = hash21(i + vec2<f32>(1.0, 0.0));
+    let c = hash21(i + vec2<f32>(0.0, 1.0));
+    let d = hash21(i + vec2<f32>(1.0, 1.0));
+
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
@@ -0,0 +1,30 @@
+#define_import_path wgsl_oil::tonemapping
+
+#import wgsl_oil::color::luminance
+
+// Simple Reinhard tonemapping, applied per channel.
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Reinhard tonemapping where `white` is the smallest value that is mapped to pure white.
+fn reinhard_extended(color: vec3<f32>, white: f32) -> vec3<f32> {
+    let numerator = color * (1.0 + color / vec3<f32>(white * white));
+    return numerator / (1.0 + color);
+}
+
+// Reinhard tonemapping applied to luminance, preserving hue.
+fn reinhard_luminance(color: vec3<f32>) -> vec3<f32> {
+    let l = luminance(color);
+    return color / (1.0 + l);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic tonemapping curve.
+fn aces_filmic(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return saturate((color * (a * color + b)) / (color * (c * color + d) + e));
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use quote::{format_ident, quote};
+
+use crate::{bindings, dispatch, reflect};
+
+/// Finds the `@location` inputs or outputs given by a value of the given type, as `(location, components)` pairs,
+/// looking through structs.
+fn locations(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+    binding: Option<&naga::Binding>,
+) -> Vec<(u32, u32)> {
+    let components = match module.types[ty].inner {
+        naga::TypeInner::Vector { size, .. } => size as u32,
+        _ => 1,
+    };
+    match (binding, &module.types[ty].inner) {
+        (Some(naga::Binding::Location { location, .. }), _) => vec![(*location, components)],
+        (None, naga::TypeInner::Struct { members, .. }) => members
+            .iter()
+            .flat_map(|member| locations(module, member.ty, member.binding.as_ref()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn input_locations(module: &naga::Module, entry_point: &naga::EntryPoint) -> Vec<(u32, u32)> {
+    entry_point
+        .function
+        .arguments
+        .iter()
+        .flat_map(|argument| locations(module, argument.ty, argument.binding.as_ref()))
+        .collect()
+}
+
+fn output_locations(module: &naga::Module, entry_point: &naga::EntryPoint) -> Vec<(u32, u32)> {
+    entry_point
+        .function
+        .result
+        .iter()
+        .flat_map(|result| locations(module, result.ty, result.binding.as_ref()))
+        .collect()
+}
+
+/// Finds the smallest value of each `wgpu::Limits` field that a device needs to create pipelines from the module, by
+/// field name. Fields which the module doesn't depend on are omitted.
+pub(crate) fn required_limits(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> BTreeMap<&'static str, u32> {
+    let mut limits = BTreeMap::new();
+    let mut require = |limit: &'static str, value: u32| {
+        if value > 0 {
+            let required = limits.entry(limit).or_insert(0);
+            *required = (*required).max(value);
+        }
+    };
+    let layouter = reflect::layouter(module);
+
+    // Bind groups, and the resources bound to each stage
+    let bindings = bindings::bindings(module);
+    for binding in &bindings {
+        require("max_bind_groups", binding.group + 1);
+        require("max_bindings_per_bind_group", binding.binding + 1);
+        match binding.global.space {
+            naga::AddressSpace::Uniform => require(
+                "max_uniform_buffer_binding_size",
+                layouter[binding.resource_ty(module)].size,
+            ),
+            naga::AddressSpace::Storage { .. } => require(
+                "max_storage_buffer_binding_size",
+                layouter[binding.resource_ty(module)].size,
+            ),
+            _ => {}
+        }
+    }
+    for stage in [
+        naga::ShaderStage::Vertex,
+        naga::ShaderStage::Fragment,
+        naga::ShaderStage::Compute,
+    ] {
+        let mut counts = BTreeMap::new();
+        for binding in &bindings {
+            if !binding.stages(module, info).contains(&stage) {
+                continue;
+            }
+            let limit = match (
+                binding.global.space,
+                &module.types[binding.resource_ty(module)].inner,
+            ) {
+                (naga::AddressSpace::Uniform, _) => "max_uniform_buffers_per_shader_stage",
+                (naga::AddressSpace::Storage { .. }, _) => "max_storage_buffers_per_shader_stage",
+                (
+                    _,
+                    naga::TypeInner::Image {
+                        class: naga::ImageClass::Storage { .. },
+                        ..
+                    },
+                ) => "max_storage_textures_per_shader_stage",
+                (_, naga::TypeInner::Image { .. }) => "max_sampled_textures_per_shader_stage",
+                (_, naga::TypeInner::Sampler { .. }) => "max_samplers_per_shader_stage",
+                _ => continue,
+            };
+            // Runtime-sized binding arrays need at least one resource
+            *counts.entry(limit).or_insert(0) += binding.array_size(module).flatten().unwrap_or(1);
+        }
+        for (limit, count) in counts {
+            require(limit, count);
+        }
+    }
+
+    for (index, entry_point) in module.entry_points.iter().enumerate() {
+        match entry_point.stage {
+            naga::ShaderStage::Vertex => {
+                require(
+                    "max_vertex_attributes",
+                    input_locations(module, entry_point).len() as u32,
+                );
+                require(
+                    "max_inter_stage_shader_components",
+                    output_locations(module, entry_point)
+                        .iter()
+                        .map(|(_, components)| components)
+                        .sum(),
+                );
+            }
+            naga::ShaderStage::Fragment => {
+                require(
+                    "max_inter_stage_shader_components",
+                    input_locations(module, entry_point)
+                        .iter()
+                        .map(|(_, components)| components)
+                        .sum(),
+                );
+                require(
+                    "max_color_attachments",
+                    output_locations(module, entry_point)
+                        .iter()
+                        .map(|(location, _)| location + 1)
+                        .max()
+                        .unwrap_or(0),
+                );
+            }
+            naga::ShaderStage::Compute => {
+                if let Some([x, y, z]) = dispatch::workgroup_size(entry_point) {
+                    require("max_compute_workgroup_size_x", x);
+                    require("max_compute_workgroup_size_y", y);
+                    require("max_compute_workgroup_size_z", z);
+                    require("max_compute_invocations_per_workgroup", x * y * z);
+                }
+                let entry_point_info = info.get_entry_point(index);
+                require(
+                    "max_compute_workgroup_storage_size",
+                    module
+                        .global_variables
+                        .iter()
+                        .filter(|(handle, global)| {
+                            global.space == naga::AddressSpace::WorkGroup
+                                && !entry_point_info[*handle].is_empty()
+                        })
+                        .map(|(_, global)| layouter[global.ty].size)
+                        .sum(),
+                );
+            }
+        }
+    }
+
+    for (_, global) in module.global_variables.iter() {
+        if global.space == naga::AddressSpace::PushConstant {
+            require("max_push_constant_size", layouter[global.ty].size);
+        }
+    }
+
+    limits
+}
+
+/// Generates, with the `wgpu` feature, a `required_limits` function giving the `wgpu` limits needed by the shader.
+pub(crate) fn limit_items(module: &naga::Module, info: &naga::valid::ModuleInfo) -> Vec<syn::Item> {
+    if !cfg!(feature = "wgpu") {
+        return Vec::new();
+    }
+
+    let fields = required_limits(module, info)
+        .into_iter()
+        .map(|(limit, value)| {
+            let limit = format_ident!("{}", limit);
+            quote! { #limit: base.#limit.max(#value) }
+        });
+
+    vec![syn::parse_quote! {
+        /// The `wgpu` limits that a device needs to create pipelines from this shader: WebGL2's downlevel defaults,
+        /// raised where the shader needs more, e.g. for its bind groups, workgroup size or push constants.
+        pub fn required_limits() -> ::wgpu::Limits {
+            let base = ::wgpu::Limits::downlevel_webgl2_defaults();
+            ::wgpu::Limits {
+                #(#fields,)*
+                ..base
+            }
+        }
+    }]
+}
@@ -0,0 +1,49 @@
+/// Gives the name of a named item, i.e. anything but `impl` blocks, macros and `use`s.
+fn item_ident(item: &syn::Item) -> Option<&syn::Ident> {
+    match item {
+        syn::Item::Const(item) => Some(&item.ident),
+        syn::Item::Enum(item) => Some(&item.ident),
+        syn::Item::Fn(item) => Some(&item.sig.ident),
+        syn::Item::Mod(item) => Some(&item.ident),
+        syn::Item::Static(item) => Some(&item.ident),
+        syn::Item::Struct(item) => Some(&item.ident),
+        syn::Item::Trait(item) => Some(&item.ident),
+        syn::Item::Type(item) => Some(&item.ident),
+        syn::Item::Union(item) => Some(&item.ident),
+        _ => None,
+    }
+}
+
+/// Adds our reflection to the items generated so far. Modules which already exist, e.g. those generated by
+/// `naga-to-tokenstream`, are merged into rather than duplicated, and where both give an item of the same name the
+/// existing one is kept.
+pub(crate) fn merge(items: &mut Vec<syn::Item>, new_items: Vec<syn::Item>) {
+    for new_item in new_items {
+        let Some(ident) = item_ident(&new_item) else {
+            items.push(new_item);
+            continue;
+        };
+
+        let Some(existing) = items
+            .iter_mut()
+            .find(|item| item_ident(item) == Some(ident))
+        else {
+            items.push(new_item);
+            continue;
+        };
+
+        if let (
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, existing_items)),
+                ..
+            }),
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, module_items)),
+                ..
+            }),
+        ) = (existing, new_item)
+        {
+            merge(existing_items, module_items);
+        }
+    }
+}
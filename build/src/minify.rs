@@ -0,0 +1,394 @@
+use std::collections::HashSet;
+
+/// Whether a character can be part of an identifier or a number, which can't be joined to its neighbours of the same
+/// kind.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether a character can be part of an operator, which can't be joined to its neighbours of the same kind, e.g.
+/// `- -x` or the `>` closing a template before `=`.
+fn is_operator_char(c: char) -> bool {
+    "+-*/%&|^<>=!".contains(c)
+}
+
+/// Removes comments and all whitespace except that separating two words or two operators.
+pub(crate) fn strip(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut separated = false;
+    while let Some(c) = chars.next() {
+        if c == '/' && chars.peek() == Some(&'/') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            separated = true;
+            continue;
+        }
+        if c == '/' && chars.peek() == Some(&'*') {
+            // Block comments nest in WGSL
+            chars.next();
+            let mut depth = 1;
+            while depth > 0 {
+                match (chars.next(), chars.peek()) {
+                    (Some('/'), Some('*')) => {
+                        chars.next();
+                        depth += 1;
+                    }
+                    (Some('*'), Some('/')) => {
+                        chars.next();
+                        depth -= 1;
+                    }
+                    (None, _) => break,
+                    _ => {}
+                }
+            }
+            separated = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            separated = true;
+            continue;
+        }
+
+        if separated {
+            if let Some(prev) = out.chars().next_back() {
+                if (is_word_char(prev) && is_word_char(c))
+                    || (is_operator_char(prev) && is_operator_char(c))
+                {
+                    out.push(' ');
+                }
+            }
+            separated = false;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Gives short names which aren't taken, reserved by WGSL or themselves already given: `a` to `Z`, then `aa` and so on.
+struct ShortNames {
+    taken: HashSet<String>,
+    next: usize,
+}
+
+impl ShortNames {
+    const LETTERS: &'static [u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+    fn next_name(&mut self) -> String {
+        loop {
+            let mut index = self.next;
+            self.next += 1;
+
+            let mut name = Vec::new();
+            loop {
+                name.push(Self::LETTERS[index % Self::LETTERS.len()]);
+                index /= Self::LETTERS.len();
+                if index == 0 {
+                    break;
+                }
+                index -= 1;
+            }
+            name.reverse();
+            let name = String::from_utf8(name).expect("letters are ASCII");
+
+            if !self.taken.contains(&name)
+                && !naga::keywords::wgsl::RESERVED.contains(&name.as_str())
+            {
+                return name;
+            }
+        }
+    }
+}
+
+fn shorten_function_names(function: &mut naga::Function, names: &mut ShortNames) {
+    for argument in &mut function.arguments {
+        if argument.name.is_some() {
+            argument.name = Some(names.next_name());
+        }
+    }
+    for (_, local) in function.local_variables.iter_mut() {
+        if local.name.is_some() {
+            local.name = Some(names.next_name());
+        }
+    }
+    for name in function.named_expressions.values_mut() {
+        *name = names.next_name();
+    }
+}
+
+/// Gives every argument, local variable and named expression of the module's functions and entry points a short name.
+/// Names which are visible outside of a function are kept. `naga` keeps the names it writes unique across the whole
+/// module, so the short names are too, rather than starting again for each function.
+pub(crate) fn shorten_names(module: &mut naga::Module) {
+    let taken = module
+        .types
+        .iter()
+        .filter_map(|(_, ty)| ty.name.clone())
+        .chain(module.constants.iter().filter_map(|(_, c)| c.name.clone()))
+        .chain(module.overrides.iter().filter_map(|(_, o)| o.name.clone()))
+        .chain(
+            module
+                .global_variables
+                .iter()
+                .filter_map(|(_, g)| g.name.clone()),
+        )
+        .chain(module.functions.iter().filter_map(|(_, f)| f.name.clone()))
+        .chain(
+            module
+                .entry_points
+                .iter()
+                .map(|entry_point| entry_point.name.clone()),
+        )
+        .collect();
+    let mut names = ShortNames { taken, next: 0 };
+
+    for (_, function) in module.functions.iter_mut() {
+        shorten_function_names(function, &mut names);
+    }
+    for entry_point in &mut module.entry_points {
+        shorten_function_names(&mut entry_point.function, &mut names);
+    }
+}
+
+/// Gives short names to the module's functions other than entry points and `exported_functions`, its constants, its
+/// globals which aren't bound to resources, and its structs and their members. Entry points and overrides are looked up
+/// by name from the host, so are kept, as are exported functions, whose names are reflected, resources, which `wgpu`
+/// names in binding errors, and the types that `naga` declares itself, which are written as WGSL's own types.
+pub(crate) fn obfuscate_names(module: &mut naga::Module, exported_functions: &HashSet<String>) {
+    let special_types = module
+        .special_types
+        .ray_desc
+        .iter()
+        .chain(&module.special_types.ray_intersection)
+        .chain(module.special_types.predeclared_types.values())
+        .copied()
+        .collect::<HashSet<_>>();
+    let is_kept = |global: &naga::GlobalVariable| {
+        global.binding.is_some() || global.space == naga::AddressSpace::PushConstant
+    };
+
+    let taken = module
+        .overrides
+        .iter()
+        .filter_map(|(_, o)| o.name.clone())
+        .chain(
+            module
+                .global_variables
+                .iter()
+                .filter(|(_, g)| is_kept(g))
+                .filter_map(|(_, g)| g.name.clone()),
+        )
+        .chain(
+            module
+                .entry_points
+                .iter()
+                .map(|entry_point| entry_point.name.clone()),
+        )
+        .chain(exported_functions.iter().cloned())
+        .chain(
+            module
+                .types
+                .iter()
+                .filter(|(handle, _)| special_types.contains(handle))
+                .filter_map(|(_, ty)| ty.name.clone()),
+        )
+        .collect();
+    let mut names = ShortNames { taken, next: 0 };
+
+    for (_, function) in module.functions.iter_mut() {
+        if function
+            .name
+            .as_ref()
+            .is_some_and(|name| !exported_functions.contains(name))
+        {
+            function.name = Some(names.next_name());
+        }
+    }
+    for (_, constant) in module.constants.iter_mut() {
+        if constant.name.is_some() {
+            constant.name = Some(names.next_name());
+        }
+    }
+    for (_, global) in module.global_variables.iter_mut() {
+        if global.name.is_some() && !is_kept(global) {
+            global.name = Some(names.next_name());
+        }
+    }
+
+    let structs = module
+        .types
+        .iter()
+        .filter(|(handle, ty)| {
+            !special_types.contains(handle) && matches!(ty.inner, naga::TypeInner::Struct { .. })
+        })
+        .map(|(handle, _)| handle)
+        .collect::<Vec<_>>();
+    for handle in structs {
+        let mut ty = module.types[handle].clone();
+        if ty.name.is_some() {
+            ty.name = Some(names.next_name());
+        }
+        if let naga::TypeInner::Struct { members, .. } = &mut ty.inner {
+            // Members only need to be unique within their struct
+            let mut member_names = ShortNames {
+                taken: HashSet::new(),
+                next: 0,
+            };
+            for member in members {
+                if member.name.is_some() {
+                    member.name = Some(member_names.next_name());
+                }
+            }
+        }
+        module.types.replace(handle, ty);
+    }
+}
+
+/// Replaces the `SOURCE` constant among `items` with `debug` in debug builds and `release` in release builds, or with
+/// either if they are the same.
+pub(crate) fn replace_source(items: &mut Vec<syn::Item>, debug: &str, release: &str) {
+    let Some(index) = items.iter().position(
+        |item| matches!(item, syn::Item::Const(item_const) if item_const.ident == "SOURCE"),
+    ) else {
+        return;
+    };
+    let syn::Item::Const(item_const) = &mut items[index] else {
+        unreachable!("the item was matched as a const");
+    };
+
+    *item_const.expr = syn::parse_quote! { #debug };
+    if debug == release {
+        return;
+    }
+
+    let docs = item_const
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .cloned()
+        .collect::<Vec<_>>();
+    item_const
+        .attrs
+        .push(syn::parse_quote! { #[cfg(debug_assertions)] });
+    items.insert(
+        index + 1,
+        syn::parse_quote! {
+            #(#docs)*
+            #[cfg(not(debug_assertions))]
+            pub const SOURCE: &str = #release;
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(source: &str) -> (naga::Module, naga::valid::ModuleInfo) {
+        let module = naga::front::wgsl::parse_str(source)
+            .unwrap_or_else(|e| panic!("{}\n{source}", e.emit_to_string(source)));
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{}\n{source}", e.emit_to_string(source)));
+        (module, info)
+    }
+
+    #[test]
+    fn obfuscate_keeps_exported_functions() {
+        let (mut module, info) = validate(
+            "fn luminance(color: vec3<f32>) -> f32 { return dot(color, vec3(0.2126, 0.7152, 0.0722)); }
+            fn helper(x: f32) -> f32 { return x * 2.0; }
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(luminance(vec3(helper(1.0)))); }",
+        );
+        obfuscate_names(&mut module, &HashSet::from(["luminance".to_owned()]));
+        let source = crate::writer::write_wgsl(&module, &info).unwrap();
+
+        validate(&source);
+        assert!(source.contains("fn luminance("));
+        assert!(!source.contains("helper"));
+        assert!(source.contains("fn fs_main("));
+    }
+
+    const SHADER: &str = "
+        struct Light { position: vec3<f32>, intensity: f32, color: vec4<f32> }
+        struct Output { @builtin(position) position: vec4<f32>, @location(0) color: vec4<f32> }
+        const LIGHT_COUNT: u32 = 4u;
+        override exposure: f32 = 1.0;
+        @group(0) @binding(0) var<uniform> lights: array<Light, LIGHT_COUNT>;
+        var<private> seed: u32 = 7u;
+
+        fn attenuation(distance: f32, falloff: f32) -> f32 {
+            let scaled = distance * falloff;
+            return 1.0 / max(scaled * scaled, 0.0001);
+        }
+
+        fn next_random() -> u32 {
+            seed = (seed ^ (seed << 13u)) ^ (seed >> 7u);
+            return seed;
+        }
+
+        @vertex fn vs_main(@builtin(vertex_index) index: u32) -> Output {
+            var out: Output;
+            var total = vec3<f32>(0.0);
+            for (var i = 0u; i < LIGHT_COUNT; i++) {
+                let light = lights[i];
+                let offset = light.position - vec3(f32(index), -1.0, f32(next_random() % 3u));
+                total += light.color.rgb * light.intensity * attenuation(length(offset), 0.5);
+            }
+            out.position = vec4(total, 1.0);
+            out.color = vec4(total * exposure, 1.0);
+            return out;
+        }
+
+        @fragment fn fs_main(in: Output) -> @location(0) vec4<f32> {
+            return in.color;
+        }
+    ";
+
+    /// Processes [`SHADER`] as the source is processed for release builds, checking that the result validates.
+    fn release_source(minify: bool, obfuscate: bool) -> String {
+        let (mut module, info) = validate(SHADER);
+        if obfuscate {
+            obfuscate_names(&mut module, &HashSet::new());
+        }
+        shorten_names(&mut module);
+        let source = crate::writer::write_wgsl(&module, &info).unwrap();
+        let source = if minify { strip(&source) } else { source };
+        validate(&source);
+        source
+    }
+
+    #[test]
+    fn minified_source_validates() {
+        let source = release_source(true, false);
+        assert!(!source.contains('\n'), "{source}");
+        assert!(source.contains("fn attenuation("), "{source}");
+        assert!(source.contains("struct Light"), "{source}");
+        assert!(!source.contains("distance"), "{source}");
+    }
+
+    #[test]
+    fn obfuscated_source_validates() {
+        let source = release_source(false, true);
+        assert!(!source.contains("attenuation"), "{source}");
+        assert!(!source.contains("Light"), "{source}");
+        assert!(source.contains("fn vs_main("), "{source}");
+        assert!(source.contains("override exposure"), "{source}");
+        assert!(source.contains(" lights:"), "{source}");
+    }
+
+    #[test]
+    fn minified_and_obfuscated_source_validates() {
+        let source = release_source(true, true);
+        assert!(!source.contains('\n'), "{source}");
+        assert!(!source.contains("attenuation"), "{source}");
+        assert!(source.contains("fn fs_main("), "{source}");
+        assert!(source.len() < release_source(true, false).len());
+    }
+}
@@ -0,0 +1,60 @@
+use naga_oil::compose::ShaderDefValue;
+use quote::quote;
+
+use crate::{artifacts, source::Sourcecode};
+
+/// Gives the tokens constructing a shader def value with `include-wgsl-oil-runtime`.
+pub(crate) fn shader_def_value(value: ShaderDefValue) -> proc_macro2::TokenStream {
+    match value {
+        ShaderDefValue::Bool(value) => {
+            quote! { ::include_wgsl_oil_runtime::ShaderDefValue::Bool(#value) }
+        }
+        ShaderDefValue::Int(value) => {
+            quote! { ::include_wgsl_oil_runtime::ShaderDefValue::Int(#value) }
+        }
+        ShaderDefValue::UInt(value) => {
+            quote! { ::include_wgsl_oil_runtime::ShaderDefValue::UInt(#value) }
+        }
+    }
+}
+
+/// Generates, for `mode = runtime`, the preprocessed source of the shader file, the shader defs it is composed with
+/// by default, and a `compose` function recomposing it with `include-wgsl-oil-runtime`, from those and the modules
+/// embedded under `imports`.
+pub(crate) fn runtime_items(source: &Sourcecode) -> Vec<syn::Item> {
+    let root_source = source.root_source();
+    let path = artifacts::crate_relative_path(source.requested_path());
+    let path = path.to_string_lossy();
+    let (names, values): (Vec<_>, Vec<_>) = source
+        .default_shader_defs()
+        .into_iter()
+        .map(|(name, value)| (name, shader_def_value(value)))
+        .unzip();
+
+    vec![
+        syn::parse_quote! {
+            /// The shader file as it is passed to the composer, with `@export` directives removed and imports by path
+            /// renamed to match `imports`.
+            pub const ROOT_SOURCE: &str = #root_source;
+        },
+        syn::parse_quote! {
+            /// The shader defs that the shader is composed with by default, which `SOURCE` was composed with.
+            pub const SHADER_DEFS: &[(&str, ::include_wgsl_oil_runtime::ShaderDefValue)] = &[#((#names, #values)),*];
+        },
+        syn::parse_quote! {
+            /// Composes the shader at runtime with `naga_oil`, with `defs` added to `SHADER_DEFS`, replacing any of the
+            /// same name, so that permutations can be chosen at startup, e.g. from quality settings. The composition
+            /// with `SHADER_DEFS` alone is validated while compiling, and is `SOURCE`.
+            pub fn compose(
+                defs: &::std::collections::HashMap<::std::string::String, ::include_wgsl_oil_runtime::ShaderDefValue>,
+            ) -> ::core::result::Result<::std::string::String, ::include_wgsl_oil_runtime::ComposeError> {
+                let mut shader_defs = SHADER_DEFS
+                    .iter()
+                    .map(|(name, value)| (::std::string::String::from(*name), *value))
+                    .collect::<::std::collections::HashMap<_, _>>();
+                shader_defs.extend(defs.iter().map(|(name, value)| (name.clone(), *value)));
+                ::include_wgsl_oil_runtime::compose(#path, imports::MODULES, ROOT_SOURCE, shader_defs)
+            }
+        },
+    ]
+}
@@ -18,6 +18,14 @@ pub(crate) struct OwnedComposableModuleDescriptor {
 }
 
 impl OwnedComposableModuleDescriptor {
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub(crate) fn as_name(&self) -> &str {
+        &self.as_name
+    }
+
     pub(crate) fn borrow_composable_descriptor(&self) -> ComposableModuleDescriptor<'_> {
         ComposableModuleDescriptor {
             source: &self.source,
@@ -37,6 +45,10 @@ pub(crate) struct OwnedNagaModuleDescriptor {
 }
 
 impl OwnedNagaModuleDescriptor {
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
     pub(crate) fn borrow_module_descriptor(&self) -> NagaModuleDescriptor<'_> {
         NagaModuleDescriptor {
             source: &self.source,
@@ -102,6 +114,7 @@ impl Module {
         module_names: &HashMap<Module, String>,
         source_root: Option<&AbsoluteRustRootPathBuf>,
         definitions: HashMap<String, ShaderDefValue>,
+        export_directive: Option<&str>,
     ) -> Result<OwnedComposableModuleDescriptor, Vec<String>> {
         let source = self.read_to_string();
 
@@ -114,7 +127,7 @@ impl Module {
         }
 
         // Replace `@export` directives with equivalent whitespace
-        let (source, _) = exports::strip_exports(&source);
+        let (source, _) = exports::strip_exports(&source, export_directive);
 
         // Replace `#import` names with substitutions
         let source = imports::replace_imports_in_source(&source, self, source_root, module_names);
@@ -133,11 +146,12 @@ impl Module {
         module_names: &HashMap<Module, String>,
         source_root: Option<&AbsoluteRustRootPathBuf>,
         definitions: HashMap<String, ShaderDefValue>,
+        export_directive: Option<&str>,
     ) -> Result<OwnedNagaModuleDescriptor, Vec<String>> {
         let source = self.read_to_string();
 
         // Replace `@export` directives with equivalent whitespace
-        let (source, _) = exports::strip_exports(&source);
+        let (source, _) = exports::strip_exports(&source, export_directive);
 
         // Replace `#import` names with substitutions
         let source = imports::replace_imports_in_source(&source, self, source_root, module_names);
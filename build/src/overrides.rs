@@ -0,0 +1,202 @@
+use std::collections::{btree_map::Entry, BTreeMap};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{reflect, structs::Math, values::ValueWriter};
+
+/// Gives the key that `wgpu` looks an override's value up by: its `@id` if it has one, otherwise its name.
+pub(crate) fn override_key(override_: &naga::Override) -> String {
+    match override_.id {
+        Some(id) => id.to_string(),
+        None => override_.name.clone().unwrap_or_default(),
+    }
+}
+
+/// Finds overrides which share an `@id`, which can happen when they are merged from different library modules, as
+/// errors naming each pair.
+pub(crate) fn duplicate_ids(module: &naga::Module) -> Vec<String> {
+    let mut ids = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (_, override_) in module.overrides.iter() {
+        let (Some(id), Some(name)) = (override_.id, &override_.name) else {
+            continue;
+        };
+        let name = reflect::display_name(name);
+        match ids.entry(id) {
+            Entry::Vacant(entry) => {
+                entry.insert(name);
+            }
+            Entry::Occupied(entry) => errors.push(format!(
+                "overrides `{}` and `{name}` both have `@id({id})`, but ids must be unique",
+                entry.get()
+            )),
+        }
+    }
+    errors
+}
+
+/// Gives the Rust type that an override is set with, and the conversion of a `value` of that type to the `f64` that
+/// `wgpu` takes.
+fn override_type(
+    module: &naga::Module,
+    override_: &naga::Override,
+) -> Option<(TokenStream, TokenStream)> {
+    let naga::TypeInner::Scalar(scalar) = module.types[override_.ty].inner else {
+        return None;
+    };
+    Some(match (scalar.kind, scalar.width) {
+        (naga::ScalarKind::Bool, _) => (quote! { bool }, quote! { f64::from(u8::from(value)) }),
+        (naga::ScalarKind::Sint, 4) => (quote! { i32 }, quote! { f64::from(value) }),
+        (naga::ScalarKind::Uint, 4) => (quote! { u32 }, quote! { f64::from(value) }),
+        (naga::ScalarKind::Float, 2 | 4) => (quote! { f32 }, quote! { f64::from(value) }),
+        (naga::ScalarKind::Float, 8) => (quote! { f64 }, quote! { value }),
+        _ => return None,
+    })
+}
+
+/// Generates an `overrides` module with a submodule for every override, giving its name, `@id`, key, WGSL type and
+/// the value given in the shader, where it is a constant rather than computed from other overrides.
+fn reflection_items(module: &naga::Module) -> syn::Item {
+    let writer = ValueWriter {
+        module,
+        math: Math::Arrays,
+        layout: None,
+    };
+    let named = module
+        .overrides
+        .iter()
+        .filter_map(|(_, override_)| Some((override_, override_.name.as_deref()?)))
+        .collect::<Vec<_>>();
+    let override_modules = named.iter().map(|(override_, name)| {
+        let ident = reflect::item_ident(name);
+        let display_name = reflect::display_name(name);
+        let id = match override_.id {
+            Some(id) => quote! { Some(#id) },
+            None => quote! { None },
+        };
+        let key = override_key(override_);
+        let type_name = reflect::type_name(module, override_.ty);
+        let default = override_type(module, override_).map(|(ty, _)| {
+            let value = match override_
+                .init
+                .and_then(|init| writer.value(init, override_.ty).ok())
+            {
+                Some(value) => quote! { Some(#value) },
+                None => quote! { None },
+            };
+            quote! {
+                /// The value given in the shader, or `None` if there isn't one or it is computed from other overrides.
+                pub const DEFAULT: Option<#ty> = #value;
+            }
+        });
+
+        quote! {
+            pub mod #ident {
+                /// The name of the override in the shader.
+                pub const NAME: &str = #display_name;
+                /// The override's `@id`, if it has one.
+                pub const ID: Option<u16> = #id;
+                /// The key that `wgpu` looks the override's value up by: its `@id` if it has one, otherwise its name.
+                pub const KEY: &str = #key;
+                /// The override's type, as written in WGSL.
+                pub const TYPE: &str = #type_name;
+                #default
+            }
+        }
+    });
+    let names = named
+        .iter()
+        .map(|(_, name)| reflect::display_name(name).into_owned());
+
+    syn::parse_quote! {
+        /// The pipeline-overridable constants of the shader, named after their declarations.
+        pub mod overrides {
+            /// The names of the overrides, in the order they appear in the composed shader.
+            pub const NAMES: &[&str] = &[#(#names),*];
+            #(#override_modules)*
+        }
+    }
+}
+
+/// Generates an `Overrides` builder with a setter for each of the module's pipeline-overridable constants, taking a
+/// value of the override's type, which collects the constants map passed to `wgpu` when creating a pipeline, and the
+/// mapping from override ids to names, along with the reflection of each override.
+pub(crate) fn override_items(module: &naga::Module) -> Vec<syn::Item> {
+    if module.overrides.is_empty() {
+        return Vec::new();
+    }
+
+    let setters = module.overrides.iter().filter_map(|(_, override_)| {
+        let name = override_.name.as_deref()?;
+        let (ty, value) = override_type(module, override_)?;
+
+        let ident = reflect::item_ident(name);
+        let key = override_key(override_);
+        let doc = format!(
+            " Sets the `{name}: {}` override.",
+            reflect::type_name(module, override_.ty)
+        );
+        Some(quote! {
+            #[doc = #doc]
+            pub fn #ident(mut self, value: #ty) -> Self {
+                self.constants.insert(#key.to_owned(), #value);
+                self
+            }
+        })
+    });
+
+    let mut ids = module
+        .overrides
+        .iter()
+        .filter_map(|(_, override_)| Some((override_.id?, override_.name.as_deref()?)))
+        .map(|(id, name)| (id, reflect::display_name(name).into_owned()))
+        .collect::<Vec<_>>();
+    ids.sort();
+    let ids = ids.iter().map(|(id, name)| quote! { (#id, #name) });
+
+    let compilation_options = cfg!(feature = "wgpu").then(|| {
+        quote! {
+            /// The compilation options setting the collected overrides, to be used when creating a pipeline.
+            pub fn compilation_options(&self) -> ::wgpu::PipelineCompilationOptions<'_> {
+                ::wgpu::PipelineCompilationOptions {
+                    constants: &self.constants,
+                    ..::core::default::Default::default()
+                }
+            }
+        }
+    });
+
+    vec![
+        reflection_items(module),
+        syn::parse_quote! {
+            /// The names of the overrides with an `@id`, by id in ascending order.
+            pub const OVERRIDE_IDS: &[(u16, &str)] = &[#(#ids),*];
+        },
+        syn::parse_quote! {
+            /// Collects values for the shader's pipeline-overridable constants, keyed as `wgpu` expects. Overrides
+            /// which aren't set keep the value given in the shader.
+            #[derive(Clone, Debug, Default)]
+            pub struct Overrides {
+                constants: ::std::collections::HashMap<::std::string::String, f64>,
+            }
+        },
+        syn::parse_quote! {
+            impl Overrides {
+                /// Creates a builder with no overrides set.
+                pub fn new() -> Self {
+                    Self::default()
+                }
+
+                #(#setters)*
+
+                /// The collected overrides, as the constants map of `wgpu::PipelineCompilationOptions`.
+                pub fn constants(&self) -> &::std::collections::HashMap<::std::string::String, f64> {
+                    &self.constants
+                }
+
+                #compilation_options
+            }
+        },
+    ]
+}
@@ -0,0 +1,268 @@
+use quote::{format_ident, quote};
+
+use crate::{bindings, docs::DocComments, push_constants, reflect, vertex};
+
+/// Finds the bind groups that the shader uses, in ascending order, or `None` if any group's layout can't be created up
+/// front.
+fn layout_groups(module: &naga::Module) -> Option<Vec<u32>> {
+    let layouter = reflect::layouter(module);
+    let bindings = bindings::bindings(module);
+    let mut groups = bindings
+        .iter()
+        .map(|binding| binding.group)
+        .collect::<Vec<_>>();
+    groups.dedup();
+    for group in &groups {
+        let in_group = bindings
+            .iter()
+            .filter(|binding| binding.group == *group)
+            .collect::<Vec<_>>();
+        if !bindings::group_has_layout(module, &layouter, &in_group) {
+            return None;
+        }
+    }
+    Some(groups)
+}
+
+/// Generates, with the `wgpu` feature, a `create_pipeline_layout` helper creating the layout of every bind group the
+/// shader uses, in order, along with the push constant ranges of its push constants. It isn't generated if any group's
+/// layout can't be created up front.
+pub(crate) fn pipeline_layout_items(module: &naga::Module) -> Vec<syn::Item> {
+    if !cfg!(feature = "wgpu") {
+        return Vec::new();
+    }
+
+    let Some(groups) = layout_groups(module) else {
+        return Vec::new();
+    };
+
+    // Groups which aren't used, but come before one which is, are given empty layouts
+    let group_count = groups.last().map_or(0, |group| group + 1);
+    let layouts = (0..group_count).map(|group| {
+        if groups.contains(&group) {
+            let ident = format_ident!("group_{}", group);
+            quote! { bindings::#ident::create_bind_group_layout(device) }
+        } else {
+            quote! {
+                device.create_bind_group_layout(&::wgpu::BindGroupLayoutDescriptor {
+                    label: Some(concat!(module_path!(), "::empty")),
+                    entries: &[],
+                })
+            }
+        }
+    });
+    let layouts = quote! { [#(#layouts),*] };
+    let bind_group_layouts = if group_count == 0 {
+        quote! { &[] }
+    } else {
+        quote! { &#layouts.each_ref() }
+    };
+    let ranges = push_constants::push_constants(module)
+        .into_iter()
+        .map(|(_, global)| {
+            let ident = reflect::item_ident(global.name.as_deref().unwrap_or_default());
+            quote! { push_constants::#ident::RANGE }
+        });
+
+    vec![syn::parse_quote! {
+        /// Creates a pipeline layout from the layout of every bind group the shader uses, with empty layouts for groups
+        /// it skips, and the push constant ranges of its push constants, labelled with the path of this module.
+        pub fn create_pipeline_layout(device: &::wgpu::Device) -> ::wgpu::PipelineLayout {
+            device.create_pipeline_layout(&::wgpu::PipelineLayoutDescriptor {
+                label: Some(module_path!()),
+                bind_group_layouts: #bind_group_layouts,
+                push_constant_ranges: &[#(#ranges),*],
+            })
+        }
+    }]
+}
+
+/// Generates, with the `wgpu` feature, a `RenderPipelineBuilder` for shaders with vertex entry points, creating the
+/// shader module and pipeline layout and filling in the entry points and their vertex buffers, so that only the
+/// targets, primitive, depth stencil and multisample states are left to be given. It isn't generated if the pipeline
+/// layout or the vertex buffers of any vertex entry point can't be reflected.
+pub(crate) fn render_pipeline_items(module: &naga::Module, docs: &DocComments) -> Vec<syn::Item> {
+    if !cfg!(feature = "wgpu") || layout_groups(module).is_none() {
+        return Vec::new();
+    }
+
+    let entry_point = |stage| {
+        module
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.stage == stage)
+            .map(|entry_point| reflect::item_ident(&entry_point.name))
+    };
+    let Some(vertex) = entry_point(naga::ShaderStage::Vertex) else {
+        return Vec::new();
+    };
+    let fragment = match entry_point(naga::ShaderStage::Fragment) {
+        Some(fragment) => quote! { Some(entry_points::EntryPoint::#fragment) },
+        None => quote! { None },
+    };
+
+    let mut vertex_buffers = Vec::new();
+    for entry_point in &module.entry_points {
+        if entry_point.stage != naga::ShaderStage::Vertex {
+            continue;
+        }
+        if vertex::vertex_buffer_tokens(module, docs, entry_point).is_none() {
+            return Vec::new();
+        }
+        let ident = reflect::item_ident(&entry_point.name);
+        vertex_buffers.push(quote! {
+            entry_points::EntryPoint::#ident => &entry_points::#ident::VERTEX_BUFFERS
+        });
+    }
+
+    let other_entry_points =
+        (vertex_buffers.len() < module.entry_points.len()).then(|| quote! { _ => &[], });
+
+    // Overrides are only settable if the shader has any
+    let (overrides_field, overrides_default, overrides_setter, compilation_options) =
+        if module.overrides.is_empty() {
+            (
+                None,
+                None,
+                None,
+                quote! { ::core::default::Default::default() },
+            )
+        } else {
+            (
+                Some(quote! { overrides: Overrides, }),
+                Some(quote! { overrides: Overrides::new(), }),
+                Some(quote! {
+                    /// Sets the pipeline-overridable constants of both stages.
+                    pub fn overrides(mut self, overrides: Overrides) -> Self {
+                        self.overrides = overrides;
+                        self
+                    }
+                }),
+                quote! { self.overrides.compilation_options() },
+            )
+        };
+
+    vec![
+        syn::parse_quote! {
+            /// Builds render pipelines from the shader, with the shader module, pipeline layout, entry points and
+            /// vertex buffers filled in from its reflection.
+            pub struct RenderPipelineBuilder<'a> {
+                device: &'a ::wgpu::Device,
+                module: ::wgpu::ShaderModule,
+                layout: ::wgpu::PipelineLayout,
+                vertex: entry_points::EntryPoint,
+                fragment: Option<entry_points::EntryPoint>,
+                targets: ::std::vec::Vec<Option<::wgpu::ColorTargetState>>,
+                primitive: ::wgpu::PrimitiveState,
+                depth_stencil: Option<::wgpu::DepthStencilState>,
+                multisample: ::wgpu::MultisampleState,
+                #overrides_field
+            }
+        },
+        syn::parse_quote! {
+            impl<'a> RenderPipelineBuilder<'a> {
+                /// Creates the shader module and pipeline layout, using the shader's first vertex entry point and its
+                /// first fragment entry point, if it has one, with no color targets and default states.
+                pub fn new(device: &'a ::wgpu::Device) -> Self {
+                    Self {
+                        device,
+                        module: create_shader_module(device),
+                        layout: create_pipeline_layout(device),
+                        vertex: entry_points::EntryPoint::#vertex,
+                        fragment: #fragment,
+                        targets: ::std::vec::Vec::new(),
+                        primitive: ::core::default::Default::default(),
+                        depth_stencil: None,
+                        multisample: ::core::default::Default::default(),
+                        #overrides_default
+                    }
+                }
+
+                /// Sets the vertex entry point, which must be a vertex entry point of the shader.
+                pub fn vertex(mut self, entry_point: entry_points::EntryPoint) -> Self {
+                    assert!(
+                        matches!(entry_point.stage(), entry_points::Stage::Vertex),
+                        "`{}` isn't a vertex entry point",
+                        entry_point.name()
+                    );
+                    self.vertex = entry_point;
+                    self
+                }
+
+                /// Sets the fragment entry point, which must be a fragment entry point of the shader, or `None` for
+                /// pipelines without a fragment stage.
+                pub fn fragment(mut self, entry_point: Option<entry_points::EntryPoint>) -> Self {
+                    if let Some(entry_point) = entry_point {
+                        assert!(
+                            matches!(entry_point.stage(), entry_points::Stage::Fragment),
+                            "`{}` isn't a fragment entry point",
+                            entry_point.name()
+                        );
+                    }
+                    self.fragment = entry_point;
+                    self
+                }
+
+                /// Sets the color targets of the fragment stage, e.g. from the entry point's `color_targets`.
+                pub fn targets(mut self, targets: &[Option<::wgpu::ColorTargetState>]) -> Self {
+                    self.targets = targets.to_vec();
+                    self
+                }
+
+                /// Sets the primitive state.
+                pub fn primitive(mut self, primitive: ::wgpu::PrimitiveState) -> Self {
+                    self.primitive = primitive;
+                    self
+                }
+
+                /// Sets the depth stencil state.
+                pub fn depth_stencil(mut self, depth_stencil: Option<::wgpu::DepthStencilState>) -> Self {
+                    self.depth_stencil = depth_stencil;
+                    self
+                }
+
+                /// Sets the multisample state.
+                pub fn multisample(mut self, multisample: ::wgpu::MultisampleState) -> Self {
+                    self.multisample = multisample;
+                    self
+                }
+
+                #overrides_setter
+
+                /// The vertex buffers read by the vertex entry point.
+                fn vertex_buffers(&self) -> &'static [::wgpu::VertexBufferLayout<'static>] {
+                    match self.vertex {
+                        #(#vertex_buffers,)*
+                        #other_entry_points
+                    }
+                }
+
+                /// Creates the render pipeline.
+                pub fn build(&self, label: Option<&str>) -> ::wgpu::RenderPipeline {
+                    let compilation_options: ::wgpu::PipelineCompilationOptions<'_> = #compilation_options;
+                    self.device.create_render_pipeline(&::wgpu::RenderPipelineDescriptor {
+                        label,
+                        layout: Some(&self.layout),
+                        vertex: ::wgpu::VertexState {
+                            module: &self.module,
+                            entry_point: Some(self.vertex.name()),
+                            compilation_options: compilation_options.clone(),
+                            buffers: self.vertex_buffers(),
+                        },
+                        primitive: self.primitive,
+                        depth_stencil: self.depth_stencil.clone(),
+                        multisample: self.multisample,
+                        fragment: self.fragment.map(|fragment| ::wgpu::FragmentState {
+                            module: &self.module,
+                            entry_point: Some(fragment.name()),
+                            compilation_options,
+                            targets: &self.targets,
+                        }),
+                        multiview: None,
+                        cache: None,
+                    })
+                }
+            }
+        },
+    ]
+}
@@ -0,0 +1,46 @@
+use proc_macro2::Literal;
+use syn::visit_mut::VisitMut;
+
+/// Rewrites multi-line string literals as raw strings, so that expanded code shows embedded WGSL as it would be
+/// written rather than as a single line of escapes.
+struct RawStrings;
+
+impl VisitMut for RawStrings {
+    fn visit_lit_str_mut(&mut self, lit: &mut syn::LitStr) {
+        let value = lit.value();
+        if !value.contains('\n') || value.contains('\r') {
+            return;
+        }
+
+        // Use enough hashes that the contents can't terminate the string
+        let mut hashes = String::from("#");
+        while value.contains(&format!("\"{hashes}")) {
+            hashes.push('#');
+        }
+
+        let raw = format!("r{hashes}\"{value}\"{hashes}");
+        let Ok(raw) = raw.parse::<Literal>() else {
+            return;
+        };
+        if let syn::Lit::Str(mut raw) = syn::Lit::new(raw) {
+            raw.set_span(lit.span());
+            *lit = raw;
+        }
+    }
+}
+
+/// Makes generated items readable and diffable when expanded, e.g. with `cargo expand`.
+pub(crate) fn raw_string_literals(items: &mut [syn::Item]) {
+    for item in items {
+        RawStrings.visit_item_mut(item);
+    }
+}
+
+/// Formats generated items as a Rust source file.
+pub(crate) fn unparse(items: Vec<syn::Item>) -> String {
+    prettyplease::unparse(&syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items,
+    })
+}
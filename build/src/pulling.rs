@@ -0,0 +1,319 @@
+use naga::Span;
+
+use crate::{bindings, docs::DocComments, reflect, vertex};
+
+/// The binding of the storage buffer holding the per-vertex inputs of an entry point in the vertex pulling variant.
+pub(crate) const VERTEX_BINDING: u32 = 0;
+/// The binding of the storage buffer holding the per-instance inputs of an entry point in the vertex pulling variant.
+pub(crate) const INSTANCE_BINDING: u32 = 1;
+
+/// A vertex entry point of the vertex pulling variant, which reads its inputs from storage buffers.
+pub(crate) struct PulledEntryPoint {
+    pub(crate) name: String,
+    /// The bind group holding the storage buffers, after every group used by the shader.
+    pub(crate) group: u32,
+    /// The array stride of the per-vertex storage buffer, or `None` if the entry point has no per-vertex inputs.
+    pub(crate) vertex_stride: Option<u32>,
+    /// The array stride of the per-instance storage buffer, or `None` if the entry point has no per-instance inputs.
+    pub(crate) instance_stride: Option<u32>,
+}
+
+/// Adds a read-only storage buffer holding an array of structs with a member for each of the inputs, giving the
+/// buffer's global variable and array stride.
+fn add_storage_buffer(
+    module: &mut naga::Module,
+    type_name: String,
+    global_name: String,
+    inputs: &[&vertex::VertexInput],
+    binding: naga::ResourceBinding,
+) -> Option<(naga::Handle<naga::GlobalVariable>, u32)> {
+    if inputs.is_empty() {
+        return None;
+    }
+
+    let layouter = reflect::layouter(module);
+    let mut members = Vec::new();
+    let mut offset = 0;
+    let mut alignment = naga::proc::Alignment::ONE;
+    for input in inputs {
+        let layout = layouter[input.ty];
+        offset = layout.alignment.round_up(offset);
+        members.push(naga::StructMember {
+            name: Some(input.name.clone()),
+            ty: input.ty,
+            binding: None,
+            offset,
+        });
+        offset += layout.size;
+        alignment = alignment.max(layout.alignment);
+    }
+    let stride = alignment.round_up(offset);
+
+    let element = module.types.insert(
+        naga::Type {
+            name: Some(type_name),
+            inner: naga::TypeInner::Struct {
+                members,
+                span: stride,
+            },
+        },
+        Span::UNDEFINED,
+    );
+    let array = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: naga::TypeInner::Array {
+                base: element,
+                size: naga::ArraySize::Dynamic,
+                stride,
+            },
+        },
+        Span::UNDEFINED,
+    );
+    let global = module.global_variables.append(
+        naga::GlobalVariable {
+            name: Some(global_name),
+            space: naga::AddressSpace::Storage {
+                access: naga::StorageAccess::LOAD,
+            },
+            binding: Some(binding),
+            ty: array,
+            init: None,
+        },
+        Span::UNDEFINED,
+    );
+
+    Some((global, stride))
+}
+
+/// Rewrites a vertex entry point to fetch its `@location` inputs from storage buffers, indexed by the vertex and
+/// instance indices. The original entry point becomes an ordinary function, called with the fetched inputs.
+fn pull_entry_point(
+    module: &mut naga::Module,
+    docs: &DocComments,
+    index: usize,
+    group: u32,
+) -> Option<PulledEntryPoint> {
+    let entry_point = module.entry_points[index].clone();
+    let inputs = vertex::vertex_inputs(module, docs, &entry_point);
+    if inputs.is_empty() {
+        return None;
+    }
+
+    let (instance_inputs, vertex_inputs): (Vec<_>, Vec<_>) =
+        inputs.iter().partition(|input| input.instance);
+    let type_name = |suffix: &str| {
+        let mut name = String::new();
+        for word in entry_point.name.split('_').chain([suffix]) {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                name.extend(first.to_uppercase().chain(chars));
+            }
+        }
+        name
+    };
+    let vertex_buffer = add_storage_buffer(
+        module,
+        type_name("Vertex"),
+        format!("{}_vertices", entry_point.name),
+        &vertex_inputs,
+        naga::ResourceBinding {
+            group,
+            binding: VERTEX_BINDING,
+        },
+    );
+    let instance_buffer = add_storage_buffer(
+        module,
+        type_name("Instance"),
+        format!("{}_instances", entry_point.name),
+        &instance_inputs,
+        naga::ResourceBinding {
+            group,
+            binding: INSTANCE_BINDING,
+        },
+    );
+
+    // The original entry point, as an ordinary function
+    let mut body = entry_point.function.clone();
+    body.name = Some(format!("{}_body", entry_point.name));
+    for argument in &mut body.arguments {
+        argument.binding = None;
+    }
+    if let Some(result) = &mut body.result {
+        result.binding = None;
+    }
+    let body = module.functions.append(body, Span::UNDEFINED);
+
+    // The built-ins read by the original entry point are passed through, along with the indices to fetch with
+    let u32_ty = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: naga::TypeInner::Scalar(naga::Scalar::U32),
+        },
+        Span::UNDEFINED,
+    );
+    let mut built_ins = vec![
+        (naga::BuiltIn::VertexIndex, u32_ty),
+        (naga::BuiltIn::InstanceIndex, u32_ty),
+    ];
+    let mut add_built_in = |binding: &Option<naga::Binding>, ty| {
+        if let Some(naga::Binding::BuiltIn(built_in)) = binding {
+            if !built_ins.iter().any(|(existing, _)| existing == built_in) {
+                built_ins.push((*built_in, ty));
+            }
+        }
+    };
+    for argument in &entry_point.function.arguments {
+        add_built_in(&argument.binding, argument.ty);
+        if let naga::TypeInner::Struct { members, .. } = &module.types[argument.ty].inner {
+            for member in members {
+                add_built_in(&member.binding, member.ty);
+            }
+        }
+    }
+
+    let mut function = naga::Function {
+        name: Some(entry_point.name.clone()),
+        arguments: built_ins
+            .iter()
+            .map(|(built_in, ty)| naga::FunctionArgument {
+                name: match built_in {
+                    naga::BuiltIn::VertexIndex => Some("vertex_index".to_owned()),
+                    naga::BuiltIn::InstanceIndex => Some("instance_index".to_owned()),
+                    _ => None,
+                },
+                ty: *ty,
+                binding: Some(naga::Binding::BuiltIn(*built_in)),
+            })
+            .collect(),
+        result: entry_point.function.result.clone(),
+        ..Default::default()
+    };
+    let expressions = &mut function.expressions;
+    let arguments = (0..built_ins.len() as u32)
+        .map(|i| expressions.append(naga::Expression::FunctionArgument(i), Span::UNDEFINED))
+        .collect::<Vec<_>>();
+    let built_in_argument = |binding: &Option<naga::Binding>| match binding {
+        Some(naga::Binding::BuiltIn(built_in)) => built_ins
+            .iter()
+            .position(|(existing, _)| existing == built_in)
+            .map(|position| arguments[position]),
+        _ => None,
+    };
+    let globals = [vertex_buffer, instance_buffer].map(|buffer| {
+        buffer.map(|(global, _)| {
+            expressions.append(naga::Expression::GlobalVariable(global), Span::UNDEFINED)
+        })
+    });
+
+    // Everything from here until the call must be emitted
+    let emit_start = expressions.len();
+    let fetch = |expressions: &mut naga::Arena<naga::Expression>,
+                 argument: usize,
+                 member: Option<usize>| {
+        let input = inputs
+            .iter()
+            .find(|input| input.argument == argument && input.member == member)?;
+        let (buffer_inputs, global, index) = if input.instance {
+            (&instance_inputs, globals[1]?, arguments[1])
+        } else {
+            (&vertex_inputs, globals[0]?, arguments[0])
+        };
+        let member_index = buffer_inputs
+            .iter()
+            .position(|other| std::ptr::eq(*other, input))?;
+
+        let element = expressions.append(
+            naga::Expression::Access {
+                base: global,
+                index,
+            },
+            Span::UNDEFINED,
+        );
+        let pointer = expressions.append(
+            naga::Expression::AccessIndex {
+                base: element,
+                index: member_index as u32,
+            },
+            Span::UNDEFINED,
+        );
+        Some(expressions.append(naga::Expression::Load { pointer }, Span::UNDEFINED))
+    };
+    let mut call_arguments = Vec::new();
+    for (argument_index, argument) in entry_point.function.arguments.iter().enumerate() {
+        let value = match &module.types[argument.ty].inner {
+            naga::TypeInner::Struct { members, .. } if argument.binding.is_none() => {
+                let components = members
+                    .iter()
+                    .enumerate()
+                    .map(|(member_index, member)| {
+                        built_in_argument(&member.binding)
+                            .or_else(|| fetch(expressions, argument_index, Some(member_index)))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                expressions.append(
+                    naga::Expression::Compose {
+                        ty: argument.ty,
+                        components,
+                    },
+                    Span::UNDEFINED,
+                )
+            }
+            _ => built_in_argument(&argument.binding)
+                .or_else(|| fetch(expressions, argument_index, None))?,
+        };
+        call_arguments.push(value);
+    }
+    let emitted = expressions.range_from(emit_start);
+    let result = entry_point
+        .function
+        .result
+        .as_ref()
+        .map(|_| expressions.append(naga::Expression::CallResult(body), Span::UNDEFINED));
+
+    function
+        .body
+        .push(naga::Statement::Emit(emitted), Span::UNDEFINED);
+    function.body.push(
+        naga::Statement::Call {
+            function: body,
+            arguments: call_arguments,
+            result,
+        },
+        Span::UNDEFINED,
+    );
+    function
+        .body
+        .push(naga::Statement::Return { value: result }, Span::UNDEFINED);
+    module.entry_points[index].function = function;
+
+    Some(PulledEntryPoint {
+        name: entry_point.name,
+        group,
+        vertex_stride: vertex_buffer.map(|(_, stride)| stride),
+        instance_stride: instance_buffer.map(|(_, stride)| stride),
+    })
+}
+
+/// Creates the vertex pulling variant of the module, where every vertex entry point with `@location` inputs fetches
+/// them from storage buffers in a bind group after those used by the shader, for GPU-driven renderers.
+pub(crate) fn pull_vertices(
+    module: &naga::Module,
+    docs: &DocComments,
+) -> (naga::Module, Vec<PulledEntryPoint>) {
+    let group = bindings::bindings(module)
+        .iter()
+        .map(|binding| binding.group + 1)
+        .max()
+        .unwrap_or_default();
+
+    let mut pulled = module.clone();
+    let mut entry_points = Vec::new();
+    for index in 0..pulled.entry_points.len() {
+        if pulled.entry_points[index].stage == naga::ShaderStage::Vertex {
+            entry_points.extend(pull_entry_point(&mut pulled, docs, index, group));
+        }
+    }
+
+    (pulled, entry_points)
+}
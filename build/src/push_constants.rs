@@ -0,0 +1,120 @@
+use quote::quote;
+
+use crate::{
+    bindings, reflect,
+    structs::{self, HostLayout, Math},
+};
+
+/// Finds the module's `var<push_constant>` globals.
+pub(crate) fn push_constants(
+    module: &naga::Module,
+) -> Vec<(naga::Handle<naga::GlobalVariable>, &naga::GlobalVariable)> {
+    module
+        .global_variables
+        .iter()
+        .filter(|(_, global)| global.space == naga::AddressSpace::PushConstant)
+        .collect()
+}
+
+/// Finds the names of the structs used as push constants, which are generated even if they aren't exported.
+pub(crate) fn push_constant_structs(module: &naga::Module) -> Vec<String> {
+    push_constants(module)
+        .into_iter()
+        .filter_map(|(_, global)| {
+            let ty = &module.types[global.ty];
+            matches!(ty.inner, naga::TypeInner::Struct { .. })
+                .then(|| ty.name.clone())
+                .flatten()
+        })
+        .collect()
+}
+
+/// Finds the stages of the entry points which use a global, directly or through the functions they call.
+fn stages(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    handle: naga::Handle<naga::GlobalVariable>,
+) -> Vec<naga::ShaderStage> {
+    let mut stages = Vec::new();
+    for (index, entry_point) in module.entry_points.iter().enumerate() {
+        if !info.get_entry_point(index)[handle].is_empty() && !stages.contains(&entry_point.stage) {
+            stages.push(entry_point.stage);
+        }
+    }
+    stages
+}
+
+/// Generates a `push_constants` module with a submodule for every push constant of the shader, named after its global
+/// variable, giving its Rust type, its size and with the `wgpu` feature the push constant range covering the stages
+/// that use it. When the shader has a single push constant, its reflection is also re-exported from `push_constants`.
+pub(crate) fn push_constant_items(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    layout: &HostLayout,
+    math: Math,
+) -> Vec<syn::Item> {
+    let push_constants = push_constants(module);
+    if push_constants.is_empty() {
+        return Vec::new();
+    }
+
+    let layouter = reflect::layouter(module);
+    let push_constant_modules = push_constants.iter().map(|(handle, global)| {
+        let ident = reflect::item_ident(global.name.as_deref().unwrap_or_default());
+        let size = layouter[global.ty].size;
+
+        // Structs are generated alongside the exported structs, and other types are used directly where they can be
+        let ty = match &module.types[global.ty].inner {
+            naga::TypeInner::Struct { .. } => {
+                let name = module.types[global.ty].name.as_deref().unwrap_or_default();
+                let ident = reflect::item_ident(name);
+                Some(quote! { super::super::types::#ident })
+            }
+            naga::TypeInner::Array { .. } => None,
+            _ => structs::field_type(module, layout, global.ty, math, true).ok(),
+        };
+        let ty = ty.map(|ty| {
+            quote! {
+                /// The Rust type of the push constant, to be written with `set_push_constants`.
+                pub type Type = #ty;
+            }
+        });
+
+        let range = cfg!(feature = "wgpu").then(|| {
+            let stages = bindings::shader_stages_tokens(&stages(module, info, *handle));
+            quote! {
+                /// The stages of the entry points which use the push constant.
+                pub const STAGES: ::wgpu::ShaderStages = #stages;
+                /// The push constant range to be given in the pipeline layout.
+                pub const RANGE: ::wgpu::PushConstantRange = ::wgpu::PushConstantRange {
+                    stages: STAGES,
+                    range: 0..SIZE_BYTES,
+                };
+            }
+        });
+
+        quote! {
+            pub mod #ident {
+                #ty
+                /// The size of the push constant, in bytes.
+                pub const SIZE_BYTES: u32 = #size;
+                #range
+            }
+        }
+    });
+    let only = match push_constants.as_slice() {
+        [(_, global)] => {
+            let ident = reflect::item_ident(global.name.as_deref().unwrap_or_default());
+            Some(quote! { pub use #ident::*; })
+        }
+        _ => None,
+    };
+
+    vec![syn::parse_quote! {
+        /// The push constants of the shader, named after their global variables.
+        pub mod push_constants {
+            #(#push_constant_modules)*
+            #only
+        }
+    }]
+}
@@ -0,0 +1,396 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use regex::{Captures, Regex};
+
+lazy_static::lazy_static! {
+    static ref DECORATED_NAMES_REGEX: Regex = Regex::new(r"(\w+?)X_naga_oil_mod_X([A-Z0-9]*)X").unwrap();
+}
+
+fn decode_module_name(encoded: &str) -> String {
+    String::from_utf8(
+        data_encoding::BASE32_NOPAD
+            .decode(encoded.as_bytes())
+            .expect("naga_oil decorations are base32 encoded"),
+    )
+    .expect("naga_oil decorations encode utf-8 module names")
+}
+
+/// Gives the name of an item as a user would write it, e.g. `wgsl_oil::color::luminance` for imported items.
+pub fn display_name(name: &str) -> Cow<'_, str> {
+    DECORATED_NAMES_REGEX.replace_all(name, |captures: &Captures<'_>| {
+        format!(
+            "{}::{}",
+            decode_module_name(captures.get(2).unwrap().as_str()),
+            captures.get(1).unwrap().as_str()
+        )
+    })
+}
+
+/// Gives the Rust identifier for a named item of the module, without the path of the module it was imported from. Names
+/// which are Rust keywords are given as raw identifiers, e.g. `r#type`.
+pub(crate) fn item_ident(name: &str) -> syn::Ident {
+    let name = display_name(name);
+    let name = name.rsplit("::").next().unwrap_or_default();
+    syn::parse_str::<syn::Ident>(name)
+        .unwrap_or_else(|_| syn::Ident::new_raw(name, proc_macro2::Span::call_site()))
+}
+
+/// Formats a type as it would be written in WGSL, e.g. `vec3<f32>`, with imported struct names undecorated.
+pub(crate) fn type_name(module: &naga::Module, ty: naga::Handle<naga::Type>) -> String {
+    // `naga` writes storage textures as e.g. `texture_2d<rgba8unorm,write>`, which isn't valid WGSL
+    if let naga::TypeInner::Image {
+        dim,
+        arrayed,
+        class: naga::ImageClass::Storage { format, access },
+    } = module.types[ty].inner
+    {
+        let dim = match dim {
+            naga::ImageDimension::D1 => "1d",
+            naga::ImageDimension::D2 => "2d",
+            naga::ImageDimension::D3 => "3d",
+            naga::ImageDimension::Cube => "cube",
+        };
+        let array = if arrayed { "_array" } else { "" };
+        return format!(
+            "texture_storage_{dim}{array}<{}, {}>",
+            format.to_wgsl(),
+            storage_access_name(access)
+        );
+    }
+
+    display_name(&ty.to_wgsl(&module.to_ctx())).into_owned()
+}
+
+/// Gives the access mode of a storage texture as it is written in WGSL, e.g. `read_write`.
+fn storage_access_name(access: naga::StorageAccess) -> &'static str {
+    #[cfg(feature = "naga-24")]
+    if access.contains(naga::StorageAccess::ATOMIC) {
+        return "atomic";
+    }
+
+    match (
+        access.contains(naga::StorageAccess::LOAD),
+        access.contains(naga::StorageAccess::STORE),
+    ) {
+        (true, true) => "read_write",
+        (true, false) => "read",
+        _ => "write",
+    }
+}
+
+/// Calculates the size and alignment of every type in the module.
+pub(crate) fn layouter(module: &naga::Module) -> naga::proc::Layouter {
+    let mut layouter = naga::proc::Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .expect("validated modules should have valid layouts");
+    layouter
+}
+
+/// Formats the address space of a global variable as it would be written in WGSL, e.g. `var<storage, read>`.
+pub(crate) fn global_declaration(space: naga::AddressSpace) -> &'static str {
+    match space {
+        naga::AddressSpace::Function => "var<function>",
+        naga::AddressSpace::Private => "var<private>",
+        naga::AddressSpace::WorkGroup => "var<workgroup>",
+        naga::AddressSpace::Uniform => "var<uniform>",
+        naga::AddressSpace::Storage { access } if access.contains(naga::StorageAccess::STORE) => {
+            "var<storage, read_write>"
+        }
+        naga::AddressSpace::Storage { .. } => "var<storage, read>",
+        naga::AddressSpace::Handle => "var",
+        naga::AddressSpace::PushConstant => "var<push_constant>",
+    }
+}
+
+/// Formats a shader stage as the attribute used to declare an entry point in WGSL.
+pub(crate) fn stage_attribute(stage: naga::ShaderStage) -> &'static str {
+    match stage {
+        naga::ShaderStage::Vertex => "@vertex",
+        naga::ShaderStage::Fragment => "@fragment",
+        naga::ShaderStage::Compute => "@compute",
+    }
+}
+
+/// Gives the lowercase name of a shader stage, e.g. `vertex`.
+pub(crate) fn stage_name(stage: naga::ShaderStage) -> &'static str {
+    match stage {
+        naga::ShaderStage::Vertex => "vertex",
+        naga::ShaderStage::Fragment => "fragment",
+        naga::ShaderStage::Compute => "compute",
+    }
+}
+
+/// Formats an IO binding as the attribute used to declare it in WGSL, e.g. `@location(0)`.
+pub(crate) fn binding_attribute(binding: &naga::Binding) -> String {
+    match binding {
+        naga::Binding::Location { location, .. } => format!("@location({location})"),
+        naga::Binding::BuiltIn(built_in) => {
+            let name = match built_in {
+                naga::BuiltIn::Position { .. } => "position",
+                naga::BuiltIn::ViewIndex => "view_index",
+                naga::BuiltIn::VertexIndex => "vertex_index",
+                naga::BuiltIn::InstanceIndex => "instance_index",
+                naga::BuiltIn::FrontFacing => "front_facing",
+                naga::BuiltIn::FragDepth => "frag_depth",
+                naga::BuiltIn::PrimitiveIndex => "primitive_index",
+                naga::BuiltIn::SampleIndex => "sample_index",
+                naga::BuiltIn::SampleMask => "sample_mask",
+                naga::BuiltIn::ClipDistance => "clip_distances",
+                naga::BuiltIn::GlobalInvocationId => "global_invocation_id",
+                naga::BuiltIn::LocalInvocationId => "local_invocation_id",
+                naga::BuiltIn::LocalInvocationIndex => "local_invocation_index",
+                naga::BuiltIn::WorkGroupId => "workgroup_id",
+                naga::BuiltIn::NumWorkGroups => "num_workgroups",
+                naga::BuiltIn::NumSubgroups => "num_subgroups",
+                naga::BuiltIn::SubgroupId => "subgroup_id",
+                naga::BuiltIn::SubgroupSize => "subgroup_size",
+                naga::BuiltIn::SubgroupInvocationId => "subgroup_invocation_id",
+                other => return format!("@builtin({other:?})"),
+            };
+            format!("@builtin({name})")
+        }
+    }
+}
+
+/// Whether any type in the module is made of scalars of the given kind and width in bytes, e.g. `f16` for
+/// `(Float, 2)`.
+pub(crate) fn uses_scalar(module: &naga::Module, kind: naga::ScalarKind, width: u8) -> bool {
+    module.types.iter().any(|(_, ty)| match ty.inner {
+        naga::TypeInner::Scalar(scalar)
+        | naga::TypeInner::Vector { scalar, .. }
+        | naga::TypeInner::Matrix { scalar, .. }
+        | naga::TypeInner::Atomic(scalar) => scalar.kind == kind && scalar.width == width,
+        _ => false,
+    })
+}
+
+/// Whether a type is or contains 64-bit scalars, i.e. `f64`, `i64` or `u64`.
+fn contains_64_bit(module: &naga::Module, inner: &naga::TypeInner) -> bool {
+    match *inner {
+        naga::TypeInner::Scalar(scalar)
+        | naga::TypeInner::Vector { scalar, .. }
+        | naga::TypeInner::Matrix { scalar, .. }
+        | naga::TypeInner::Atomic(scalar)
+        | naga::TypeInner::ValuePointer { scalar, .. } => scalar.width == 8,
+        naga::TypeInner::Pointer { base, .. }
+        | naga::TypeInner::Array { base, .. }
+        | naga::TypeInner::BindingArray { base, .. } => {
+            contains_64_bit(module, &module.types[base].inner)
+        }
+        naga::TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .any(|member| contains_64_bit(module, &module.types[member.ty].inner)),
+        _ => false,
+    }
+}
+
+/// Finds the functions, entry points, global variables and constants which use 64-bit types, by name, e.g.
+/// `wgsl_oil::random::hash`.
+pub(crate) fn uses_of_64_bit_types(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Vec<String> {
+    let ty_is_64_bit =
+        |ty: naga::Handle<naga::Type>| contains_64_bit(module, &module.types[ty].inner);
+    let function_uses = |function: &naga::Function, function_info: &naga::valid::FunctionInfo| {
+        function
+            .arguments
+            .iter()
+            .any(|argument| ty_is_64_bit(argument.ty))
+            || function
+                .result
+                .as_ref()
+                .is_some_and(|result| ty_is_64_bit(result.ty))
+            || function
+                .local_variables
+                .iter()
+                .any(|(_, local)| ty_is_64_bit(local.ty))
+            || function.expressions.iter().any(|(handle, _)| {
+                contains_64_bit(module, function_info[handle].ty.inner_with(&module.types))
+            })
+    };
+
+    let mut uses = Vec::new();
+    for (handle, function) in module.functions.iter() {
+        if function_uses(function, &info[handle]) {
+            uses.push(display_name(function.name.as_deref().unwrap_or("_")).into_owned());
+        }
+    }
+    for (index, entry_point) in module.entry_points.iter().enumerate() {
+        if function_uses(&entry_point.function, info.get_entry_point(index)) {
+            uses.push(entry_point.name.clone());
+        }
+    }
+    for (_, global) in module.global_variables.iter() {
+        if ty_is_64_bit(global.ty) {
+            uses.push(display_name(global.name.as_deref().unwrap_or("_")).into_owned());
+        }
+    }
+    for (_, constant) in module.constants.iter() {
+        if ty_is_64_bit(constant.ty) {
+            uses.push(display_name(constant.name.as_deref().unwrap_or("_")).into_owned());
+        }
+    }
+    uses
+}
+
+/// Finds the built-in inputs of an entry point, including those given as members of struct arguments.
+pub(crate) fn input_built_ins(
+    module: &naga::Module,
+    entry_point: &naga::EntryPoint,
+) -> Vec<naga::BuiltIn> {
+    let mut built_ins = Vec::new();
+    for argument in &entry_point.function.arguments {
+        if let Some(naga::Binding::BuiltIn(built_in)) = argument.binding {
+            built_ins.push(built_in);
+        }
+        if let naga::TypeInner::Struct { members, .. } = &module.types[argument.ty].inner {
+            built_ins.extend(members.iter().filter_map(|member| match member.binding {
+                Some(naga::Binding::BuiltIn(built_in)) => Some(built_in),
+                _ => None,
+            }));
+        }
+    }
+    built_ins
+}
+
+/// Describes the layout of every named struct in the module, keyed by name, e.g.
+/// `{ scale: f32 @ 0, offset: vec3<f32> @ 16 } (32 bytes)`.
+pub(crate) fn struct_signatures(module: &naga::Module) -> BTreeMap<String, String> {
+    module
+        .types
+        .iter()
+        .filter_map(|(_, ty)| {
+            let naga::TypeInner::Struct { members, span } = &ty.inner else {
+                return None;
+            };
+
+            let members = members
+                .iter()
+                .map(|member| {
+                    format!(
+                        "{}: {} @ {}",
+                        member.name.as_deref().unwrap_or("_"),
+                        type_name(module, member.ty),
+                        member.offset
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            Some((
+                display_name(ty.name.as_deref()?).into_owned(),
+                format!("{{ {members} }} ({span} bytes)"),
+            ))
+        })
+        .collect()
+}
+
+/// Finds the validator capabilities that the module can't be validated without, by name, e.g. `PUSH_CONSTANT`.
+pub(crate) fn required_capabilities(module: &naga::Module) -> Vec<&'static str> {
+    naga::valid::Capabilities::all()
+        .iter_names()
+        .filter(|(_, capability)| {
+            naga::valid::Validator::new(
+                naga::valid::ValidationFlags::all(),
+                naga::valid::Capabilities::all() - *capability,
+            )
+            .validate(module)
+            .is_err()
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Finds the `bool`s in a type, which keep it from being host-shareable, as paths through its members from the type's
+/// name, e.g. `Flags.enabled`.
+fn bool_paths(module: &naga::Module, ty: naga::Handle<naga::Type>, path: String) -> Vec<String> {
+    match &module.types[ty].inner {
+        naga::TypeInner::Scalar(scalar) | naga::TypeInner::Vector { scalar, .. }
+            if scalar.kind == naga::ScalarKind::Bool =>
+        {
+            vec![path]
+        }
+        naga::TypeInner::Array { base, .. } | naga::TypeInner::BindingArray { base, .. } => {
+            bool_paths(module, *base, path)
+        }
+        naga::TypeInner::Struct { members, .. } => members
+            .iter()
+            .flat_map(|member| {
+                let name = member.name.as_deref().unwrap_or_default();
+                bool_paths(module, member.ty, format!("{path}.{name}"))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Describes the buffers and push constants of the module which can't be bound because their types have `bool`s,
+/// e.g. "`params` has a `bool` at `Params.enabled`".
+pub(crate) fn bound_bools(module: &naga::Module) -> Vec<String> {
+    module
+        .global_variables
+        .iter()
+        .filter(|(_, global)| {
+            matches!(
+                global.space,
+                naga::AddressSpace::Uniform
+                    | naga::AddressSpace::Storage { .. }
+                    | naga::AddressSpace::PushConstant
+            )
+        })
+        .filter_map(|(_, global)| {
+            let paths = bool_paths(module, global.ty, type_name(module, global.ty));
+            let name = display_name(global.name.as_deref().unwrap_or_default()).into_owned();
+            (!paths.is_empty())
+                .then(|| format!("`{name}` has a `bool` at `{}`", paths.join("`, `")))
+        })
+        .collect()
+}
+
+/// Describes why values of a type can't be stored in structs, if they can't: resources and pointers only exist within
+/// the shader.
+pub(crate) fn unstorable_kind(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+) -> Option<&'static str> {
+    match module.types[ty].inner {
+        naga::TypeInner::Image { .. }
+        | naga::TypeInner::Sampler { .. }
+        | naga::TypeInner::AccelerationStructure
+        | naga::TypeInner::RayQuery
+        | naga::TypeInner::BindingArray { .. } => {
+            Some("a resource, which must be bound on its own with `@group` and `@binding`")
+        }
+        naga::TypeInner::Pointer { .. } | naga::TypeInner::ValuePointer { .. } => {
+            Some("a pointer, which only exists within the shader")
+        }
+        naga::TypeInner::Array { base, .. } => unstorable_kind(module, base),
+        _ => None,
+    }
+}
+
+/// Describes the members of the module's structs which hold types that can't be stored in structs, e.g. "`Material.albedo`
+/// is a `texture_2d<f32>`, a resource, which must be bound on its own with `@group` and `@binding`".
+pub(crate) fn unstorable_members(module: &naga::Module) -> Vec<String> {
+    module
+        .types
+        .iter()
+        .filter_map(|(_, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, .. } => Some((ty.name.as_deref(), members)),
+            _ => None,
+        })
+        .flat_map(|(name, members)| {
+            let name = display_name(name.unwrap_or_default()).into_owned();
+            members.iter().filter_map(move |member| {
+                let kind = unstorable_kind(module, member.ty)?;
+                Some(format!(
+                    "`{name}.{}` is a `{}`, {kind}",
+                    member.name.as_deref().unwrap_or_default(),
+                    type_name(module, member.ty)
+                ))
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,220 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{artifacts, reflect};
+
+/// The extension of the files recording the statistics of each shader, from which the report is aggregated.
+const ENTRY_EXTENSION: &str = "report-entry";
+
+/// How many of the slowest compositions are listed in the report.
+const SLOWEST_COUNT: usize = 5;
+
+/// The statistics recorded for one shader in the compilation report.
+struct ReportEntry {
+    shader: String,
+    composed_bytes: usize,
+    bindings: usize,
+    groups: usize,
+    capabilities: Vec<String>,
+    compose_time: Duration,
+}
+
+impl ReportEntry {
+    fn new(
+        module: &naga::Module,
+        shader: String,
+        composed_bytes: usize,
+        compose_time: Duration,
+    ) -> Self {
+        let bindings = module
+            .global_variables
+            .iter()
+            .filter_map(|(_, global)| global.binding.as_ref())
+            .collect::<Vec<_>>();
+        let mut groups = bindings
+            .iter()
+            .map(|binding| binding.group)
+            .collect::<Vec<_>>();
+        groups.sort();
+        groups.dedup();
+
+        Self {
+            shader,
+            composed_bytes,
+            bindings: bindings.len(),
+            groups: groups.len(),
+            capabilities: reflect::required_capabilities(module)
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            compose_time,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.shader,
+            self.composed_bytes,
+            self.bindings,
+            self.groups,
+            self.capabilities.join(","),
+            self.compose_time.as_micros()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end().split('\t');
+        let shader = fields.next()?.to_owned();
+        let composed_bytes = fields.next()?.parse().ok()?;
+        let bindings = fields.next()?.parse().ok()?;
+        let groups = fields.next()?.parse().ok()?;
+        let capabilities = fields
+            .next()?
+            .split(',')
+            .filter(|capability| !capability.is_empty())
+            .map(str::to_owned)
+            .collect();
+        let compose_time = Duration::from_micros(fields.next()?.parse().ok()?);
+
+        Some(Self {
+            shader,
+            composed_bytes,
+            bindings,
+            groups,
+            capabilities,
+            compose_time,
+        })
+    }
+}
+
+/// Reads every entry recorded within `dir`.
+fn collect_entries(dir: &Path, entries: &mut Vec<ReportEntry>) {
+    let Ok(dir_entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in dir_entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        if path.is_dir() {
+            collect_entries(&path, entries);
+        } else if path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION) {
+            let entry = fs::read_to_string(&path)
+                .ok()
+                .and_then(|line| ReportEntry::from_line(&line));
+            entries.extend(entry);
+        }
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.1}ms", time.as_secs_f64() * 1000.0)
+}
+
+fn format_report(entries: &[ReportEntry]) -> String {
+    let mut out = String::from("# include-wgsl-oil shader compilation report\n");
+
+    let total_bytes = entries
+        .iter()
+        .map(|entry| entry.composed_bytes)
+        .sum::<usize>();
+    let total_time = entries
+        .iter()
+        .map(|entry| entry.compose_time)
+        .sum::<Duration>();
+    writeln!(out, "\nshaders: {}", entries.len()).unwrap();
+    writeln!(out, "composed size: {total_bytes} bytes").unwrap();
+    writeln!(out, "composition time: {}", format_time(total_time)).unwrap();
+
+    // Per-shader statistics
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.shader.len())
+        .max()
+        .unwrap_or(0)
+        .max("shader".len());
+    writeln!(
+        out,
+        "\n{:name_width$}  {:>10}  {:>8}  {:>6}  {:>10}",
+        "shader", "bytes", "bindings", "groups", "time"
+    )
+    .unwrap();
+    for entry in entries {
+        writeln!(
+            out,
+            "{:name_width$}  {:>10}  {:>8}  {:>6}  {:>10}",
+            entry.shader,
+            entry.composed_bytes,
+            entry.bindings,
+            entry.groups,
+            format_time(entry.compose_time)
+        )
+        .unwrap();
+    }
+
+    // Required capabilities, and which shaders need them
+    let mut capabilities = entries
+        .iter()
+        .flat_map(|entry| entry.capabilities.iter())
+        .collect::<Vec<_>>();
+    capabilities.sort();
+    capabilities.dedup();
+    writeln!(out, "\nrequired capabilities:").unwrap();
+    if capabilities.is_empty() {
+        writeln!(out, "    none").unwrap();
+    }
+    for capability in capabilities {
+        let shaders = entries
+            .iter()
+            .filter(|entry| entry.capabilities.contains(capability))
+            .map(|entry| entry.shader.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(out, "    {capability}: {shaders}").unwrap();
+    }
+
+    // Slowest compositions
+    let mut slowest = entries.iter().collect::<Vec<_>>();
+    slowest.sort_by_key(|entry| std::cmp::Reverse(entry.compose_time));
+    writeln!(out, "\nslowest compositions:").unwrap();
+    for entry in slowest.into_iter().take(SLOWEST_COUNT) {
+        writeln!(
+            out,
+            "    {}: {}",
+            entry.shader,
+            format_time(entry.compose_time)
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+/// Records the statistics of a shader, then rewrites the crate's report from every shader recorded so far. Proc macros
+/// have no hook for the end of a build, so the report is complete once the last shader in the crate has been expanded.
+pub(crate) fn record(
+    module: &naga::Module,
+    shader_path: &str,
+    composed_bytes: usize,
+    compose_time: Duration,
+) -> Result<PathBuf, String> {
+    let shader = artifacts::crate_relative_path(shader_path)
+        .to_string_lossy()
+        .into_owned();
+    let entry = ReportEntry::new(module, shader, composed_bytes, compose_time);
+    artifacts::write_artifact(shader_path, ENTRY_EXTENSION, &entry.to_line())?;
+
+    let dir = artifacts::artifacts_dir()?;
+    let mut entries = Vec::new();
+    collect_entries(&dir, &mut entries);
+    entries.sort_by(|a, b| a.shader.cmp(&b.shader));
+
+    let path = dir.join("report.txt");
+    fs::write(&path, format_report(&entries))
+        .map_err(|e| format!("could not write `{}`: {e}", path.display()))?;
+
+    Ok(path)
+}
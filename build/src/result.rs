@@ -0,0 +1,1110 @@
+use std::{collections::HashSet, error::Error, fs, time::Duration};
+
+use quote::{format_ident, quote};
+
+#[cfg(feature = "naga-24")]
+use naga_to_tokenstream::{ModuleToTokens, ModuleToTokensConfig};
+
+use crate::{
+    artifacts, bindings, budget, compress, constants, defaults, docs, entry_points,
+    exports::{self, Export},
+    features, gpu_tests, hot_reload, import_sources,
+    input::Mode,
+    json, limits, merge, minify, mode, overrides, pipelines, pretty, pulling, push_constants,
+    reflect, report, snapshot,
+    source::Sourcecode,
+    structs::{self, HostLayout},
+    summary, writer,
+};
+
+/// Reflection data must be usable in `const` contexts, so any immutable `static`s generated for the module are emitted as
+/// `const`s instead, recursing into generated submodules.
+fn constify(items: &mut [syn::Item]) {
+    for item in items {
+        match item {
+            syn::Item::Static(item_static)
+                if matches!(item_static.mutability, syn::StaticMutability::None) =>
+            {
+                let syn::ItemStatic {
+                    attrs,
+                    vis,
+                    ident,
+                    ty,
+                    expr,
+                    ..
+                } = item_static.clone();
+                *item = syn::parse_quote! {
+                    #(#attrs)*
+                    #vis const #ident: #ty = #expr;
+                };
+            }
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => constify(items),
+            _ => {}
+        }
+    }
+}
+
+/// Lints allowed on every generated item, in addition to those requested with `lint_allows`.
+const DEFAULT_LINT_ALLOWS: &[&str] = &[
+    "dead_code",
+    "non_snake_case",
+    "non_camel_case_types",
+    "non_upper_case_globals",
+];
+
+/// Gives the attributes of any kind of item that we generate.
+fn item_attrs_mut(item: &mut syn::Item) -> Option<&mut Vec<syn::Attribute>> {
+    match item {
+        syn::Item::Const(item) => Some(&mut item.attrs),
+        syn::Item::Enum(item) => Some(&mut item.attrs),
+        syn::Item::Fn(item) => Some(&mut item.attrs),
+        syn::Item::Impl(item) => Some(&mut item.attrs),
+        syn::Item::Macro(item) => Some(&mut item.attrs),
+        syn::Item::Mod(item) => Some(&mut item.attrs),
+        syn::Item::Static(item) => Some(&mut item.attrs),
+        syn::Item::Struct(item) => Some(&mut item.attrs),
+        syn::Item::Trait(item) => Some(&mut item.attrs),
+        syn::Item::Type(item) => Some(&mut item.attrs),
+        syn::Item::Union(item) => Some(&mut item.attrs),
+        syn::Item::Use(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+fn validate_module(
+    module: &naga::Module,
+    capabilities: naga::valid::Capabilities,
+) -> Result<naga::valid::ModuleInfo, String> {
+    let mut validator =
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities);
+    validator
+        .validate(module)
+        .map_err(|e| {
+            let mut e_base: &dyn Error = e.as_inner();
+            let mut message = format!("{}", e);
+            let mut error_count = 1;
+            while let Some(e) = e_base.source() {
+                message = format!("{}: \n{}{}", message, "    ".repeat(error_count), e);
+                e_base = e;
+                error_count += 1;
+            }
+            message
+        })
+        .map_err(|mut message| {
+            // Point towards the opt-in capabilities which would make the module valid
+            let missing = reflect::required_capabilities(module)
+                .into_iter()
+                .filter(|name| {
+                    naga::valid::Capabilities::from_name(name)
+                        .is_some_and(|capability| !capabilities.contains(capability))
+                })
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                message += &format!(
+                    "\nhelp: enable the capabilities with `capabilities = [{}]`",
+                    missing.join(", ")
+                );
+            }
+            message
+        })
+        .and_then(|info| {
+            // The composed source is embedded as WGSL, so anything that can't be written back out is also an error
+            writer::write_wgsl(module, &info).map_err(|e| {
+                format!("the shader can't be written back out as WGSL by naga: {e}")
+            })?;
+            Ok(info)
+        })
+}
+
+/// Writes a validated module back out as WGSL, keeping the `enable f16;` directive needed by modules using `f16`.
+fn wgsl_source(module: &naga::Module, info: &naga::valid::ModuleInfo) -> String {
+    let source =
+        writer::write_wgsl(module, info).expect("validated modules should be writable as wgsl");
+
+    if reflect::uses_scalar(module, naga::ScalarKind::Float, 2) && !source.contains("enable f16;") {
+        format!("enable f16;\n\n{source}")
+    } else {
+        source
+    }
+}
+
+/// Gives a `SOURCE_HASH` constant for each `SOURCE` constant among `items`, under the same `cfg`.
+fn source_hash_items(items: &[syn::Item]) -> Vec<syn::Item> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let syn::Item::Const(item_const) = item else {
+                return None;
+            };
+            let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(source),
+                ..
+            }) = &*item_const.expr
+            else {
+                return None;
+            };
+            if item_const.ident != "SOURCE" {
+                return None;
+            }
+
+            let hash = xxhash_rust::xxh3::xxh3_64(source.value().as_bytes());
+            let cfgs = item_const
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("cfg"));
+            Some(syn::parse_quote! {
+                /// The XXH3 hash of the composed shader, which only changes when `SOURCE` does, for keying caches of
+                /// pipelines and compiled shaders. Runtime replacements of the source aren't reflected in it.
+                #(#cfgs)*
+                pub const SOURCE_HASH: u64 = #hash;
+            })
+        })
+        .collect()
+}
+
+/// The shader composed with the definitions for one of the requested targets.
+struct TargetVariant {
+    target: String,
+    module: naga::Module,
+    info: Option<naga::valid::ModuleInfo>,
+}
+
+/// Names the permuted definitions enabled by some flags, e.g. `SHADOWS | SKINNING`.
+fn permutation_defs(defs: &[String], flags: u32) -> String {
+    defs.iter()
+        .enumerate()
+        .filter(|(bit, _)| flags & 1 << bit != 0)
+        .map(|(_, def)| def.as_str())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// The shader composed with a combination of the permuted definitions.
+struct Permutation {
+    /// The definitions enabled, as bits in the order they were requested.
+    flags: u32,
+    module: naga::Module,
+    info: Option<naga::valid::ModuleInfo>,
+}
+
+/// The variant of the shader whose vertex entry points fetch their inputs from storage buffers.
+struct PullingVariant {
+    module: naga::Module,
+    info: naga::valid::ModuleInfo,
+    entry_points: Vec<pulling::PulledEntryPoint>,
+}
+
+/// The output of the transformations provided by this crate.
+pub(crate) struct ShaderResult {
+    source: Sourcecode,
+    module: naga::Module,
+    info: Option<naga::valid::ModuleInfo>,
+    variants: Vec<TargetVariant>,
+    permutations: Vec<Permutation>,
+    pulling: Option<PullingVariant>,
+    /// How long composing the shader and its variants took.
+    compose_time: Duration,
+}
+
+impl ShaderResult {
+    pub(crate) fn new(
+        source: Sourcecode,
+        module: naga::Module,
+        variants: Vec<(String, naga::Module)>,
+        permutations: Vec<(u32, naga::Module)>,
+        compose_time: Duration,
+    ) -> Self {
+        Self {
+            source,
+            module,
+            info: None,
+            variants: variants
+                .into_iter()
+                .map(|(target, module)| TargetVariant {
+                    target,
+                    module,
+                    info: None,
+                })
+                .collect(),
+            permutations: permutations
+                .into_iter()
+                .map(|(flags, module)| Permutation {
+                    flags,
+                    module,
+                    info: None,
+                })
+                .collect(),
+            pulling: None,
+            compose_time,
+        }
+    }
+
+    pub(crate) fn validate(&mut self) -> Option<naga::valid::ModuleInfo> {
+        let capabilities = self.source.options().validator_capabilities();
+        for variant in &mut self.variants {
+            match validate_module(&variant.module, capabilities) {
+                // Native-only capabilities may be enabled, but WebGPU has no 64-bit types
+                Ok(info) if variant.target == "web" => {
+                    let uses = reflect::uses_of_64_bit_types(&variant.module, &info);
+                    if !uses.is_empty() {
+                        self.source.push_error(format!(
+                            "while composing for the `web` target: 64-bit types are not supported by WebGPU, \
+                            but are used by `{}`",
+                            uses.join("`, `")
+                        ));
+                    }
+                    variant.info = Some(info);
+                }
+                Ok(info) => variant.info = Some(info),
+                Err(message) => self.source.push_error(format!(
+                    "while composing for the `{}` target: {message}",
+                    variant.target
+                )),
+            }
+        }
+        self.check_variant_types();
+
+        for permutation in &mut self.permutations {
+            match validate_module(&permutation.module, capabilities) {
+                Ok(info) => permutation.info = Some(info),
+                Err(message) => {
+                    let defs =
+                        permutation_defs(&self.source.options().permutations, permutation.flags);
+                    self.source
+                        .push_error(format!("while composing with `{defs}`: {message}"))
+                }
+            }
+        }
+
+        match validate_module(&self.module, capabilities) {
+            Ok(info) => {
+                self.info = Some(info.clone());
+                self.check_baseline();
+                self.check_source_budget();
+                self.check_runtime_mode();
+                if self.source.options().vertex_pulling {
+                    self.create_pulling_variant(capabilities);
+                }
+                Some(info)
+            }
+            Err(message) => {
+                self.source.push_error(message);
+
+                None
+            }
+        }
+    }
+
+    /// Generates a `PermutationFlags` set of the permuted definitions, and a `source_for` function selecting the
+    /// composition with some of them enabled, or `None` if some of the permutations aren't valid.
+    fn permutation_items(&self) -> Option<Vec<syn::Item>> {
+        let defs = &self.source.options().permutations;
+        if defs.is_empty() {
+            return None;
+        }
+
+        let flag_consts = defs.iter().enumerate().map(|(bit, def)| {
+            let ident = format_ident!("{}", def);
+            let flag = 1u32 << bit;
+            let doc = format!(" The composition with `{def}` defined.");
+            quote! {
+                #[doc = #doc]
+                pub const #ident: Self = Self(#flag);
+            }
+        });
+        let all = (1u32 << defs.len()) - 1;
+        let arms = self
+            .permutations
+            .iter()
+            .map(|permutation| {
+                let info = permutation.info.as_ref()?;
+                let flags = permutation.flags;
+                let source = wgsl_source(&permutation.module, info);
+                let source = if self.source.options().compress_source {
+                    compress::lazy_source(&source)
+                } else {
+                    quote! { #source }
+                };
+                Some(quote! { #flags => #source, })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let source = self.source_expr();
+
+        Some(vec![
+            syn::parse_quote! {
+                /// A set of the definitions that the shader was precompiled with every combination of.
+                #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+                pub struct PermutationFlags(u32);
+            },
+            syn::parse_quote! {
+                impl PermutationFlags {
+                    #(#flag_consts)*
+
+                    /// The composition with none of the definitions, which is `SOURCE`.
+                    pub const fn empty() -> Self {
+                        Self(0)
+                    }
+
+                    /// The composition with every definition.
+                    pub const fn all() -> Self {
+                        Self(#all)
+                    }
+
+                    /// The definitions as bits, in the order they were requested.
+                    pub const fn bits(self) -> u32 {
+                        self.0
+                    }
+
+                    /// The definitions given by bits, ignoring those which don't correspond to a definition.
+                    pub const fn from_bits_truncate(bits: u32) -> Self {
+                        Self(bits & #all)
+                    }
+
+                    /// Whether every definition in `other` is also in `self`.
+                    pub const fn contains(self, other: Self) -> bool {
+                        self.0 & other.0 == other.0
+                    }
+
+                    /// The definitions in either `self` or `other`.
+                    pub const fn union(self, other: Self) -> Self {
+                        Self(self.0 | other.0)
+                    }
+                }
+            },
+            syn::parse_quote! {
+                impl ::core::ops::BitOr for PermutationFlags {
+                    type Output = Self;
+
+                    fn bitor(self, other: Self) -> Self {
+                        self.union(other)
+                    }
+                }
+            },
+            syn::parse_quote! {
+                /// The shader precompiled with the given definitions.
+                pub fn source_for(defs: &PermutationFlags) -> &'static str {
+                    match defs.bits() {
+                        0 => #source,
+                        #(#arms)*
+                        _ => unreachable!("permutation flags only hold known definitions"),
+                    }
+                }
+            },
+        ])
+    }
+
+    /// Checks that every struct shared between the default composition and a target variant has the same layout, so
+    /// that the types generated from the default composition can be used with every target.
+    fn check_variant_types(&mut self) {
+        let base = reflect::struct_signatures(&self.module);
+
+        let mut errors = Vec::new();
+        for variant in &self.variants {
+            let other = reflect::struct_signatures(&variant.module);
+            for (name, signature) in &base {
+                let Some(other_signature) = other.get(name) else {
+                    continue;
+                };
+                if other_signature != signature {
+                    errors.push(format!(
+                        "struct `{name}` differs between the default composition and the `{}` target:\n\
+                        default: {signature}\n{}: {other_signature}",
+                        variant.target, variant.target
+                    ));
+                }
+            }
+        }
+
+        for error in errors {
+            self.source.push_error(error);
+        }
+    }
+
+    /// Creates and validates the vertex pulling variant of the default composition.
+    fn create_pulling_variant(&mut self, capabilities: naga::valid::Capabilities) {
+        let (module, entry_points) = pulling::pull_vertices(&self.module, self.source.docs());
+        match validate_module(&module, capabilities) {
+            Ok(info) => {
+                self.pulling = Some(PullingVariant {
+                    module,
+                    info,
+                    entry_points,
+                })
+            }
+            Err(message) => self.source.push_error(format!(
+                "while creating the vertex pulling variant: {message}"
+            )),
+        }
+    }
+
+    /// Checks that the composed source is within the requested `max_source_bytes` budget.
+    fn check_source_budget(&mut self) {
+        let (Some(budget), Some(source)) =
+            (self.source.options().max_source_bytes, self.wgsl_source())
+        else {
+            return;
+        };
+
+        let shader = artifacts::crate_relative_path(self.source.requested_path());
+        if let Err(e) = budget::check_source_budget(&source, &shader.to_string_lossy(), budget) {
+            self.source.push_error(e);
+        }
+    }
+
+    /// Checks that a shader to be recomposed at runtime can be written back to WGSL there.
+    fn check_runtime_mode(&mut self) {
+        if self.source.options().mode == Mode::Runtime && !self.module.overrides.is_empty() {
+            self.source.push_error(
+                "`mode = runtime` can't be used with shaders declaring overrides, as naga can't write them to WGSL \
+                at runtime"
+                    .to_owned(),
+            );
+        }
+    }
+
+    /// Checks that no valid composition uses anything newer than the requested `wgsl_baseline`.
+    fn check_baseline(&mut self) {
+        let Some(baseline) = self.source.options().wgsl_baseline.clone() else {
+            return;
+        };
+
+        let compositions = std::iter::once((String::new(), &self.module)).chain(
+            self.variants
+                .iter()
+                .filter(|variant| variant.info.is_some())
+                .map(|variant| {
+                    let context = format!("while composing for the `{}` target: ", variant.target);
+                    (context, &variant.module)
+                }),
+        );
+        let mut errors = Vec::new();
+        for (context, module) in compositions {
+            let violations = features::baseline_violations(module, &baseline);
+            if !violations.is_empty() {
+                errors.push(format!(
+                    "{context}the shader uses functionality newer than the `{baseline}` baseline selected with \
+                    `wgsl_baseline`: {}",
+                    violations.join(", ")
+                ));
+            }
+        }
+
+        for error in errors {
+            self.source.push_error(error);
+        }
+    }
+
+    /// Writes any requested files describing the shader to `OUT_DIR`.
+    pub(crate) fn write_artifacts(&mut self) {
+        // Dependencies are known even if the shader is invalid, and build systems need them to know when to retry
+        if self.source.options().depfile {
+            let shader_path = self.source.requested_path().to_owned();
+            let depfile = artifacts::depfile(self.source.invocation_path(), &self.source.files());
+            if let Err(e) = artifacts::write_artifact(&shader_path, "d", &depfile) {
+                self.source.push_error(e);
+            }
+        }
+
+        // The graph is also wanted when untangling imports which fail to compose
+        if let Some(graph) = self.source.import_graph() {
+            let shader_path = self.source.requested_path().to_owned();
+            let graph = graph.to_owned();
+            if let Err(e) = artifacts::write_artifact(&shader_path, "dot", &graph) {
+                self.source.push_error(e);
+            }
+        }
+
+        if self.info.is_none() {
+            return;
+        }
+
+        if let (Some(path), Some(source)) = (
+            self.source.options().emit_composed.clone(),
+            self.wgsl_source(),
+        ) {
+            if let Err(e) = artifacts::write_crate_file(&path, &source) {
+                self.source.push_error(e);
+            }
+        }
+
+        if self.source.options().snapshot {
+            let shader_path = self.source.requested_path().to_owned();
+            let shader_name = artifacts::crate_relative_path(&shader_path);
+            let snapshot = snapshot::snapshot(&self.module, &shader_name.to_string_lossy());
+            if let Err(e) = artifacts::write_artifact(&shader_path, "snap", &snapshot) {
+                self.source.push_error(e);
+            }
+        }
+
+        if self.source.options().reflection_json {
+            let shader_path = self.source.requested_path().to_owned();
+            let reflection = self
+                .reflection_json()
+                .expect("the shader has been validated");
+            if let Err(e) = artifacts::write_artifact(&shader_path, "json", &reflection) {
+                self.source.push_error(e);
+            }
+        }
+
+        if self.source.options().report {
+            let shader_path = self.source.requested_path().to_owned();
+            let composed_bytes = self.wgsl_source().map_or(0, |source| source.len());
+            if let Err(e) = report::record(
+                &self.module,
+                &shader_path,
+                composed_bytes,
+                self.compose_time,
+            ) {
+                self.source.push_error(e);
+            }
+        }
+    }
+
+    pub(crate) fn source(&self) -> &Sourcecode {
+        &self.source
+    }
+
+    pub(crate) fn module(&self) -> &naga::Module {
+        &self.module
+    }
+
+    pub(crate) fn info(&self) -> Option<&naga::valid::ModuleInfo> {
+        self.info.as_ref()
+    }
+
+    /// Gives the composed shader as WGSL, if it is valid.
+    pub(crate) fn wgsl_source(&self) -> Option<String> {
+        Some(wgsl_source(&self.module, self.info.as_ref()?))
+    }
+
+    /// Gives the expression for the composed shader as a `&'static str` in the generated module, which decompresses it if
+    /// it is embedded compressed.
+    fn source_expr(&self) -> proc_macro2::TokenStream {
+        if self.source.options().compress_source {
+            quote! { decompressed_source() }
+        } else {
+            quote! { SOURCE }
+        }
+    }
+
+    /// Gives the JSON description of the shader's interface, if it is valid.
+    pub(crate) fn reflection_json(&self) -> Option<String> {
+        let shader_name = artifacts::crate_relative_path(self.source.requested_path());
+        Some(json::reflection(
+            &self.module,
+            self.info.as_ref()?,
+            &shader_name.to_string_lossy(),
+            &self.source.default_shader_defs(),
+        ))
+    }
+
+    /// Gives the overview of the shader documenting the module it is included into, if it is valid.
+    pub(crate) fn module_doc(&self) -> Option<String> {
+        self.info.as_ref()?;
+        Some(summary::module_doc(&self.module, &self.source))
+    }
+
+    pub(crate) fn items(&self) -> Vec<syn::Item> {
+        let mut items = Vec::new();
+
+        // Errors
+        for msg in self.source.errors() {
+            items.push(syn::parse_quote! {
+                compile_error!(#msg);
+            });
+        }
+
+        // Dependencies, to re-run macro on shader change
+        let origin = self
+            .source
+            .invocation_path()
+            .parent()
+            .map(|path| path.to_path_buf())
+            .expect("source should have a parent directory");
+        for dependent_path in self.source.dependents() {
+            let dependent = pathdiff::diff_paths(&**dependent_path, &origin)
+                .expect("relative path should be easy");
+            let dependent = dependent.to_string_lossy();
+            items.push(syn::parse_quote! {
+                const _: &[u8] = include_bytes!(#dependent);
+            });
+        }
+        let source = self.source.requested_path();
+        items.push(syn::parse_quote! {
+            const _: &[u8] = include_bytes!(#source);
+        });
+
+        items.append(&mut self.generated_items());
+
+        items
+    }
+
+    /// Generates the items describing the shader, without the errors and dependency tracking that `items` adds for
+    /// the macro.
+    pub(crate) fn generated_items(&self) -> Vec<syn::Item> {
+        let mut items = Vec::new();
+
+        // Convert to info about the module
+        let mut module_items = self.module_items();
+        if let Some(info) = &self.info {
+            match self.struct_items(info) {
+                Ok(struct_items) => merge::merge(&mut module_items, struct_items),
+                Err(msg) => items.push(syn::parse_quote! {
+                    compile_error!(#msg);
+                }),
+            }
+            match exports::export_items(
+                &self.module,
+                self.source.exports(),
+                self.source.alias_targets(),
+            ) {
+                Ok(mut export_items) => module_items.append(&mut export_items),
+                Err(msg) => items.push(syn::parse_quote! {
+                    compile_error!(#msg);
+                }),
+            }
+        }
+        // Reflection of the resources bound by the shader, the functionality it requires, its entry points, overrides and
+        // constants
+        if let Some(info) = &self.info {
+            merge::merge(
+                &mut module_items,
+                bindings::binding_items(&self.module, info),
+            );
+            merge::merge(&mut module_items, features::feature_items(&self.module));
+            merge::merge(&mut module_items, limits::limit_items(&self.module, info));
+            merge::merge(
+                &mut module_items,
+                entry_points::entry_point_items(&self.module, info, self.source.docs()),
+            );
+            merge::merge(&mut module_items, overrides::override_items(&self.module));
+            merge::merge(
+                &mut module_items,
+                pipelines::pipeline_layout_items(&self.module),
+            );
+            merge::merge(
+                &mut module_items,
+                pipelines::render_pipeline_items(&self.module, self.source.docs()),
+            );
+            // Last, so that the shader's constants can't shadow the rest of the reflection
+            merge::merge(&mut module_items, constants::constant_items(&self.module));
+        }
+
+        constify(&mut module_items);
+        docs::attach(&mut module_items, self.source.docs());
+        exports::apply_options(&mut module_items, self.source.exports());
+        items.append(&mut module_items);
+
+        // Stripped, minified, obfuscated or compressed source, keeping the readable source for debug builds if requested
+        let options = self.source.options();
+        let info = self.info.as_ref();
+        if let Some(info) = info.filter(|_| {
+            options.debug_source || options.minify || options.obfuscate || options.compress_source
+        }) {
+            let source = wgsl_source(&self.module, info);
+            let release = if options.minify || options.obfuscate {
+                // Only names change, so the module info still applies
+                let mut module = self.module.clone();
+                if options.obfuscate {
+                    let exported_functions = self
+                        .source
+                        .exports()
+                        .keys()
+                        .filter_map(|export| match export {
+                            Export::Function { function_name } => Some(function_name.clone()),
+                            _ => None,
+                        })
+                        .collect();
+                    minify::obfuscate_names(&mut module, &exported_functions);
+                }
+                minify::shorten_names(&mut module);
+                wgsl_source(&module, info)
+            } else {
+                source.clone()
+            };
+            let release = if options.minify || options.debug_source {
+                minify::strip(&release)
+            } else {
+                release
+            };
+            let debug = if options.debug_source {
+                source
+            } else {
+                release.clone()
+            };
+            minify::replace_source(&mut items, &debug, &release);
+        }
+
+        // Hashed before compression, so that each hashes `SOURCE` as it is in the same configuration
+        let source_hashes = if self.info.is_some() {
+            source_hash_items(&items)
+        } else {
+            Vec::new()
+        };
+        if self.info.is_some() && options.compress_source {
+            compress::replace_source(&mut items);
+        }
+
+        // Indirection allowing the source to be replaced at runtime
+        let source = self.source_expr();
+        if self.info.is_some() {
+            if cfg!(feature = "runtime") {
+                items.push(syn::parse_quote! {
+                    /// The composed shader, or in debug builds a replacement registered for this module with
+                    /// `include_wgsl_oil_runtime::set_source`.
+                    pub fn source() -> ::std::borrow::Cow<'static, str> {
+                        if cfg!(debug_assertions) {
+                            ::include_wgsl_oil_runtime::source(module_path!(), #source)
+                        } else {
+                            ::std::borrow::Cow::Borrowed(#source)
+                        }
+                    }
+                });
+            } else {
+                items.push(syn::parse_quote! {
+                    /// The composed shader.
+                    pub fn source() -> ::std::borrow::Cow<'static, str> {
+                        ::std::borrow::Cow::Borrowed(#source)
+                    }
+                });
+            }
+        }
+
+        // The shader before composition, for recomposing it at runtime
+        if self.info.is_some() && self.source.options().raw_source {
+            if let Ok(raw_source) = fs::read_to_string(self.source.requested_path()) {
+                items.push(syn::parse_quote! {
+                    /// The shader file as it was read, before its imports, includes and shader defs were resolved.
+                    pub const RAW_SOURCE: &str = #raw_source;
+                });
+            }
+        }
+
+        // The sources the shader is composed from, for recomposing it at runtime
+        let runtime_mode = self.source.options().mode == Mode::Runtime;
+        if self.info.is_some() && (self.source.options().import_sources || runtime_mode) {
+            items.append(&mut import_sources::import_source_items(
+                self.source.module_sources(),
+            ));
+        }
+        if self.info.is_some() && runtime_mode {
+            items.append(&mut mode::runtime_items(&self.source));
+        }
+
+        // Rereading the shader from disk in debug builds
+        if self.info.is_some() {
+            items.append(&mut hot_reload::load_source_items(
+                &self.source,
+                self.source_expr(),
+            ));
+        }
+
+        // Hashes of the composed source, for keying caches of pipelines and compiled shaders, and of everything it was
+        // composed from
+        if self.info.is_some() {
+            items.extend(source_hashes);
+
+            let hash = self.source.content_hash();
+            items.push(syn::parse_quote! {
+                /// The XXH3 hash of the shader file, every file it imports and every include it uses, which changes
+                /// whenever any of them does, even if the composed shader doesn't.
+                pub const CONTENT_HASH: u64 = #hash;
+            });
+        }
+
+        // Files to watch for changes to the shader
+        if self.info.is_some() {
+            let files = self.source.files();
+            let files = files.iter().map(|path| path.to_string_lossy());
+            items.push(syn::parse_quote! {
+                /// The absolute path of every WGSL file that went into the composed shader, on the machine that compiled
+                /// it: the shader file, the files it imports, and the files of the includes it uses.
+                pub const DEPENDENCIES: &[&str] = &[#(#files),*];
+            });
+        }
+
+        // Shader module creation, going through `source()` so that runtime replacements are used
+        if self.info.is_some() && cfg!(feature = "wgpu") {
+            let label = artifacts::crate_relative_path(self.source.requested_path());
+            let label = label.to_string_lossy();
+            items.push(syn::parse_quote! {
+                /// Creates a shader module from the composed shader, labelled with the shader's path.
+                pub fn create_shader_module(device: &::wgpu::Device) -> ::wgpu::ShaderModule {
+                    device.create_shader_module(::wgpu::ShaderModuleDescriptor {
+                        label: Some(#label),
+                        source: ::wgpu::ShaderSource::Wgsl(source()),
+                    })
+                }
+            });
+        }
+
+        // Per-target sources
+        for variant in &self.variants {
+            let Some(info) = &variant.info else {
+                continue;
+            };
+
+            let target = format_ident!("{}", variant.target);
+            let source = wgsl_source(&variant.module, info);
+            items.push(syn::parse_quote! {
+                pub mod #target {
+                    pub const SOURCE: &str = #source;
+                }
+            });
+        }
+
+        // Permutations, selected at runtime
+        if self.info.is_some() {
+            if let Some(mut permutation_items) = self.permutation_items() {
+                items.append(&mut permutation_items);
+            }
+        }
+
+        // Vertex pulling variant
+        if let Some(pulling) = &self.pulling {
+            let source = wgsl_source(&pulling.module, &pulling.info);
+            let entry_points = pulling.entry_points.iter().map(|entry_point| {
+                let ident = reflect::item_ident(&entry_point.name);
+                let group = entry_point.group;
+                let vertex_buffer = entry_point.vertex_stride.map(|stride| {
+                    let binding = pulling::VERTEX_BINDING;
+                    let stride = u64::from(stride);
+                    quote! {
+                        /// The binding of the storage buffer holding the per-vertex inputs.
+                        pub const VERTEX_BINDING: u32 = #binding;
+                        /// The array stride of the per-vertex storage buffer, in bytes.
+                        pub const VERTEX_STRIDE: u64 = #stride;
+                    }
+                });
+                let instance_buffer = entry_point.instance_stride.map(|stride| {
+                    let binding = pulling::INSTANCE_BINDING;
+                    let stride = u64::from(stride);
+                    quote! {
+                        /// The binding of the storage buffer holding the per-instance inputs.
+                        pub const INSTANCE_BINDING: u32 = #binding;
+                        /// The array stride of the per-instance storage buffer, in bytes.
+                        pub const INSTANCE_STRIDE: u64 = #stride;
+                    }
+                });
+                quote! {
+                    pub mod #ident {
+                        /// The bind group holding the storage buffers that the entry point's inputs are fetched from.
+                        pub const GROUP: u32 = #group;
+                        #vertex_buffer
+                        #instance_buffer
+                    }
+                }
+            });
+            items.push(syn::parse_quote! {
+                /// A variant of the shader whose vertex entry points fetch their `@location` inputs from storage
+                /// buffers, indexed by the vertex and instance indices.
+                pub mod vertex_pulling {
+                    pub const SOURCE: &str = #source;
+                    #(#entry_points)*
+                }
+            });
+        }
+
+        // Headless device tests
+        if cfg!(feature = "gpu-tests") && self.info.is_some() {
+            let mut test_items =
+                gpu_tests::gpu_test_items(&self.module, self.source.requested_path());
+            items.append(&mut test_items);
+        }
+
+        pretty::raw_string_literals(&mut items);
+
+        // Names mirror the shader and most reflection goes unused, so neither should break crates denying warnings
+        let lints = DEFAULT_LINT_ALLOWS
+            .iter()
+            .map(|lint| syn::parse_str::<syn::Path>(lint).expect("default lints are paths"))
+            .chain(self.source.options().lint_allows.iter().cloned())
+            .collect::<Vec<_>>();
+        let allow: syn::Attribute = syn::parse_quote! { #[allow(#(#lints),*)] };
+        for item in &mut items {
+            if let Some(attrs) = item_attrs_mut(item) {
+                attrs.push(allow.clone());
+            }
+        }
+
+        items
+    }
+
+    /// Generates the Rust structs for the exported structs, push constants and runtime-sized storage buffers, along with
+    /// the defaults declared for them in the shader and the reflection of the push constants.
+    fn struct_items(&self, info: &naga::valid::ModuleInfo) -> Result<Vec<syn::Item>, String> {
+        let exported_structs = self
+            .source
+            .exports()
+            .keys()
+            .filter_map(|export| match export {
+                Export::Struct { struct_name } => Some(struct_name.clone()),
+                _ => None,
+            })
+            .chain(push_constants::push_constant_structs(&self.module))
+            .chain(bindings::runtime_sized_structs(&self.module))
+            .collect::<HashSet<_>>();
+        let layout = HostLayout::new(&self.module, info, self.source.options().layout());
+        let math = self.source.options().math();
+
+        let derives = self.source.options().struct_derives();
+
+        let mut items =
+            structs::struct_items(&self.module, &layout, &exported_structs, math, &derives)?;
+        items.extend(defaults::default_impls(
+            &self.module,
+            &layout,
+            &exported_structs,
+            math,
+            &derives,
+        )?);
+        items.extend(push_constants::push_constant_items(
+            &self.module,
+            info,
+            &layout,
+            math,
+        ));
+        Ok(items)
+    }
+
+    /// Generates the items describing the module's constants and globals, using `naga-to-tokenstream`. Structs are
+    /// generated by us, so that they have the same layout as in the shader.
+    #[cfg(feature = "naga-24")]
+    fn module_items(&self) -> Vec<syn::Item> {
+        self.module.to_items(ModuleToTokensConfig {
+            structs_filter: Some(Default::default()),
+            gen_glam: cfg!(feature = "glam"),
+            gen_encase: cfg!(feature = "encase"),
+            gen_naga: cfg!(feature = "naga"),
+            derive_bytemuck: cfg!(feature = "bytemuck"),
+        })
+    }
+
+    /// `naga-to-tokenstream` only tracks the current `naga` release, so on the previous backend we only emit the
+    /// composed source, along with the structs we generate ourselves.
+    #[cfg(not(feature = "naga-24"))]
+    fn module_items(&self) -> Vec<syn::Item> {
+        let Some(info) = &self.info else {
+            return Vec::new();
+        };
+
+        let source = wgsl_source(&self.module, info);
+
+        vec![syn::parse_quote! {
+            pub const SOURCE: &str = #source;
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use quote::ToTokens;
+
+    use crate::testing;
+
+    /// Gives the value of each constant named `name` among `items`, by its `cfg` attributes.
+    fn constants<'a>(items: &'a [syn::Item], name: &str) -> BTreeMap<String, &'a syn::Expr> {
+        items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Const(item) if item.ident == name => {
+                    let cfgs = item
+                        .attrs
+                        .iter()
+                        .filter(|attr| attr.path().is_ident("cfg"))
+                        .map(|attr| attr.to_token_stream().to_string())
+                        .collect();
+                    Some((cfgs, &*item.expr))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn runtime_mode_rejects_overrides() {
+        let result = testing::compose(
+            "override scale: f32 = 1.0;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scale); }",
+            |shader| shader.mode("runtime"),
+        );
+        let Err(errors) = result else {
+            panic!("shaders declaring overrides are composed with `mode = runtime`");
+        };
+        assert!(
+            errors.iter().any(|error| error.contains(
+                "`mode = runtime` can't be used with shaders declaring overrides, as naga can't write them to \
+                WGSL at runtime"
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn source_hash_matches_source() {
+        let source = "
+            @fragment
+            fn fs_main() -> @location(0) vec4<f32> {
+                let color = vec4(0.5);
+                return color;
+            }
+        ";
+        for (debug_source, compress_source) in
+            [(false, false), (true, false), (false, true), (true, true)]
+        {
+            let items = testing::compose(source, |shader| {
+                shader
+                    .debug_source(debug_source)
+                    .compress_source(compress_source)
+            })
+            .unwrap_or_else(|errors| panic!("{}", errors.join("\n")))
+            .items();
+
+            let sources = if compress_source {
+                constants(&items, "SOURCE_DEFLATE")
+            } else {
+                constants(&items, "SOURCE")
+            };
+            let hashes = constants(&items, "SOURCE_HASH");
+            assert_eq!(hashes.len(), if debug_source { 2 } else { 1 });
+            assert_eq!(
+                hashes.keys().collect::<Vec<_>>(),
+                sources.keys().collect::<Vec<_>>()
+            );
+            for (cfg, hash) in hashes {
+                let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(hash),
+                    ..
+                }) = hash
+                else {
+                    panic!("`SOURCE_HASH` is an integer literal");
+                };
+                let source = match sources[&cfg] {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(source),
+                        ..
+                    }) => source.value(),
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::ByteStr(bytes),
+                        ..
+                    }) => {
+                        let bytes =
+                            miniz_oxide::inflate::decompress_to_vec(&bytes.value()).unwrap();
+                        String::from_utf8(bytes).unwrap()
+                    }
+                    source => panic!("unexpected source `{}`", source.to_token_stream()),
+                };
+                assert_eq!(
+                    hash.base10_parse::<u64>().unwrap(),
+                    xxhash_rust::xxh3::xxh3_64(source.as_bytes()),
+                    "{cfg}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt::Write;
+
+use crate::reflect;
+
+/// Gives a canonical textual description of the interface of a module. Everything is sorted, so the snapshot only
+/// changes when the types, bindings or entry points of the shader change.
+pub(crate) fn snapshot(module: &naga::Module, shader_name: &str) -> String {
+    let layouter = reflect::layouter(module);
+    let mut out = format!("# include-wgsl-oil reflection snapshot of `{shader_name}`\n");
+
+    // Types
+    let mut structs = module
+        .types
+        .iter()
+        .filter_map(|(handle, ty)| match &ty.inner {
+            naga::TypeInner::Struct { members, span } => Some((
+                reflect::display_name(ty.name.as_deref()?).into_owned(),
+                handle,
+                members,
+                *span,
+            )),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    structs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    writeln!(out, "\ntypes:").unwrap();
+    for (name, handle, members, span) in structs {
+        let align = layouter[handle].alignment;
+        writeln!(out, "    struct {name}: size {span}, align {align}").unwrap();
+        for member in members {
+            let binding = member
+                .binding
+                .as_ref()
+                .map(|binding| reflect::binding_attribute(binding) + " ")
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "        {}: {binding}{}: {}",
+                member.offset,
+                member.name.as_deref().unwrap_or("_"),
+                reflect::type_name(module, member.ty)
+            )
+            .unwrap();
+        }
+    }
+
+    // Bindings
+    let mut bindings = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, global)| Some((global.binding.as_ref()?, global)))
+        .collect::<Vec<_>>();
+    bindings.sort_by_key(|(binding, _)| (binding.group, binding.binding));
+
+    writeln!(out, "\nbindings:").unwrap();
+    for (binding, global) in bindings {
+        writeln!(
+            out,
+            "    @group({}) @binding({}) {} {}: {}",
+            binding.group,
+            binding.binding,
+            reflect::global_declaration(global.space),
+            reflect::display_name(global.name.as_deref().unwrap_or("_")),
+            reflect::type_name(module, global.ty)
+        )
+        .unwrap();
+    }
+
+    // Entry points
+    let mut entry_points = module.entry_points.iter().collect::<Vec<_>>();
+    entry_points.sort_by(|a, b| a.name.cmp(&b.name));
+
+    writeln!(out, "\nentry points:").unwrap();
+    for entry_point in entry_points {
+        let mut attributes = reflect::stage_attribute(entry_point.stage).to_owned();
+        if entry_point.stage == naga::ShaderStage::Compute {
+            let [x, y, z] = entry_point.workgroup_size;
+            write!(attributes, " @workgroup_size({x}, {y}, {z})").unwrap();
+        }
+
+        let arguments = entry_point
+            .function
+            .arguments
+            .iter()
+            .map(|argument| {
+                let binding = argument
+                    .binding
+                    .as_ref()
+                    .map(|binding| reflect::binding_attribute(binding) + " ")
+                    .unwrap_or_default();
+                format!(
+                    "{binding}{}: {}",
+                    argument.name.as_deref().unwrap_or("_"),
+                    reflect::type_name(module, argument.ty)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let result = entry_point
+            .function
+            .result
+            .as_ref()
+            .map(|result| {
+                let binding = result
+                    .binding
+                    .as_ref()
+                    .map(|binding| reflect::binding_attribute(binding) + " ")
+                    .unwrap_or_default();
+                format!(" -> {binding}{}", reflect::type_name(module, result.ty))
+            })
+            .unwrap_or_default();
+
+        writeln!(
+            out,
+            "    {attributes} fn {}({arguments}){result}",
+            entry_point.name
+        )
+        .unwrap();
+    }
+
+    out
+}
@@ -0,0 +1,552 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::PathBuf,
+    time::Instant,
+};
+
+use naga_oil::compose::{ComposableModuleDescriptor, Composer, ShaderDefValue};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::{
+    artifacts,
+    docs::DocComments,
+    exports::{alias_targets, strip_exports, Export, ExportOptions},
+    files::{self, AbsoluteRustFilePathBuf, AbsoluteRustRootPathBuf, AbsoluteWGSLFilePathBuf},
+    graph,
+    imports::{self, ImportOrder},
+    includes::Include,
+    input::{Constants, MacroInput, Options, TARGETS},
+    library, overrides, reflect,
+    result::ShaderResult,
+};
+
+/// A file that the shader is composed from, with the name it is added to the composer as and the names its imports by
+/// path are renamed to, so that it can be read and composed again at runtime.
+pub(crate) struct ModuleFile {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    pub(crate) imports: Vec<(String, String)>,
+    /// The module's source, for modules which aren't files on disk, such as the built-in library.
+    pub(crate) source: Option<String>,
+}
+
+/// Shader sourcecode generated from the token stream provided
+pub(crate) struct Sourcecode {
+    exports: HashMap<Export, ExportOptions>,
+    /// The types given to the aliases declared in the shader, by name.
+    alias_targets: HashMap<String, String>,
+    docs: DocComments,
+    requested_path_input: String,
+    source_path: AbsoluteWGSLFilePathBuf,
+    invocation_path: AbsoluteRustFilePathBuf,
+    project_root: Option<AbsoluteRustRootPathBuf>,
+    errors: Vec<String>,
+    dependents: Vec<AbsoluteWGSLFilePathBuf>,
+    import_tree: Vec<(usize, AbsoluteWGSLFilePathBuf)>,
+    /// The import graph of the shader in Graphviz DOT, if requested.
+    import_graph: Option<String>,
+    includes: HashMap<String, Include>,
+    /// The names of the includes added to the composer, in the order first added.
+    used_includes: Vec<String>,
+    /// The name and preprocessed source of every module added to the composer, in the order added.
+    module_sources: Vec<(String, String)>,
+    /// The preprocessed source of the shader file, as passed to the composer.
+    root_source: String,
+    /// The files of the modules added to the composer, in the order added, followed by the shader file.
+    module_files: Vec<ModuleFile>,
+    constants: Constants,
+    targets: Vec<(String, Constants)>,
+    options: Options,
+}
+
+impl Sourcecode {
+    pub(crate) fn new(invocation_path: AbsoluteRustFilePathBuf, ins: MacroInput) -> Self {
+        let MacroInput {
+            wgsl_path: requested_path_input,
+            mut includes,
+            constants,
+            targets,
+            options,
+        } = ins;
+
+        let mut errors = Vec::new();
+        // Shaders outside of the crate may be moved or changed without cargo knowing, so must be opted into
+        for (_, path, _) in includes.values() {
+            if let Err(e) = files::check_within_crate(path, options.allow_external_paths) {
+                errors.push(e);
+            }
+        }
+
+        // Built-in modules are available to every shader, unless shadowed by a user include of the same name
+        for (name, include) in library::library_includes() {
+            includes.entry(name).or_insert(include);
+        }
+
+        // Interpret as relative to invoking file
+        let source_path = invocation_path
+            .parent()
+            .expect("files have parent directories")
+            .join(&requested_path_input);
+        if !source_path.is_file() {
+            if source_path.exists() {
+                panic!(
+                    "could not find import `{}`: `{}` exists but is not a file",
+                    requested_path_input,
+                    source_path.display()
+                )
+            }
+            panic!(
+                "could not find import `{}`: `{}` does not exist",
+                requested_path_input,
+                source_path.display()
+            );
+        }
+        assert!(source_path.is_absolute());
+
+        if source_path.extension() != Some(OsStr::new("wgsl")) {
+            panic!(
+                "file `{}` does not have the required `.wgsl` extension",
+                requested_path_input,
+            );
+        };
+
+        let source_path = AbsoluteWGSLFilePathBuf::new(source_path);
+        if let Err(e) = files::check_within_crate(&source_path, options.allow_external_paths) {
+            errors.push(e);
+        }
+
+        // Calculate top level exports
+        let root_src = std::fs::read_to_string(&*source_path).expect("asserted was file");
+        let (stripped_src, exports) = strip_exports(&root_src, options.export_directive());
+        let exports = exports
+            .into_iter()
+            .filter_map(
+                |(export, arguments)| match ExportOptions::parse(&arguments) {
+                    Ok(options) => Some((export, options)),
+                    Err(e) => {
+                        errors.push(e);
+                        None
+                    }
+                },
+            )
+            .collect();
+        let alias_targets = alias_targets(&stripped_src);
+        let mut docs = DocComments::parse(&stripped_src);
+        errors.extend(docs.take_errors());
+
+        let project_root = invocation_path.get_source_rust_root();
+
+        Self {
+            requested_path_input,
+            source_path,
+            invocation_path,
+            project_root,
+            exports,
+            alias_targets,
+            docs,
+            errors,
+            dependents: Vec::new(),
+            import_tree: Vec::new(),
+            import_graph: None,
+            module_sources: Vec::new(),
+            root_source: String::new(),
+            module_files: Vec::new(),
+            includes,
+            used_includes: Vec::new(),
+            constants,
+            targets,
+            options,
+        }
+    }
+
+    /// Traverses the imports in each file, starting with the file given by this object, to give all of the files required
+    /// and the order in which they need to be processed.
+    fn find_import_order(&mut self) -> Option<ImportOrder> {
+        match ImportOrder::calculate(self.source_path.clone(), self.project_root.as_ref()) {
+            Ok(order) => Some(order),
+            Err(err) => {
+                self.push_error(format!("{}", err));
+                None
+            }
+        }
+    }
+
+    /// Gives the definitions passed to the preprocessor, optionally including those for one of the requested targets.
+    fn shader_defs(&self, target: Option<&(String, Constants)>) -> HashMap<String, ShaderDefValue> {
+        let mut shader_defs = HashMap::new();
+        if cfg!(debug_assertions) {
+            shader_defs.insert("__DEBUG".to_string(), ShaderDefValue::Bool(true));
+        }
+
+        for (a, b) in &self.constants.inner {
+            shader_defs.insert(a.clone(), *b);
+        }
+
+        for name in TARGETS {
+            let is_target = target.is_some_and(|(target_name, _)| target_name == name);
+            shader_defs.insert(
+                format!("__{}", name.to_uppercase()),
+                ShaderDefValue::Bool(is_target),
+            );
+        }
+
+        if let Some((_, target_constants)) = target {
+            for (a, b) in &target_constants.inner {
+                shader_defs.insert(a.clone(), *b);
+            }
+        }
+
+        shader_defs
+    }
+
+    /// Gives the definitions of the default composition, sorted by name.
+    pub(crate) fn default_shader_defs(&self) -> BTreeMap<String, ShaderDefValue> {
+        self.shader_defs(None).into_iter().collect()
+    }
+
+    /// Uses naga_oil to process includes
+    fn compose(&mut self, shader_defs: HashMap<String, ShaderDefValue>) -> Option<naga::Module> {
+        let mut composer = Composer::default();
+        composer.capabilities = naga::valid::Capabilities::all();
+        composer.validate = true;
+
+        let (_, reqs, _) = naga_oil::compose::get_preprocessor_data(
+            fs::read_to_string(self.requested_path()).ok()?.as_str(),
+        );
+
+        let mut reqs = reqs
+            .into_iter()
+            .map(|req| req.import)
+            .collect::<HashSet<_>>();
+
+        let mut used_includes = Vec::new();
+        let mut module_sources = Vec::new();
+        let mut module_files = Vec::new();
+        while !reqs.is_empty() {
+            let mut next_reqs = HashSet::default();
+
+            for (req, (subreqs, path, src)) in reqs
+                .iter()
+                .filter_map(|r| self.includes.get(r).map(|n| (r, n)))
+            {
+                if composer.contains_module(req) {
+                    continue;
+                }
+
+                if subreqs.iter().all(|sr| composer.contains_module(&sr)) {
+                    let (src, _) = strip_exports(src, self.options.export_directive());
+                    composer
+                        .add_composable_module(ComposableModuleDescriptor {
+                            source: &src,
+                            file_path: &path.to_string_lossy(),
+                            language: naga_oil::compose::ShaderLanguage::Wgsl,
+                            as_name: Some(req.clone()),
+                            ..Default::default()
+                        })
+                        .unwrap();
+                    used_includes.push(req.clone());
+                    module_files.push(ModuleFile {
+                        name: req.clone(),
+                        path: path.clone(),
+                        imports: Vec::new(),
+                        source: (!path.is_file()).then(|| src.clone()),
+                    });
+                    module_sources.push((req.clone(), src));
+                }
+                next_reqs.extend(
+                    subreqs
+                        .iter()
+                        .cloned()
+                        .filter(|r| !composer.contains_module(r)),
+                );
+                next_reqs.insert(req.clone());
+            }
+
+            reqs = next_reqs;
+        }
+        for include in used_includes {
+            if !self.used_includes.contains(&include) {
+                self.used_includes.push(include);
+            }
+        }
+
+        // Calculate import order
+        let import_order = self.find_import_order()?;
+        self.import_tree = import_order.import_tree();
+        if self.options.import_graph {
+            self.import_graph = Some(graph::import_graph(&import_order, &self.includes));
+        }
+
+        // Calculate names of imports
+        let reduced_names = import_order.reduced_names();
+
+        // Add imports in order to naga-oil
+        let (imports, root) = import_order.modules();
+        for import in imports {
+            if let Err(e) =
+                files::check_within_crate(&import.path(), self.options.allow_external_paths)
+            {
+                self.push_error(e);
+                continue;
+            }
+            if !self.dependents.contains(&import.path()) {
+                self.dependents.push(import.path());
+            }
+
+            let desc = import.to_composable_module_descriptor(
+                &reduced_names,
+                self.project_root.as_ref(),
+                shader_defs.clone(),
+                self.options.export_directive(),
+            );
+            let desc = match desc {
+                Ok(desc) => desc,
+                Err(errors) => {
+                    for error in errors {
+                        self.push_error(error);
+                    }
+                    return None;
+                }
+            };
+
+            module_sources.push((desc.as_name().to_owned(), desc.source().to_owned()));
+            module_files.push(ModuleFile {
+                name: desc.as_name().to_owned(),
+                path: import.path().to_path_buf(),
+                imports: imports::import_renames(
+                    &import.read_to_string(),
+                    &import,
+                    self.project_root.as_ref(),
+                    &reduced_names,
+                ),
+                source: None,
+            });
+            let res = composer.add_composable_module(desc.borrow_composable_descriptor());
+            if let Err(e) = res {
+                self.push_error(crate::error::format_compose_error(e, &composer));
+            }
+        }
+
+        self.module_sources = module_sources;
+
+        if !self.errors.is_empty() {
+            return None;
+        }
+
+        // Add main module to link everything
+        let desc = root.to_naga_module_descriptor(
+            &reduced_names,
+            self.project_root.as_ref(),
+            shader_defs,
+            self.options.export_directive(),
+        );
+        let desc = match desc {
+            Ok(desc) => desc,
+            Err(errors) => {
+                for error in errors {
+                    self.push_error(error);
+                }
+                return None;
+            }
+        };
+        self.root_source = desc.source().to_owned();
+        module_files.push(ModuleFile {
+            name: artifacts::crate_relative_path(self.requested_path())
+                .to_string_lossy()
+                .into_owned(),
+            path: root.path().to_path_buf(),
+            imports: imports::import_renames(
+                &root.read_to_string(),
+                &root,
+                self.project_root.as_ref(),
+                &reduced_names,
+            ),
+            source: None,
+        });
+        self.module_files = module_files;
+        let res = composer.make_naga_module(desc.borrow_module_descriptor());
+
+        match res {
+            Ok(module) => Some(module),
+            Err(e) => {
+                // naga rejects overrides which share an `@id` without saying which collide, so name them instead
+                composer.validate = false;
+                let module = composer
+                    .make_naga_module(desc.borrow_module_descriptor())
+                    .ok();
+                let duplicate_ids = module
+                    .as_ref()
+                    .map(overrides::duplicate_ids)
+                    .unwrap_or_default();
+                if duplicate_ids.is_empty() {
+                    let mut message = crate::error::format_compose_error(e, &composer);
+                    // naga only says that a type isn't host-shareable, so point out the `bool`s keeping it from being
+                    for bound in module
+                        .as_ref()
+                        .map(reflect::bound_bools)
+                        .unwrap_or_default()
+                    {
+                        message += &format!(
+                            "\nhelp: {bound}, which can't be in buffers or push constants; use a `u32` instead"
+                        );
+                    }
+                    // Likewise, naga only says that it expected a data type for members which are resources
+                    for member in module
+                        .as_ref()
+                        .map(reflect::unstorable_members)
+                        .unwrap_or_default()
+                    {
+                        message += &format!(
+                            "\nhelp: {member}; structs can only hold scalars, vectors, matrices, atomics, arrays \
+                            and other structs"
+                        );
+                    }
+                    self.push_error(message);
+                }
+                for error in duplicate_ids {
+                    self.push_error(error);
+                }
+
+                None
+            }
+        }
+    }
+
+    pub(crate) fn complete(mut self) -> ShaderResult {
+        let start = Instant::now();
+        let module = self.compose(self.shader_defs(None)).unwrap_or_default();
+
+        // Compose again for each requested target
+        let mut variants = Vec::new();
+        for i in 0..self.targets.len() {
+            let shader_defs = self.shader_defs(Some(&self.targets[i]));
+            if let Some(variant) = self.compose(shader_defs) {
+                variants.push((self.targets[i].0.clone(), variant));
+            }
+        }
+
+        // Compose again for each combination of the permuted definitions, apart from none of them, which is the default
+        let mut permutations = Vec::new();
+        for flags in 1..1u32 << self.options.permutations.len() {
+            let mut shader_defs = self.shader_defs(None);
+            for (bit, def) in self.options.permutations.iter().enumerate() {
+                if flags & 1 << bit != 0 {
+                    shader_defs.insert(def.clone(), ShaderDefValue::Bool(true));
+                }
+            }
+            if let Some(permutation) = self.compose(shader_defs) {
+                permutations.push((flags, permutation));
+            }
+        }
+
+        let compose_time = start.elapsed();
+
+        ShaderResult::new(self, module, variants, permutations, compose_time)
+    }
+
+    pub(crate) fn push_error(&mut self, message: String) {
+        self.errors.push(message)
+    }
+
+    pub(crate) fn errors(&self) -> impl Iterator<Item = &String> {
+        self.errors.iter()
+    }
+
+    pub(crate) fn dependents(&self) -> impl Iterator<Item = &AbsoluteWGSLFilePathBuf> {
+        self.dependents.iter()
+    }
+
+    /// The path of every file that went into the composition: the shader file, the files it imports, and the files of the
+    /// includes it uses, leaving out the built-in library, which isn't read from disk.
+    pub(crate) fn files(&self) -> Vec<PathBuf> {
+        let includes = self
+            .used_includes
+            .iter()
+            .filter_map(|name| self.includes.get(name))
+            .map(|(_, path, _)| path.clone())
+            .filter(|path| path.is_absolute());
+        std::iter::once(self.source_path.to_path_buf())
+            .chain(self.dependents.iter().map(|path| path.to_path_buf()))
+            .chain(includes)
+            .collect()
+    }
+
+    /// Hashes the shader file, every file it imports and every include it uses, by their names and contents, so that the
+    /// hash changes whenever any part of the shader does.
+    pub(crate) fn content_hash(&self) -> u64 {
+        let mut files = std::iter::once(&self.source_path)
+            .chain(&self.dependents)
+            .map(|path| {
+                let name = artifacts::crate_relative_path(&path.to_string_lossy());
+                let source = fs::read_to_string(&**path).unwrap_or_default();
+                (name.to_string_lossy().into_owned(), source)
+            })
+            .collect::<Vec<_>>();
+        let mut includes = self
+            .used_includes
+            .iter()
+            .filter_map(|name| {
+                let (_, _, source) = self.includes.get(name)?;
+                Some((name.clone(), source.clone()))
+            })
+            .collect::<Vec<_>>();
+        files[1..].sort();
+        includes.sort();
+
+        let mut hasher = Xxh3::new();
+        for (name, source) in files.iter().chain(&includes) {
+            hasher.update(name.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(source.as_bytes());
+            hasher.update(&[0]);
+        }
+        hasher.digest()
+    }
+
+    /// The files imported by the shader, as `(depth, path)` pairs walking the tree of imports depth first.
+    pub(crate) fn import_tree(&self) -> &[(usize, AbsoluteWGSLFilePathBuf)] {
+        &self.import_tree
+    }
+
+    pub(crate) fn module_sources(&self) -> &[(String, String)] {
+        &self.module_sources
+    }
+
+    pub(crate) fn module_files(&self) -> &[ModuleFile] {
+        &self.module_files
+    }
+
+    pub(crate) fn root_source(&self) -> &str {
+        &self.root_source
+    }
+
+    pub(crate) fn import_graph(&self) -> Option<&str> {
+        self.import_graph.as_deref()
+    }
+
+    pub(crate) fn requested_path(&self) -> &str {
+        &self.requested_path_input
+    }
+
+    pub(crate) fn invocation_path(&self) -> &AbsoluteRustFilePathBuf {
+        &self.invocation_path
+    }
+
+    pub(crate) fn exports(&self) -> &HashMap<Export, ExportOptions> {
+        &self.exports
+    }
+
+    pub(crate) fn alias_targets(&self) -> &HashMap<String, String> {
+        &self.alias_targets
+    }
+
+    pub(crate) fn docs(&self) -> &DocComments {
+        &self.docs
+    }
+
+    pub(crate) fn options(&self) -> &Options {
+        &self.options
+    }
+}
@@ -0,0 +1,956 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+
+use crate::{reflect, values};
+
+/// How vectors and matrices are represented in the generated structs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Math {
+    /// Arrays of scalars, with matrices as arrays of columns.
+    Arrays,
+    /// `glam` types where `glam` has one with the same layout and no padding of its own, and arrays otherwise.
+    Glam,
+    /// `nalgebra` types, except for three component vectors and matrix columns which WGSL pads, which are arrays.
+    Nalgebra,
+    /// `mint` types, except for three component vectors and matrix columns which WGSL pads, which are arrays.
+    Mint,
+}
+
+impl Math {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "arrays" => Some(Self::Arrays),
+            "glam" => Some(Self::Glam),
+            "nalgebra" => Some(Self::Nalgebra),
+            "mint" => Some(Self::Mint),
+            _ => None,
+        }
+    }
+
+    /// The representation selected by the crate's features.
+    pub(crate) fn from_features() -> Self {
+        if cfg!(feature = "glam") {
+            Self::Glam
+        } else {
+            Self::Arrays
+        }
+    }
+
+    /// Gives the type representing a vector, if it isn't an array. `padded` vectors are three component vectors given a
+    /// fourth component by WGSL's layout rules, as elements of arrays.
+    pub(crate) fn vector(
+        self,
+        size: naga::VectorSize,
+        scalar: naga::Scalar,
+        padded: bool,
+    ) -> Option<TokenStream> {
+        match self {
+            Self::Arrays => None,
+            Self::Glam => {
+                let prefix = match (scalar.kind, scalar.width) {
+                    (naga::ScalarKind::Float, 4) => "",
+                    (naga::ScalarKind::Float, 8) => "D",
+                    (naga::ScalarKind::Sint, 4) => "I",
+                    (naga::ScalarKind::Uint, 4) => "U",
+                    (naga::ScalarKind::Sint, 8) => "I64",
+                    (naga::ScalarKind::Uint, 8) => "U64",
+                    (naga::ScalarKind::Bool, _) => "B",
+                    _ => return None,
+                };
+                if padded && size == naga::VectorSize::Tri {
+                    return None;
+                }
+                let ident = format_ident!("{}Vec{}", prefix, size as u8);
+                Some(quote! { ::glam::#ident })
+            }
+            Self::Nalgebra | Self::Mint => {
+                if padded && size == naga::VectorSize::Tri {
+                    return None;
+                }
+                let scalar = values::scalar_type(scalar).ok()?;
+                let ident = format_ident!("Vector{}", size as u8);
+                Some(match self {
+                    Self::Mint => quote! { ::mint::#ident<#scalar> },
+                    _ => quote! { ::nalgebra::#ident<#scalar> },
+                })
+            }
+        }
+    }
+
+    /// Gives the type representing a matrix, if it isn't an array of columns. `padded` is whether the matrix is in a
+    /// struct with its WGSL layout, so has padded columns if they have three components.
+    pub(crate) fn matrix(
+        self,
+        columns: naga::VectorSize,
+        rows: naga::VectorSize,
+        scalar: naga::Scalar,
+        padded: bool,
+    ) -> Option<TokenStream> {
+        match self {
+            Self::Arrays => None,
+            // `glam` only has square matrices, its three by three matrices aren't padded like WGSL's, and its `f32` two
+            // by two matrix is more aligned than WGSL's
+            Self::Glam => {
+                let misaligned = match rows {
+                    naga::VectorSize::Bi => scalar == naga::Scalar::F32,
+                    naga::VectorSize::Tri => true,
+                    naga::VectorSize::Quad => false,
+                };
+                if columns != rows || (padded && misaligned) {
+                    return None;
+                }
+                let ident = match scalar {
+                    naga::Scalar::F32 => format_ident!("Mat{}", columns as u8),
+                    naga::Scalar::F64 => format_ident!("DMat{}", columns as u8),
+                    _ => return None,
+                };
+                Some(quote! { ::glam::#ident })
+            }
+            Self::Nalgebra => {
+                if padded && rows == naga::VectorSize::Tri {
+                    return None;
+                }
+                let scalar = values::scalar_type(scalar).ok()?;
+                // `nalgebra` names matrices by their rows and then columns, unlike WGSL
+                let ident = if columns == rows {
+                    format_ident!("Matrix{}", columns as u8)
+                } else {
+                    format_ident!("Matrix{}x{}", rows as u8, columns as u8)
+                };
+                Some(quote! { ::nalgebra::#ident<#scalar> })
+            }
+            Self::Mint => {
+                if padded && rows == naga::VectorSize::Tri {
+                    return None;
+                }
+                let scalar = values::scalar_type(scalar).ok()?;
+                // `mint` names matrices by their rows and then columns, unlike WGSL
+                let ident = if columns == rows {
+                    format_ident!("ColumnMatrix{}", columns as u8)
+                } else {
+                    format_ident!("ColumnMatrix{}x{}", rows as u8, columns as u8)
+                };
+                Some(quote! { ::mint::#ident<#scalar> })
+            }
+        }
+    }
+}
+
+/// How the generated structs are laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StructLayout {
+    /// Host-shareable structs are `#[repr(C)]` with their WGSL layout, given by explicit padding fields.
+    ReprC,
+    /// Structs derive `encase::ShaderType`, which lays them out when they are written to buffers.
+    Encase,
+}
+
+impl StructLayout {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "repr_c" => Some(Self::ReprC),
+            "encase" => Some(Self::Encase),
+            _ => None,
+        }
+    }
+
+    /// The layout selected by the crate's features.
+    pub(crate) fn from_features() -> Self {
+        if cfg!(feature = "encase") {
+            Self::Encase
+        } else {
+            Self::ReprC
+        }
+    }
+}
+
+/// The layout of the generated structs, of which host-shareable structs with the `repr_c` layout are padded. Structs
+/// which would be host-shareable but for their `bool` members are also padded, with each `bool` stored as a `u32` and
+/// the struct laid out by WGSL's rules as if it were one.
+pub(crate) struct HostLayout {
+    struct_layout: StructLayout,
+    padded: Vec<bool>,
+    types: Vec<TypeLayout>,
+}
+
+/// The layout of a type on the host.
+struct TypeLayout {
+    size: u32,
+    alignment: u32,
+    /// For structs, the offset of each member.
+    offsets: Vec<u32>,
+}
+
+impl HostLayout {
+    pub(crate) fn new(
+        module: &naga::Module,
+        info: &naga::valid::ModuleInfo,
+        struct_layout: StructLayout,
+    ) -> Self {
+        let padded = module
+            .types
+            .iter()
+            .map(|(handle, ty)| {
+                matches!(ty.inner, naga::TypeInner::Struct { .. })
+                    && shareable_with_bools(module, info, handle)
+                    && struct_layout == StructLayout::ReprC
+            })
+            .collect();
+
+        // Types are declared after the types they use, so members are laid out before their structs
+        let layouter = reflect::layouter(module);
+        let mut types: Vec<TypeLayout> = Vec::new();
+        for (handle, ty) in module.types.iter() {
+            let shader = TypeLayout {
+                size: layouter[handle].size,
+                alignment: layouter[handle].alignment.round_up(1),
+                offsets: match &ty.inner {
+                    naga::TypeInner::Struct { members, .. } => {
+                        members.iter().map(|member| member.offset).collect()
+                    }
+                    _ => Vec::new(),
+                },
+            };
+            let layout = match &ty.inner {
+                _ if is_bool(module, handle) => TypeLayout {
+                    size: 4,
+                    alignment: 4,
+                    offsets: Vec::new(),
+                },
+                naga::TypeInner::Struct { members, .. }
+                    if !info[handle].contains(naga::valid::TypeFlags::HOST_SHAREABLE)
+                        && shareable_with_bools(module, info, handle) =>
+                {
+                    let mut offsets = Vec::new();
+                    let (mut end, mut alignment) = (0u32, 1u32);
+                    for member in members {
+                        let member = &types[member.ty.index()];
+                        let offset = end.next_multiple_of(member.alignment);
+                        offsets.push(offset);
+                        end = offset + member.size;
+                        alignment = alignment.max(member.alignment);
+                    }
+                    TypeLayout {
+                        size: end.next_multiple_of(alignment),
+                        alignment,
+                        offsets,
+                    }
+                }
+                _ => shader,
+            };
+            types.push(layout);
+        }
+
+        Self {
+            struct_layout,
+            padded,
+            types,
+        }
+    }
+
+    /// The size of a type on the host, in bytes, which is its size in the shader unless it is or has a `bool`.
+    pub(crate) fn size(&self, ty: naga::Handle<naga::Type>) -> u32 {
+        self.types[ty.index()].size
+    }
+
+    /// The alignment of a type on the host, in bytes, which is its alignment in the shader unless it is or has a
+    /// `bool`.
+    pub(crate) fn alignment(&self, ty: naga::Handle<naga::Type>) -> u32 {
+        self.types[ty.index()].alignment
+    }
+
+    /// The offsets of the members of a struct on the host, in bytes.
+    pub(crate) fn offsets(&self, ty: naga::Handle<naga::Type>) -> &[u32] {
+        &self.types[ty.index()].offsets
+    }
+
+    /// Whether a struct is laid out with explicit padding fields.
+    pub(crate) fn is_padded(&self, ty: naga::Handle<naga::Type>) -> bool {
+        self.padded[ty.index()]
+    }
+
+    /// Gives the bytes of padding after each of the first members of a padded struct, before the next member or the
+    /// given end of the struct. Padding fields are named `_pad0`, `_pad1`, etc. in order.
+    pub(crate) fn member_padding(
+        &self,
+        ty: naga::Handle<naga::Type>,
+        members: &[naga::StructMember],
+        span: u32,
+    ) -> Vec<u32> {
+        let offsets = self.offsets(ty);
+        members
+            .iter()
+            .enumerate()
+            .map(|(index, member)| {
+                let end = offsets[index] + self.size(member.ty);
+                let next = offsets.get(index + 1).filter(|_| index + 1 < members.len());
+                next.copied().unwrap_or(span) - end
+            })
+            .collect()
+    }
+}
+
+/// Whether a type is a scalar `bool`, which generated structs store as a `u32`.
+pub(crate) fn is_bool(module: &naga::Module, ty: naga::Handle<naga::Type>) -> bool {
+    matches!(
+        module.types[ty].inner,
+        naga::TypeInner::Scalar(naga::Scalar {
+            kind: naga::ScalarKind::Bool,
+            ..
+        })
+    )
+}
+
+/// Whether a type would be host-shareable if its `bool` members were `u32`s, looking through nested structs.
+fn shareable_with_bools(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    ty: naga::Handle<naga::Type>,
+) -> bool {
+    match &module.types[ty].inner {
+        _ if info[ty].contains(naga::valid::TypeFlags::HOST_SHAREABLE) => true,
+        naga::TypeInner::Struct { members, .. } => members.iter().all(|member| {
+            is_bool(module, member.ty) || shareable_with_bools(module, info, member.ty)
+        }),
+        _ => false,
+    }
+}
+
+/// Gives the Rust type of a vector. `padded` vectors are three component vectors given their fourth component by WGSL's
+/// layout rules. `ty` is the type containing the vector, for errors.
+fn vector_type(
+    module: &naga::Module,
+    layout: &HostLayout,
+    ty: naga::Handle<naga::Type>,
+    (size, scalar): (naga::VectorSize, naga::Scalar),
+    math: Math,
+    padded: bool,
+) -> Result<TokenStream, String> {
+    if let Some(ty) = math.vector(size, scalar, padded) {
+        return Ok(ty);
+    }
+    // `encase` lays arrays out as WGSL arrays, so vectors and matrices must be types it knows
+    if layout.struct_layout == StructLayout::Encase {
+        return Err(format!(
+            "`{}` can only be laid out by `encase` with a math library, e.g. `math = glam`",
+            reflect::type_name(module, ty)
+        ));
+    }
+    let scalar = values::scalar_type(scalar)?;
+    let size = Literal::usize_unsuffixed(if padded { 4 } else { size as usize });
+    Ok(quote! { [#scalar; #size] })
+}
+
+/// Gives the Rust type of the elements of an array. In padded structs, three component vectors are given their fourth
+/// component, as arrays of them have a stride of 16 bytes.
+pub(crate) fn element_type(
+    module: &naga::Module,
+    layout: &HostLayout,
+    base: naga::Handle<naga::Type>,
+    math: Math,
+    padded: bool,
+) -> Result<TokenStream, String> {
+    match module.types[base].inner {
+        naga::TypeInner::Vector {
+            size: naga::VectorSize::Tri,
+            scalar,
+        } if padded => vector_type(
+            module,
+            layout,
+            base,
+            (naga::VectorSize::Tri, scalar),
+            math,
+            true,
+        ),
+        _ => field_type(module, layout, base, math, padded),
+    }
+}
+
+/// Gives the Rust type of a struct member. `padded` is whether the struct has its WGSL layout, so three component
+/// vectors in arrays and matrices are given their fourth component.
+pub(crate) fn field_type(
+    module: &naga::Module,
+    layout: &HostLayout,
+    ty: naga::Handle<naga::Type>,
+    math: Math,
+    padded: bool,
+) -> Result<TokenStream, String> {
+    match &module.types[ty].inner {
+        naga::TypeInner::Scalar(scalar) | naga::TypeInner::Atomic(scalar) => {
+            values::scalar_type(*scalar)
+        }
+        naga::TypeInner::Vector { size, scalar } => {
+            vector_type(module, layout, ty, (*size, *scalar), math, false)
+        }
+        naga::TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => {
+            if let Some(ty) = math.matrix(*columns, *rows, *scalar, padded) {
+                return Ok(ty);
+            }
+            let column = vector_type(
+                module,
+                layout,
+                ty,
+                (*rows, *scalar),
+                math,
+                padded && *rows == naga::VectorSize::Tri,
+            )?;
+            let columns = Literal::usize_unsuffixed(*columns as usize);
+            Ok(quote! { [#column; #columns] })
+        }
+        naga::TypeInner::Array {
+            base,
+            size: naga::ArraySize::Constant(size),
+            ..
+        } => {
+            let element = element_type(module, layout, *base, math, padded)?;
+            let size = Literal::usize_unsuffixed(size.get() as usize);
+            Ok(quote! { [#element; #size] })
+        }
+        naga::TypeInner::Struct { .. } => {
+            let name = module.types[ty].name.as_deref().unwrap_or_default();
+            let ident = reflect::item_ident(name);
+            Ok(quote! { #ident })
+        }
+        _ => match reflect::unstorable_kind(module, ty) {
+            Some(kind) => Err(format!(
+                "`{}` is {kind}, so can't be shared with the host",
+                reflect::type_name(module, ty)
+            )),
+            None => Err(format!(
+                "`{}` can't be represented in Rust",
+                reflect::type_name(module, ty)
+            )),
+        },
+    }
+}
+
+/// Gives a note for the docs of members which are atomics, or arrays of them, which are plain integers on the host.
+fn atomic_note(module: &naga::Module, ty: naga::Handle<naga::Type>) -> Option<String> {
+    match module.types[ty].inner {
+        naga::TypeInner::Atomic(scalar) => {
+            let scalar = values::scalar_type(scalar).ok()?;
+            Some(format!(
+                " `{}` in the shader, which has the same layout as a plain `{scalar}`.",
+                reflect::type_name(module, ty)
+            ))
+        }
+        naga::TypeInner::Array { base, .. } => atomic_note(module, base),
+        _ => None,
+    }
+}
+
+/// Finds the runtime-sized array ending a storage buffer's type, i.e. the type itself or the last member of a struct,
+/// giving its offset and the stride of its elements in bytes.
+pub(crate) fn runtime_array(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+) -> Option<(u32, u32)> {
+    match &module.types[ty].inner {
+        naga::TypeInner::Array {
+            size: naga::ArraySize::Dynamic,
+            stride,
+            ..
+        } => Some((0, *stride)),
+        naga::TypeInner::Struct { members, .. } => {
+            let last = members.last()?;
+            runtime_array(module, last.ty)
+                .filter(|(offset, _)| *offset == 0)
+                .map(|(_, stride)| (last.offset, stride))
+        }
+        _ => None,
+    }
+}
+
+/// Generates the helpers sizing a buffer which ends in a runtime-sized array: its `ELEMENT_STRIDE` and
+/// `min_binding_size` and `buffer_size` functions taking the number of elements.
+pub(crate) fn runtime_size_tokens((offset, stride): (u32, u32), align: u32) -> TokenStream {
+    let (stride, align) = (u64::from(stride), u64::from(align));
+    // Adding an offset of zero is flagged by clippy in the crate using the shader
+    let offset = (offset != 0).then(|| {
+        let offset = u64::from(offset);
+        quote!(#offset +)
+    });
+    let size = quote! {
+        let elements = if elements == 0 { 1 } else { elements };
+        (#offset elements * #stride).next_multiple_of(#align)
+    };
+    quote! {
+        /// The stride of the elements of the runtime-sized array, in bytes.
+        pub const ELEMENT_STRIDE: u64 = #stride;
+
+        /// The smallest size that a binding holding the given number of elements in the runtime-sized array can have,
+        /// in bytes. Bindings must hold at least one element, so this has room for one even when there are none,
+        /// matching the minimum that `wgpu` validates against.
+        pub const fn min_binding_size(elements: u64) -> u64 {
+            #size
+        }
+
+        /// The size of a buffer holding the given number of elements in the runtime-sized array, in bytes, which is
+        /// the same as `min_binding_size`.
+        pub const fn buffer_size(elements: u64) -> u64 {
+            #size
+        }
+    }
+}
+
+/// Adds the structs used by the members of a struct, through arrays or directly, to `structs`.
+fn add_struct(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+    structs: &mut Vec<naga::Handle<naga::Type>>,
+) {
+    match &module.types[ty].inner {
+        naga::TypeInner::Array { base, .. } => add_struct(module, *base, structs),
+        naga::TypeInner::Struct { members, .. } if !structs.contains(&ty) => {
+            structs.push(ty);
+            for member in members {
+                add_struct(module, member.ty, structs);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Generates a `types` module with a Rust struct for every exported struct, and the structs they use. With the `repr_c`
+/// layout host-shareable structs have explicit padding fields, so that they have the same layout as in the shader and,
+/// with the `bytemuck` feature, derive `bytemuck::Pod` to be written to buffers directly. With the `encase` layout they
+/// derive `encase::ShaderType` instead.
+pub(crate) fn struct_items(
+    module: &naga::Module,
+    layout: &HostLayout,
+    exported: &HashSet<String>,
+    math: Math,
+    derives: &[syn::Path],
+) -> Result<Vec<syn::Item>, String> {
+    // Padding isn't part of the data, so isn't serialized
+    let serde = derives.iter().any(|derive| {
+        derive
+            .segments
+            .first()
+            .is_some_and(|segment| segment.ident == "serde")
+    });
+    let skip = serde.then(|| quote! { #[serde(skip)] });
+
+    let mut structs = Vec::new();
+    for (handle, ty) in module.types.iter() {
+        if ty.name.as_ref().is_some_and(|name| exported.contains(name)) {
+            add_struct(module, handle, &mut structs);
+        }
+    }
+    if structs.is_empty() {
+        return Ok(Vec::new());
+    }
+    structs.sort();
+
+    let mut type_items = Vec::new();
+    let mut offset_modules = Vec::new();
+    let mut stride_modules = Vec::new();
+    for ty in structs {
+        let naga::TypeInner::Struct { members, .. } = &module.types[ty].inner else {
+            continue;
+        };
+        let name = module.types[ty].name.as_deref().unwrap_or_default();
+        let ident = reflect::item_ident(name);
+        let padded = layout.is_padded(ty);
+
+        // Structs ending in a runtime-sized array are generated as the fixed-size header before it
+        let runtime_array = runtime_array(module, ty);
+        let (members, span, array_member) = match runtime_array {
+            Some((offset, _)) => {
+                let (array_member, members) = members.split_last().expect("arrays are members");
+                (members, offset, Some(array_member))
+            }
+            None => (&members[..], layout.size(ty), None),
+        };
+
+        let padding = if padded {
+            layout.member_padding(ty, members, span)
+        } else {
+            vec![0; members.len()]
+        };
+        let mut fields = Vec::new();
+        let mut arguments = Vec::new();
+        let mut initializers = Vec::new();
+        let mut offsets = Vec::new();
+        let mut accessors = Vec::new();
+        let mut padding_fields = 0u32;
+        for ((member, padding), offset) in members.iter().zip(padding).zip(layout.offsets(ty)) {
+            let member_name = member.name.as_deref().unwrap_or_default();
+            let member_ident = reflect::item_ident(member_name);
+            let member_ty = field_type(module, layout, member.ty, math, padded).map_err(|e| {
+                format!(
+                    "exported struct `{}` can't be generated, as member `{member_name}`: {e}",
+                    reflect::display_name(name)
+                )
+            })?;
+            if is_bool(module, member.ty) {
+                // `bool`s aren't host-shareable, so they are backed by a `u32` with accessors taking `bool`s
+                let doc = format!(
+                    " `{member_name}` is a `bool` in the shader, stored as `1` for `true` and `0` for `false`."
+                );
+                fields.push(quote! { #[doc = #doc] pub #member_ident: u32 });
+                arguments.push(quote! { #member_ident: bool });
+                initializers.push(quote! { #member_ident: #member_ident as u32 });
+
+                let doc = format!(" Gives the value of `{member_name}`.");
+                let setter = format_ident!("set_{}", member_ident.unraw());
+                let setter_doc = format!(" Sets the value of `{member_name}`.");
+                accessors.push(quote! {
+                    #[doc = #doc]
+                    pub const fn #member_ident(&self) -> bool {
+                        self.#member_ident != 0
+                    }
+
+                    #[doc = #setter_doc]
+                    pub fn #setter(&mut self, value: bool) {
+                        self.#member_ident = value as u32;
+                    }
+                });
+            } else {
+                let note = atomic_note(module, member.ty).map(|note| quote! { #[doc = #note] });
+                fields.push(quote! { #note pub #member_ident: #member_ty });
+                arguments.push(quote! { #member_ident: #member_ty });
+                initializers.push(quote! { #member_ident });
+            }
+            let offset = u64::from(*offset);
+            let doc = format!(" The offset of `{member_name}` in the struct, in bytes.");
+            offsets.push(quote! {
+                #[doc = #doc]
+                pub const #member_ident: u64 = #offset;
+            });
+
+            if padding > 0 {
+                let padding_ident = format_ident!("_pad{}", padding_fields);
+                let padding = Literal::usize_unsuffixed(padding as usize);
+                fields.push(quote! { #skip pub #padding_ident: [u8; #padding] });
+                initializers.push(quote! { #padding_ident: [0; #padding] });
+                padding_fields += 1;
+            }
+        }
+
+        if let Some(member) = array_member {
+            let member_name = member.name.as_deref().unwrap_or_default();
+            let member_ident = reflect::item_ident(member_name);
+            let offset = u64::from(member.offset);
+            let doc = format!(" The offset of `{member_name}` in the struct, in bytes.");
+            offsets.push(quote! {
+                #[doc = #doc]
+                pub const #member_ident: u64 = #offset;
+            });
+        }
+
+        // Array strides are given separately, as e.g. `array<vec3<f32>, N>` has a stride of 16 rather than 12
+        let strides = members
+            .iter()
+            .chain(array_member)
+            .filter_map(|member| {
+                let naga::TypeInner::Array { stride, .. } = module.types[member.ty].inner else {
+                    return None;
+                };
+                let member_name = member.name.as_deref().unwrap_or_default();
+                let member_ident = reflect::item_ident(member_name);
+                let stride = u64::from(stride);
+                let doc = format!(" The stride of the elements of `{member_name}`, in bytes.");
+                Some(quote! {
+                    #[doc = #doc]
+                    pub const #member_ident: u64 = #stride;
+                })
+            })
+            .collect::<Vec<_>>();
+        if !strides.is_empty() {
+            let doc = format!(
+                " The strides of the array members of [`{ident}`](super::{ident}) in the shader, in bytes."
+            );
+            stride_modules.push(quote! {
+                #[doc = #doc]
+                pub mod #ident {
+                    #(#strides)*
+                }
+            });
+        }
+
+        let mut derives = derives
+            .iter()
+            .map(|derive| quote! { #derive })
+            .collect::<Vec<_>>();
+        if layout.struct_layout == StructLayout::Encase {
+            derives.push(quote! { ::encase::ShaderType });
+        }
+        // `mint` types don't implement `bytemuck`'s traits
+        if padded && cfg!(feature = "bytemuck") && math != Math::Mint {
+            derives.push(quote! { ::bytemuck::Pod });
+            derives.push(quote! { ::bytemuck::Zeroable });
+        }
+        let size = u64::from(span);
+        let align = u64::from(layout.alignment(ty));
+        let size_doc = match array_member {
+            Some(member) => format!(
+                " The size of the struct in the shader before `{}`, its runtime-sized array, in bytes.",
+                member.name.as_deref().unwrap_or_default()
+            ),
+            None => String::from(" The size of the struct in the shader, in bytes."),
+        };
+        let runtime_size =
+            runtime_array.map(|array| runtime_size_tokens(array, layout.alignment(ty)));
+        type_items.push(quote! {
+            #[repr(C)]
+            #[derive(#(#derives),*)]
+            pub struct #ident {
+                #(#fields),*
+            }
+
+            impl #ident {
+                #[doc = #size_doc]
+                pub const SIZE_BYTES: u64 = #size;
+                /// The alignment of the struct in the shader, in bytes.
+                pub const ALIGN_BYTES: u64 = #align;
+                #runtime_size
+
+                /// Creates the struct from its members, filling any padding with zeroes.
+                #[allow(clippy::too_many_arguments)]
+                pub const fn new(#(#arguments),*) -> Self {
+                    Self { #(#initializers),* }
+                }
+
+                #(#accessors)*
+            }
+        });
+
+        let doc = format!(
+            " The offsets of the members of [`{ident}`](super::{ident}) in the shader, in bytes."
+        );
+        offset_modules.push(quote! {
+            #[doc = #doc]
+            pub mod #ident {
+                #(#offsets)*
+            }
+        });
+
+        // `glam` types may be more aligned than their WGSL counterparts, which would add padding we don't know about. The
+        // struct is named outside of `assert!` so that it is renamed with the struct.
+        if padded {
+            let span = Literal::usize_unsuffixed(span as usize);
+            let message = format!(
+                "`{}` doesn't have the same layout as in the shader",
+                reflect::display_name(name)
+            );
+            type_items.push(quote! {
+                const _: () = {
+                    let size = ::core::mem::size_of::<#ident>();
+                    assert!(size == #span, #message);
+                };
+            });
+        }
+    }
+
+    Ok(vec![syn::parse_quote! {
+        /// The structs exported from the shader.
+        pub mod types {
+            #(#type_items)*
+
+            /// The offsets of the members of each struct in the shader, for writing parts of buffers.
+            pub mod offsets {
+                #(#offset_modules)*
+            }
+
+            /// The strides of the array members of each struct in the shader, for packing their elements.
+            pub mod strides {
+                #(#stride_modules)*
+            }
+        }
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::ToTokens;
+
+    use crate::testing;
+
+    /// Finds an item of the generated module, or of one of its modules.
+    fn find_item<'a>(items: &'a [syn::Item], path: &[&str]) -> &'a syn::Item {
+        let (name, rest) = path.split_first().expect("paths aren't empty");
+        let item = items
+            .iter()
+            .find(|item| match item {
+                syn::Item::Mod(item) => item.ident == name,
+                syn::Item::Struct(item) => item.ident == name,
+                _ => false,
+            })
+            .unwrap_or_else(|| panic!("no item `{name}`"));
+        match (item, rest) {
+            (_, []) => item,
+            (syn::Item::Mod(item), _) => find_item(&item.content.as_ref().unwrap().1, rest),
+            _ => panic!("`{name}` isn't a module"),
+        }
+    }
+
+    fn const_value(items: &[syn::Item], name: &str) -> String {
+        items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Const(item) if item.ident == name => Some(item.expr.to_token_stream()),
+                syn::Item::Impl(item) => item.items.iter().find_map(|item| match item {
+                    syn::ImplItem::Const(item) if item.ident == name => {
+                        Some(item.expr.to_token_stream())
+                    }
+                    _ => None,
+                }),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no const `{name}`"))
+            .to_string()
+    }
+
+    #[test]
+    fn vec3_and_scalar_padding() {
+        let shader = testing::compose(
+            "@export struct Params { a: f32, b: vec3<f32>, c: f32, d: vec2<f32> }
+            @group(0) @binding(0) var<uniform> params: Params;",
+            |shader| shader.layout("repr_c"),
+        )
+        .unwrap_or_else(|errors| panic!("{}", errors.join("\n")));
+        let items = shader.items();
+
+        // `b` is aligned to 16 bytes and `c` fits in its last four, while the struct is rounded up to 16 bytes
+        let syn::Item::Struct(params) = find_item(&items, &["types", "Params"]) else {
+            panic!("`Params` isn't a struct");
+        };
+        let fields = params
+            .fields
+            .iter()
+            .map(|field| {
+                let name = field.ident.as_ref().unwrap().to_string();
+                if name.starts_with("_pad") {
+                    format!("{name}: {}", field.ty.to_token_stream())
+                } else {
+                    name
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            ["a", "_pad0: [u8 ; 12]", "b", "c", "d", "_pad1: [u8 ; 8]"]
+        );
+
+        let syn::Item::Mod(types) = find_item(&items, &["types"]) else {
+            panic!("`types` isn't a module");
+        };
+        let types = &types.content.as_ref().unwrap().1;
+        assert_eq!(const_value(types, "SIZE_BYTES"), "48u64");
+        assert_eq!(const_value(types, "ALIGN_BYTES"), "16u64");
+
+        let syn::Item::Mod(offsets) = find_item(&items, &["types", "offsets", "Params"]) else {
+            panic!("`offsets::Params` isn't a module");
+        };
+        let offsets = &offsets.content.as_ref().unwrap().1;
+        let offsets = ["a", "b", "c", "d"].map(|member| const_value(offsets, member));
+        assert_eq!(offsets, ["0u64", "16u64", "28u64", "32u64"]);
+    }
+
+    /// Evaluates the body of a generated `const fn(elements: u64) -> u64`, which only uses literals, arithmetic,
+    /// `if` and `next_multiple_of`.
+    fn evaluate(expr: &syn::Expr, elements: u64) -> u64 {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) => int.base10_parse().unwrap(),
+            syn::Expr::Path(path) if path.path.is_ident("elements") => elements,
+            syn::Expr::Paren(paren) => evaluate(&paren.expr, elements),
+            syn::Expr::Binary(binary) => {
+                let left = evaluate(&binary.left, elements);
+                let right = evaluate(&binary.right, elements);
+                match binary.op {
+                    syn::BinOp::Add(_) => left + right,
+                    syn::BinOp::Mul(_) => left * right,
+                    syn::BinOp::Eq(_) => u64::from(left == right),
+                    _ => panic!("unexpected operator in `{}`", expr.to_token_stream()),
+                }
+            }
+            syn::Expr::If(expr_if) => {
+                let (_, otherwise) = expr_if.else_branch.as_ref().unwrap();
+                if evaluate(&expr_if.cond, elements) != 0 {
+                    evaluate_block(&expr_if.then_branch, elements)
+                } else {
+                    evaluate(otherwise, elements)
+                }
+            }
+            syn::Expr::Block(block) => evaluate_block(&block.block, elements),
+            syn::Expr::MethodCall(call) if call.method == "next_multiple_of" => {
+                evaluate(&call.receiver, elements)
+                    .next_multiple_of(evaluate(&call.args[0], elements))
+            }
+            _ => panic!("unexpected expression `{}`", expr.to_token_stream()),
+        }
+    }
+
+    fn evaluate_block(block: &syn::Block, mut elements: u64) -> u64 {
+        let (last, statements) = block.stmts.split_last().unwrap();
+        for statement in statements {
+            let syn::Stmt::Local(local) = statement else {
+                panic!("unexpected statement `{}`", statement.to_token_stream());
+            };
+            elements = evaluate(&local.init.as_ref().unwrap().expr, elements);
+        }
+        let syn::Stmt::Expr(last, None) = last else {
+            panic!("unexpected statement `{}`", last.to_token_stream());
+        };
+        evaluate(last, elements)
+    }
+
+    #[test]
+    fn min_binding_size_matches_layouter() {
+        let shader = testing::compose(
+            "struct Particles { count: u32, items: array<vec3<f32>> }
+            @group(0) @binding(0) var<storage, read_write> particles: Particles;
+            @group(0) @binding(1) var<storage, read_write> values: array<f32>;
+            @compute @workgroup_size(1) fn main() { particles.count = arrayLength(&values); }",
+            |shader| shader,
+        )
+        .unwrap_or_else(|errors| panic!("{}", errors.join("\n")));
+        let module = shader.module();
+        let mut layouter = naga::proc::Layouter::default();
+        layouter.update(module.to_ctx()).unwrap();
+
+        for (_, global) in module.global_variables.iter() {
+            let ty = global.ty;
+            let array = super::runtime_array(module, ty).unwrap();
+            let tokens = super::runtime_size_tokens(array, layouter[ty].alignment.round_up(1));
+            let file = syn::parse2::<syn::File>(tokens).unwrap();
+            let min_binding_size = file
+                .items
+                .iter()
+                .find_map(|item| match item {
+                    syn::Item::Fn(item) if item.sig.ident == "min_binding_size" => {
+                        Some(&item.block)
+                    }
+                    _ => None,
+                })
+                .unwrap();
+
+            // `wgpu` requires bindings to be at least the size of the type with one element in the array
+            let span = u64::from(layouter[ty].size);
+            assert_eq!(evaluate_block(min_binding_size, 0), span);
+            assert_eq!(evaluate_block(min_binding_size, 1), span);
+            let (offset, stride) = array;
+            assert_eq!(
+                evaluate_block(min_binding_size, 3),
+                u64::from(offset + 3 * stride)
+                    .next_multiple_of(u64::from(layouter[ty].alignment.round_up(1)))
+            );
+            // Adding an offset of zero is flagged by clippy
+            assert_eq!(
+                offset == 0,
+                !min_binding_size.to_token_stream().to_string().contains('+')
+            );
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::{env, fmt::Write, path::Path};
+
+use crate::{bindings, dispatch, reflect, source::Sourcecode};
+
+/// Formats the path of a shader file relative to the root of the crate, or in full for shaders outside of it.
+pub(crate) fn display_path(path: &Path) -> String {
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Gives a markdown overview of the shader for the documentation of the module it is included into: its entry points,
+/// with their stages and workgroup sizes, the bindings of each bind group and the tree of files it imports. Sections
+/// which would be empty are left out.
+pub(crate) fn module_doc(module: &naga::Module, source: &Sourcecode) -> String {
+    let shader = display_path(Path::new(source.requested_path()));
+    let mut out = format!(" Generated from `{shader}`.\n");
+
+    if !module.entry_points.is_empty() {
+        writeln!(out, "\n # Entry points\n").unwrap();
+        for entry_point in &module.entry_points {
+            write!(
+                out,
+                " - `{}`: {}",
+                entry_point.name,
+                reflect::stage_name(entry_point.stage)
+            )
+            .unwrap();
+            if let Some([x, y, z]) = dispatch::workgroup_size(entry_point) {
+                write!(out, ", with a workgroup size of {x} × {y} × {z}").unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+    }
+
+    let bindings = bindings::bindings(module);
+    if !bindings.is_empty() {
+        writeln!(out, "\n # Bind groups\n").unwrap();
+        let mut group = None;
+        for binding in &bindings {
+            if group != Some(binding.group) {
+                writeln!(out, " - Group {}:", binding.group).unwrap();
+                group = Some(binding.group);
+            }
+            writeln!(
+                out,
+                "   - `@binding({}) {} {}: {}`",
+                binding.binding,
+                reflect::global_declaration(binding.global.space),
+                reflect::display_name(binding.global.name.as_deref().unwrap_or("_")),
+                reflect::type_name(module, binding.global.ty)
+            )
+            .unwrap();
+        }
+    }
+
+    if !source.import_tree().is_empty() {
+        writeln!(out, "\n # Imports\n").unwrap();
+        for (depth, path) in source.import_tree() {
+            writeln!(out, " {}- `{}`", "  ".repeat(*depth), display_path(path)).unwrap();
+        }
+    }
+
+    out.trim_end().to_owned()
+}
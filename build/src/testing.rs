@@ -0,0 +1,23 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{ComposedShader, Shader};
+
+/// Composes a shader written to a file of its own in the temporary directory, with the builder set up by `configure`.
+pub(crate) fn compose(
+    source: &str,
+    configure: impl FnOnce(Shader) -> Shader,
+) -> Result<ComposedShader, Vec<String>> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "include-wgsl-oil-test-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("shader.wgsl");
+    std::fs::write(&path, source).unwrap();
+
+    let result = configure(Shader::new(&path).allow_external_paths(true)).compose();
+    std::fs::remove_dir_all(&dir).unwrap();
+    result
+}
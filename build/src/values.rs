@@ -0,0 +1,380 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::structs::{HostLayout, Math};
+
+fn literal_tokens(literal: naga::Literal) -> Result<TokenStream, String> {
+    let literal = match literal {
+        naga::Literal::F64(v) => Literal::f64_suffixed(v),
+        naga::Literal::F32(v) => Literal::f32_suffixed(v),
+        naga::Literal::U32(v) => Literal::u32_suffixed(v),
+        naga::Literal::I32(v) => Literal::i32_suffixed(v),
+        naga::Literal::U64(v) => Literal::u64_suffixed(v),
+        naga::Literal::I64(v) => Literal::i64_suffixed(v),
+        naga::Literal::Bool(v) => return Ok(quote! { #v }),
+        other => return Err(format!("unsupported literal `{other:?}`")),
+    };
+    Ok(quote! { #literal })
+}
+
+/// Builds Rust expressions for the values of constant expressions.
+pub(crate) struct ValueWriter<'a> {
+    pub(crate) module: &'a naga::Module,
+    /// How vectors and matrices are represented, matching the types generated for the module. Expressions using `glam`
+    /// types can't be used in `const` contexts.
+    pub(crate) math: Math,
+    /// The layout of the generated structs, whose padding fields are filled with zeroes, or `None` if no structs are
+    /// written.
+    pub(crate) layout: Option<&'a HostLayout>,
+}
+
+impl ValueWriter<'_> {
+    /// Gives the scalar components of a vector-valued expression, flattening nested vectors and splats.
+    fn scalar_components(
+        &self,
+        expr: naga::Handle<naga::Expression>,
+    ) -> Vec<naga::Handle<naga::Expression>> {
+        match &self.module.global_expressions[expr] {
+            naga::Expression::Constant(constant) => {
+                self.scalar_components(self.module.constants[*constant].init)
+            }
+            naga::Expression::Compose { components, .. } => components
+                .iter()
+                .flat_map(|component| self.scalar_components(*component))
+                .collect(),
+            naga::Expression::Splat { size, value } => vec![*value; *size as usize],
+            _ => vec![expr],
+        }
+    }
+
+    fn is_padded(&self, ty: naga::Handle<naga::Type>) -> bool {
+        self.layout.is_some_and(|layout| layout.is_padded(ty))
+    }
+
+    /// Writes a vector from its components. `padded` vectors are given a fourth component by WGSL's layout rules.
+    fn vector(
+        &self,
+        mut scalars: Vec<TokenStream>,
+        size: naga::VectorSize,
+        scalar: naga::Scalar,
+        padded: bool,
+    ) -> Result<TokenStream, String> {
+        if self.math.vector(size, scalar, padded).is_some() {
+            return Ok(quote! { [#(#scalars),*].into() });
+        }
+        if padded {
+            scalars.push(zero_literal(scalar)?);
+        }
+        Ok(quote! { [#(#scalars),*] })
+    }
+
+    /// Writes a matrix from the components of its columns. `padded` is whether the matrix is in a struct with its WGSL
+    /// layout.
+    fn matrix(
+        &self,
+        columns: Vec<Vec<TokenStream>>,
+        size: (naga::VectorSize, naga::VectorSize),
+        scalar: naga::Scalar,
+        padded: bool,
+    ) -> Result<TokenStream, String> {
+        let (column_count, rows) = size;
+        if let Some(matrix) = self.math.matrix(column_count, rows, scalar, padded) {
+            let columns = columns.iter().map(|column| quote! { [#(#column),*] });
+            return Ok(match self.math {
+                Math::Glam => quote! { #matrix::from_cols_array_2d(&[#(#columns),*]) },
+                _ => quote! { <#matrix>::from([#(#columns),*]) },
+            });
+        }
+        let columns = columns
+            .into_iter()
+            .map(|column| {
+                self.vector(
+                    column,
+                    rows,
+                    scalar,
+                    padded && rows == naga::VectorSize::Tri,
+                )
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(quote! { [#(#columns),*] })
+    }
+
+    /// Writes an array element, which is padded if it is a three component vector in a struct with its WGSL layout.
+    fn element(
+        &self,
+        expr: Option<naga::Handle<naga::Expression>>,
+        base: naga::Handle<naga::Type>,
+        padded: bool,
+    ) -> Result<TokenStream, String> {
+        if let naga::TypeInner::Vector {
+            size: naga::VectorSize::Tri,
+            scalar,
+        } = self.module.types[base].inner
+        {
+            if padded {
+                let scalars = match expr {
+                    Some(expr) => self
+                        .scalar_components(expr)
+                        .into_iter()
+                        .map(|scalar| self.value_in(scalar, base, padded))
+                        .collect::<Result<Vec<_>, String>>()?,
+                    None => vec![zero_literal(scalar)?; 3],
+                };
+                return self.vector(scalars, naga::VectorSize::Tri, scalar, true);
+            }
+        }
+        match expr {
+            Some(expr) => self.value_in(expr, base, padded),
+            None => self.zero(base, padded),
+        }
+    }
+
+    /// Writes a struct from the values of its members, filling its padding fields.
+    fn struct_value(
+        &self,
+        ty: naga::Handle<naga::Type>,
+        values: Vec<TokenStream>,
+    ) -> Result<TokenStream, String> {
+        let naga::TypeInner::Struct { members, .. } = &self.module.types[ty].inner else {
+            return Err("expected a struct".to_owned());
+        };
+        let padding = match self.layout {
+            Some(layout) if layout.is_padded(ty) => {
+                layout.member_padding(ty, members, layout.size(ty))
+            }
+            _ => vec![0; members.len()],
+        };
+
+        let mut fields = Vec::new();
+        let mut padding_fields = 0u32;
+        for ((member, value), padding) in members.iter().zip(values).zip(padding) {
+            let name = crate::reflect::item_ident(member.name.as_deref().unwrap_or("_"));
+            if crate::structs::is_bool(self.module, member.ty) {
+                fields.push(quote! { #name: #value as u32 });
+            } else {
+                fields.push(quote! { #name: #value });
+            }
+            if padding > 0 {
+                let padding_ident = format_ident!("_pad{}", padding_fields);
+                let padding = Literal::usize_unsuffixed(padding as usize);
+                fields.push(quote! { #padding_ident: [0; #padding] });
+                padding_fields += 1;
+            }
+        }
+        let name = self.struct_path(ty)?;
+        Ok(quote! { #name { #(#fields),* } })
+    }
+
+    fn zero(&self, ty: naga::Handle<naga::Type>, padded: bool) -> Result<TokenStream, String> {
+        match &self.module.types[ty].inner {
+            naga::TypeInner::Scalar(scalar) => zero_literal(*scalar),
+            naga::TypeInner::Vector { size, scalar } => {
+                let zero = zero_literal(*scalar)?;
+                self.vector(vec![zero; *size as usize], *size, *scalar, false)
+            }
+            naga::TypeInner::Matrix {
+                columns,
+                rows,
+                scalar,
+            } => {
+                let zero = zero_literal(*scalar)?;
+                let column = vec![zero; *rows as usize];
+                self.matrix(
+                    vec![column; *columns as usize],
+                    (*columns, *rows),
+                    *scalar,
+                    padded,
+                )
+            }
+            naga::TypeInner::Array {
+                base,
+                size: naga::ArraySize::Constant(size),
+                ..
+            } => {
+                let element = self.element(None, *base, padded)?;
+                let elements = std::iter::repeat_n(element, size.get() as usize);
+                Ok(quote! { [#(#elements),*] })
+            }
+            naga::TypeInner::Struct { members, .. } => {
+                let values = members
+                    .iter()
+                    .map(|member| self.zero(member.ty, self.is_padded(ty)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                self.struct_value(ty, values)
+            }
+            _ => Err(format!(
+                "unsupported constant of type `{}`",
+                crate::reflect::type_name(self.module, ty)
+            )),
+        }
+    }
+
+    fn struct_path(&self, ty: naga::Handle<naga::Type>) -> Result<TokenStream, String> {
+        let name = self.module.types[ty]
+            .name
+            .as_deref()
+            .ok_or("structs should be named")?;
+        let name = crate::reflect::item_ident(name);
+        Ok(quote! { types::#name })
+    }
+
+    /// Writes the zero value of a type, as WGSL gives variables declared without an initializer.
+    pub(crate) fn zero_value(&self, ty: naga::Handle<naga::Type>) -> Result<TokenStream, String> {
+        self.zero(ty, false)
+    }
+
+    pub(crate) fn value(
+        &self,
+        expr: naga::Handle<naga::Expression>,
+        ty: naga::Handle<naga::Type>,
+    ) -> Result<TokenStream, String> {
+        self.value_in(expr, ty, false)
+    }
+
+    /// Writes a value of the given type. `padded` is whether the value is in a struct with its WGSL layout.
+    fn value_in(
+        &self,
+        expr: naga::Handle<naga::Expression>,
+        ty: naga::Handle<naga::Type>,
+        padded: bool,
+    ) -> Result<TokenStream, String> {
+        let components = match &self.module.global_expressions[expr] {
+            naga::Expression::Literal(literal) => return literal_tokens(*literal),
+            naga::Expression::Constant(constant) => {
+                return self.value_in(self.module.constants[*constant].init, ty, padded)
+            }
+            naga::Expression::ZeroValue(_) => return self.zero(ty, padded),
+            naga::Expression::Compose { components, .. } => components.clone(),
+            naga::Expression::Splat { .. } => Vec::new(),
+            other => return Err(format!("unsupported constant expression `{other:?}`")),
+        };
+
+        match &self.module.types[ty].inner {
+            naga::TypeInner::Vector { size, scalar } => {
+                let scalars = self
+                    .scalar_components(expr)
+                    .into_iter()
+                    .map(|scalar| self.value_in(scalar, ty, padded))
+                    .collect::<Result<Vec<_>, String>>()?;
+                self.vector(scalars, *size, *scalar, false)
+            }
+            naga::TypeInner::Matrix {
+                columns,
+                rows,
+                scalar,
+            } => {
+                let columns_values = components
+                    .iter()
+                    .map(|column| {
+                        self.scalar_components(*column)
+                            .into_iter()
+                            .map(|scalar| self.value_in(scalar, ty, padded))
+                            .collect::<Result<Vec<_>, String>>()
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                self.matrix(columns_values, (*columns, *rows), *scalar, padded)
+            }
+            naga::TypeInner::Array { base, .. } => {
+                let elements = components
+                    .iter()
+                    .map(|element| self.element(Some(*element), *base, padded))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(quote! { [#(#elements),*] })
+            }
+            naga::TypeInner::Struct { members, .. } => {
+                let values = members
+                    .iter()
+                    .zip(components)
+                    .map(|(member, component)| {
+                        self.value_in(component, member.ty, self.is_padded(ty))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                self.struct_value(ty, values)
+            }
+            _ => Err(format!(
+                "unsupported constant of type `{}`",
+                crate::reflect::type_name(self.module, ty)
+            )),
+        }
+    }
+}
+
+fn zero_literal(scalar: naga::Scalar) -> Result<TokenStream, String> {
+    match scalar.kind {
+        naga::ScalarKind::Bool => Ok(quote! { false }),
+        naga::ScalarKind::Float if scalar.width == 2 => {
+            scalar_type(scalar)?;
+            Ok(quote! { ::half::f16::ZERO })
+        }
+        _ => literal_tokens(match (scalar.kind, scalar.width) {
+            (naga::ScalarKind::Float, 8) => naga::Literal::F64(0.0),
+            (naga::ScalarKind::Float, 4) => naga::Literal::F32(0.0),
+            (naga::ScalarKind::Uint, 8) => naga::Literal::U64(0),
+            (naga::ScalarKind::Uint, 4) => naga::Literal::U32(0),
+            (naga::ScalarKind::Sint, 8) => naga::Literal::I64(0),
+            (naga::ScalarKind::Sint, 4) => naga::Literal::I32(0),
+            _ => return Err(format!("unsupported scalar `{scalar:?}`")),
+        }),
+    }
+}
+
+/// Gives the Rust type of a scalar.
+pub(crate) fn scalar_type(scalar: naga::Scalar) -> Result<TokenStream, String> {
+    Ok(match (scalar.kind, scalar.width) {
+        (naga::ScalarKind::Bool, _) => quote! { bool },
+        (naga::ScalarKind::Float, 8) => quote! { f64 },
+        (naga::ScalarKind::Float, 4) => quote! { f32 },
+        (naga::ScalarKind::Float, 2) if cfg!(feature = "half") => quote! { ::half::f16 },
+        (naga::ScalarKind::Float, 2) => {
+            return Err(
+                "`f16` values need the `half` feature, to be given as `half::f16`".to_owned(),
+            )
+        }
+        (naga::ScalarKind::Uint, 8) => quote! { u64 },
+        (naga::ScalarKind::Uint, 4) => quote! { u32 },
+        (naga::ScalarKind::Sint, 8) => quote! { i64 },
+        (naga::ScalarKind::Sint, 4) => quote! { i32 },
+        _ => return Err(format!("unsupported scalar `{scalar:?}`")),
+    })
+}
+
+/// Gives the Rust type of a constant with the given WGSL type, using arrays for vectors and matrices so that values can
+/// be written in `const` contexts.
+pub(crate) fn rust_type(
+    module: &naga::Module,
+    ty: naga::Handle<naga::Type>,
+) -> Result<TokenStream, String> {
+    match &module.types[ty].inner {
+        naga::TypeInner::Scalar(scalar) => scalar_type(*scalar),
+        naga::TypeInner::Vector { size, scalar } => {
+            let scalar = scalar_type(*scalar)?;
+            let size = Literal::usize_unsuffixed(*size as usize);
+            Ok(quote! { [#scalar; #size] })
+        }
+        naga::TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => {
+            let scalar = scalar_type(*scalar)?;
+            let (columns, rows) = (
+                Literal::usize_unsuffixed(*columns as usize),
+                Literal::usize_unsuffixed(*rows as usize),
+            );
+            Ok(quote! { [[#scalar; #rows]; #columns] })
+        }
+        naga::TypeInner::Array {
+            base,
+            size: naga::ArraySize::Constant(size),
+            ..
+        } => {
+            let base = rust_type(module, *base)?;
+            let size = Literal::usize_unsuffixed(size.get() as usize);
+            Ok(quote! { [#base; #size] })
+        }
+        _ => Err(format!(
+            "constants of type `{}` can't be reflected",
+            crate::reflect::type_name(module, ty)
+        )),
+    }
+}
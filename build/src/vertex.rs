@@ -0,0 +1,177 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::docs::DocComments;
+
+/// An input of a vertex entry point, read from a vertex buffer.
+pub(crate) struct VertexInput {
+    pub(crate) name: String,
+    /// The index of the entry point argument giving the input, and of the member if the argument is a struct.
+    pub(crate) argument: usize,
+    pub(crate) member: Option<usize>,
+    pub(crate) location: u32,
+    pub(crate) ty: naga::Handle<naga::Type>,
+    /// Whether the input is marked with `//! wgsl-oil: step=instance`, so is read from the per-instance buffer.
+    pub(crate) instance: bool,
+}
+
+fn is_instance(hints: &[(String, String)]) -> bool {
+    hints
+        .iter()
+        .any(|(key, value)| key == "step" && value == "instance")
+}
+
+/// Finds the `@location` inputs of a vertex entry point, given directly as arguments or as the members of struct
+/// arguments, ordered by location.
+pub(crate) fn vertex_inputs(
+    module: &naga::Module,
+    docs: &DocComments,
+    entry_point: &naga::EntryPoint,
+) -> Vec<VertexInput> {
+    let mut inputs = Vec::new();
+    for (argument_index, argument) in entry_point.function.arguments.iter().enumerate() {
+        if let Some(naga::Binding::Location { location, .. }) = argument.binding {
+            let name = argument.name.as_deref().unwrap_or_default();
+            inputs.push(VertexInput {
+                name: name.to_owned(),
+                argument: argument_index,
+                member: None,
+                location,
+                ty: argument.ty,
+                instance: is_instance(docs.member_hints(&entry_point.name, name)),
+            });
+            continue;
+        }
+
+        let ty = &module.types[argument.ty];
+        let naga::TypeInner::Struct { members, .. } = &ty.inner else {
+            continue;
+        };
+        // Hints on the struct apply to all of its members
+        let struct_name = ty.name.as_deref().unwrap_or_default();
+        let struct_instance = is_instance(docs.item_hints(struct_name));
+        for (member_index, member) in members.iter().enumerate() {
+            if let Some(naga::Binding::Location { location, .. }) = member.binding {
+                let name = member.name.as_deref().unwrap_or_default();
+                inputs.push(VertexInput {
+                    name: name.to_owned(),
+                    argument: argument_index,
+                    member: Some(member_index),
+                    location,
+                    ty: member.ty,
+                    instance: struct_instance || is_instance(docs.member_hints(struct_name, name)),
+                });
+            }
+        }
+    }
+    inputs.sort_by_key(|input| input.location);
+    inputs
+}
+
+/// Gives the name of the `wgpu::VertexFormat` that a vertex input of the given type is naturally read with, and its
+/// size in bytes.
+pub(crate) fn vertex_format(inner: &naga::TypeInner) -> Option<(String, u64)> {
+    let (scalar, components) = match *inner {
+        naga::TypeInner::Scalar(scalar) => (scalar, 1),
+        naga::TypeInner::Vector { size, scalar } => (scalar, size as u8),
+        _ => return None,
+    };
+    let kind = match scalar.kind {
+        naga::ScalarKind::Float => "Float",
+        naga::ScalarKind::Sint => "Sint",
+        naga::ScalarKind::Uint => "Uint",
+        _ => return None,
+    };
+
+    // Half precision formats only come in one, two and four components
+    if scalar.width == 2 && components == 3 {
+        return None;
+    }
+    let bits = u64::from(scalar.width) * 8;
+    let name = match components {
+        1 => format!("{kind}{bits}"),
+        _ => format!("{kind}{bits}x{components}"),
+    };
+    Some((name, u64::from(scalar.width) * u64::from(components)))
+}
+
+/// Gives the attributes of a vertex buffer holding the given inputs, packed in order of location, and the buffer's
+/// stride.
+fn buffer_attributes(
+    module: &naga::Module,
+    inputs: &[&VertexInput],
+) -> Option<(Vec<TokenStream>, u64)> {
+    let mut attributes = Vec::new();
+    let mut offset = 0u64;
+    for input in inputs {
+        let (format, size) = vertex_format(&module.types[input.ty].inner)?;
+        // Attributes are aligned to their size, up to four bytes
+        let alignment = size.min(4);
+        offset = offset.next_multiple_of(alignment);
+
+        let format = format_ident!("{}", format);
+        let location = input.location;
+        attributes.push(quote! {
+            ::wgpu::VertexAttribute {
+                format: ::wgpu::VertexFormat::#format,
+                offset: #offset,
+                shader_location: #location,
+            }
+        });
+        offset += size;
+    }
+
+    Some((attributes, offset.next_multiple_of(4)))
+}
+
+/// Generates the attributes and strides of the per-vertex and per-instance buffers read by a vertex entry point, and
+/// the `VERTEX_BUFFERS` layouts of the buffers which are used, or `None` if an input has no `wgpu::VertexFormat`.
+pub(crate) fn vertex_buffer_tokens(
+    module: &naga::Module,
+    docs: &DocComments,
+    entry_point: &naga::EntryPoint,
+) -> Option<TokenStream> {
+    let inputs = vertex_inputs(module, docs, entry_point);
+    let (instance_inputs, vertex_inputs): (Vec<_>, Vec<_>) =
+        inputs.iter().partition(|input| input.instance);
+    let (vertex_attributes, vertex_stride) = buffer_attributes(module, &vertex_inputs)?;
+    let (instance_attributes, instance_stride) = buffer_attributes(module, &instance_inputs)?;
+
+    let mut buffers = Vec::new();
+    if !vertex_attributes.is_empty() {
+        buffers.push(quote! {
+            ::wgpu::VertexBufferLayout {
+                array_stride: VERTEX_STRIDE,
+                step_mode: ::wgpu::VertexStepMode::Vertex,
+                attributes: &VERTEX_ATTRIBUTES,
+            }
+        });
+    }
+    if !instance_attributes.is_empty() {
+        buffers.push(quote! {
+            ::wgpu::VertexBufferLayout {
+                array_stride: INSTANCE_STRIDE,
+                step_mode: ::wgpu::VertexStepMode::Instance,
+                attributes: &INSTANCE_ATTRIBUTES,
+            }
+        });
+    }
+    let vertex_count = vertex_attributes.len();
+    let instance_count = instance_attributes.len();
+    let buffer_count = buffers.len();
+
+    Some(quote! {
+        /// The stride of the per-vertex buffer, in bytes.
+        pub const VERTEX_STRIDE: u64 = #vertex_stride;
+        /// The attributes read from the per-vertex buffer, packed in order of location.
+        pub const VERTEX_ATTRIBUTES: [::wgpu::VertexAttribute; #vertex_count] = [#(#vertex_attributes),*];
+        /// The stride of the per-instance buffer, in bytes.
+        pub const INSTANCE_STRIDE: u64 = #instance_stride;
+        /// The attributes read from the per-instance buffer, i.e. the inputs marked with
+        /// `//! wgsl-oil: step=instance`, packed in order of location.
+        pub const INSTANCE_ATTRIBUTES: [::wgpu::VertexAttribute; #instance_count] = [#(#instance_attributes),*];
+        /// The layouts of the per-vertex buffer and then the per-instance buffer, omitting either if it has no
+        /// attributes.
+        pub const VERTEX_BUFFERS: [::wgpu::VertexBufferLayout<'static>; #buffer_count] = [#(#buffers),*];
+    })
+}
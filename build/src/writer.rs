@@ -0,0 +1,676 @@
+use naga::Span;
+
+/// Names the `const`s standing in for overrides, and for the operands of their initializers, while the module is
+/// written. WGSL reserves identifiers starting with `__`, but `naga`'s namer rewrites those, so instead the names start
+/// with a prefix which no identifier in the module contains, and so can't collide with anything else in the output.
+struct Placeholders {
+    prefix: String,
+}
+
+impl Placeholders {
+    fn new(module: &naga::Module) -> Self {
+        let mut names = Vec::new();
+        for (_, ty) in module.types.iter() {
+            names.extend(ty.name.as_deref());
+            if let naga::TypeInner::Struct { members, .. } = &ty.inner {
+                names.extend(members.iter().filter_map(|member| member.name.as_deref()));
+            }
+        }
+        for (_, constant) in module.constants.iter() {
+            names.extend(constant.name.as_deref());
+        }
+        for (_, override_) in module.overrides.iter() {
+            names.extend(override_.name.as_deref());
+        }
+        for (_, variable) in module.global_variables.iter() {
+            names.extend(variable.name.as_deref());
+        }
+        for entry_point in &module.entry_points {
+            names.push(entry_point.name.as_str());
+        }
+        let functions = module.functions.iter().map(|(_, function)| function);
+        let entry_points = module
+            .entry_points
+            .iter()
+            .map(|entry_point| &entry_point.function);
+        for function in functions.chain(entry_points) {
+            names.extend(function.name.as_deref());
+            for argument in &function.arguments {
+                names.extend(argument.name.as_deref());
+            }
+            for (_, variable) in function.local_variables.iter() {
+                names.extend(variable.name.as_deref());
+            }
+            names.extend(function.named_expressions.values().map(String::as_str));
+        }
+
+        let mut prefix = "wgslOil".to_owned();
+        let mut count = 0;
+        while names.iter().any(|name| name.contains(&prefix)) {
+            count += 1;
+            prefix = format!("wgslOil{count}x");
+        }
+        Self { prefix }
+    }
+
+    /// The name of the `const` standing in for an override. Names don't end with digits, which `naga` would suffix.
+    fn override_name(&self, index: usize) -> String {
+        format!("{}Override{index}Placeholder", self.prefix)
+    }
+
+    fn operand_name(&self, index: usize) -> String {
+        format!("{}Operand{index}Placeholder", self.prefix)
+    }
+}
+
+fn scalar_name(scalar: naga::Scalar) -> Option<&'static str> {
+    use naga::ScalarKind as Kind;
+    Some(match (scalar.kind, scalar.width) {
+        (Kind::Bool, _) => "bool",
+        (Kind::Float, 2) => "f16",
+        (Kind::Float, 4) => "f32",
+        (Kind::Float, 8) => "f64",
+        (Kind::Sint, 4) => "i32",
+        (Kind::Sint, 8) => "i64",
+        (Kind::Uint, 4) => "u32",
+        (Kind::Uint, 8) => "u64",
+        _ => return None,
+    })
+}
+
+fn binary_operator(op: naga::BinaryOperator) -> &'static str {
+    use naga::BinaryOperator as Op;
+    match op {
+        Op::Add => "+",
+        Op::Subtract => "-",
+        Op::Multiply => "*",
+        Op::Divide => "/",
+        Op::Modulo => "%",
+        Op::Equal => "==",
+        Op::NotEqual => "!=",
+        Op::Less => "<",
+        Op::LessEqual => "<=",
+        Op::Greater => ">",
+        Op::GreaterEqual => ">=",
+        Op::And => "&",
+        Op::ExclusiveOr => "^",
+        Op::InclusiveOr => "|",
+        Op::LogicalAnd => "&&",
+        Op::LogicalOr => "||",
+        Op::ShiftLeft => "<<",
+        Op::ShiftRight => ">>",
+    }
+}
+
+/// Writes an expression referring to overrides, which `naga` can only write in function bodies, or `None` if it uses an
+/// expression we can't write. Literals, constants and zero values are left to `naga`, as operands which are collected
+/// and written in place of their placeholders.
+fn write_initializer(
+    module: &naga::Module,
+    handle: naga::Handle<naga::Expression>,
+    placeholders: &Placeholders,
+    operands: &mut Vec<naga::Handle<naga::Expression>>,
+) -> Option<String> {
+    let mut write = |handle| write_initializer(module, handle, placeholders, operands);
+    Some(match module.global_expressions[handle] {
+        naga::Expression::Binary { op, left, right } => {
+            format!(
+                "({} {} {})",
+                write(left)?,
+                binary_operator(op),
+                write(right)?
+            )
+        }
+        naga::Expression::Unary { op, expr } => {
+            let op = match op {
+                naga::UnaryOperator::Negate => "-",
+                naga::UnaryOperator::LogicalNot => "!",
+                naga::UnaryOperator::BitwiseNot => "~",
+            };
+            format!("{op}{}", write(expr)?)
+        }
+        naga::Expression::Select {
+            condition,
+            accept,
+            reject,
+        } => format!(
+            "select({}, {}, {})",
+            write(reject)?,
+            write(accept)?,
+            write(condition)?
+        ),
+        naga::Expression::As {
+            expr,
+            kind,
+            convert: Some(width),
+        } => {
+            let ty = scalar_name(naga::Scalar { kind, width })?;
+            format!("{ty}({})", write(expr)?)
+        }
+        naga::Expression::Override(override_) => {
+            module.overrides[override_].name.clone().unwrap_or_default()
+        }
+        naga::Expression::Literal(_)
+        | naga::Expression::Constant(_)
+        | naga::Expression::ZeroValue(_) => {
+            operands.push(handle);
+            placeholders.operand_name(operands.len() - 1)
+        }
+        _ => return None,
+    })
+}
+
+/// Whether `naga` can write an expression outside of a function, which it can only do for those not using overrides.
+fn is_writable(module: &naga::Module, handle: naga::Handle<naga::Expression>) -> bool {
+    match module.global_expressions[handle] {
+        naga::Expression::Literal(_)
+        | naga::Expression::Constant(_)
+        | naga::Expression::ZeroValue(_) => true,
+        naga::Expression::Compose { ref components, .. } => components
+            .iter()
+            .all(|&component| is_writable(module, component)),
+        naga::Expression::Splat { value, .. } => is_writable(module, value),
+        _ => false,
+    }
+}
+
+/// Points the operands of a global expression at the handles the expressions they refer to were moved to.
+fn move_operands(expression: &mut naga::Expression, moved: &[naga::Handle<naga::Expression>]) {
+    let move_operand =
+        |handle: &mut naga::Handle<naga::Expression>| *handle = moved[handle.index()];
+    match *expression {
+        naga::Expression::Compose {
+            ref mut components, ..
+        } => components.iter_mut().for_each(move_operand),
+        naga::Expression::Access {
+            ref mut base,
+            ref mut index,
+        } => {
+            move_operand(base);
+            move_operand(index);
+        }
+        naga::Expression::AccessIndex { ref mut base, .. } => move_operand(base),
+        naga::Expression::Splat { ref mut value, .. } => move_operand(value),
+        naga::Expression::Swizzle { ref mut vector, .. } => move_operand(vector),
+        naga::Expression::Unary { ref mut expr, .. }
+        | naga::Expression::As { ref mut expr, .. } => move_operand(expr),
+        naga::Expression::Binary {
+            ref mut left,
+            ref mut right,
+            ..
+        } => {
+            move_operand(left);
+            move_operand(right);
+        }
+        naga::Expression::Select {
+            ref mut condition,
+            ref mut accept,
+            ref mut reject,
+        } => {
+            move_operand(condition);
+            move_operand(accept);
+            move_operand(reject);
+        }
+        naga::Expression::Relational {
+            ref mut argument, ..
+        } => move_operand(argument),
+        naga::Expression::Math {
+            ref mut arg,
+            ref mut arg1,
+            ref mut arg2,
+            ref mut arg3,
+            ..
+        } => {
+            move_operand(arg);
+            [arg1, arg2, arg3]
+                .into_iter()
+                .flatten()
+                .for_each(move_operand);
+        }
+        // Other expressions can't be used outside of functions, or don't refer to other expressions
+        _ => {}
+    }
+}
+
+/// Writes a module as WGSL. `naga`'s writer doesn't support pipeline-overridable constants, so overrides are written as
+/// placeholder `const`s, whose declarations are then replaced with the overrides' own and whose uses are renamed. The
+/// output is parsed and validated again, so that anything this gets wrong is an error rather than a broken shader.
+pub(crate) fn write_wgsl(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Result<String, String> {
+    let flags = naga::back::wgsl::WriterFlags::empty();
+    if module.overrides.is_empty() {
+        return naga::back::wgsl::write_string(module, info, flags).map_err(|e| e.to_string());
+    }
+
+    #[cfg(feature = "naga-24")]
+    if module.types.iter().any(|(_, ty)| {
+        matches!(
+            ty.inner,
+            naga::TypeInner::Array {
+                size: naga::ArraySize::Pending(_),
+                ..
+            } | naga::TypeInner::BindingArray {
+                size: naga::ArraySize::Pending(_),
+                ..
+            }
+        )
+    }) {
+        return Err("arrays sized by overrides are not supported".to_owned());
+    }
+
+    if let Some((_, variable)) = module
+        .global_variables
+        .iter()
+        .find(|(_, variable)| variable.init.is_some_and(|init| !is_writable(module, init)))
+    {
+        return Err(format!(
+            "the initializer of `{}` can't be written",
+            variable.name.as_deref().unwrap_or_default()
+        ));
+    }
+
+    let placeholders = Placeholders::new(module);
+    let mut operands = Vec::new();
+    let mut declarations = Vec::new();
+    for (_, override_) in module.overrides.iter() {
+        let name = override_.name.as_deref().unwrap_or_default();
+        let ty = match module.types[override_.ty].inner {
+            naga::TypeInner::Scalar(scalar) => scalar_name(scalar),
+            _ => None,
+        }
+        .ok_or_else(|| format!("the type of override `{name}` can't be written"))?;
+        let initializer = match override_.init {
+            Some(init) => {
+                let initializer = write_initializer(module, init, &placeholders, &mut operands)
+                    .ok_or_else(|| {
+                        format!("the initializer of override `{name}` can't be written")
+                    })?;
+                format!(" = {initializer}")
+            }
+            None => String::new(),
+        };
+        let id = override_
+            .id
+            .map(|id| format!("@id({id}) "))
+            .unwrap_or_default();
+        declarations.push(format!("{id}override {name}: {ty}{initializer};"));
+    }
+
+    // The sizes of each compute entry point's workgroups, where any are set by overrides
+    let mut workgroup_sizes = Vec::<Option<Vec<String>>>::new();
+    #[cfg(feature = "naga-24")]
+    for entry_point in &module.entry_points {
+        if entry_point.stage != naga::ShaderStage::Compute {
+            continue;
+        }
+        let Some(overrides) = entry_point.workgroup_size_overrides else {
+            workgroup_sizes.push(None);
+            continue;
+        };
+        let mut sizes = Vec::new();
+        for (size, override_) in entry_point.workgroup_size.into_iter().zip(overrides) {
+            sizes.push(match override_ {
+                Some(expression) => {
+                    write_initializer(module, expression, &placeholders, &mut operands).ok_or_else(
+                        || {
+                            format!(
+                                "the workgroup size of entry point `{}` can't be written",
+                                entry_point.name
+                            )
+                        },
+                    )?
+                }
+                None => size.to_string(),
+            });
+        }
+        workgroup_sizes.push(Some(sizes));
+    }
+
+    // A constant's initializer has to come before any expression that refers to the constant, so the zero values given
+    // to placeholders go before the module's own global expressions
+    let mut placeholder = module.clone();
+    let mut global_expressions = naga::Arena::new();
+    let zero_values = module
+        .overrides
+        .iter()
+        .map(|(_, override_)| {
+            global_expressions.append(naga::Expression::ZeroValue(override_.ty), Span::UNDEFINED)
+        })
+        .collect::<Vec<_>>();
+    let mut moved = Vec::with_capacity(module.global_expressions.len());
+    for (_, mut expression, span) in placeholder.global_expressions.drain() {
+        move_operands(&mut expression, &moved);
+        moved.push(global_expressions.append(expression, span));
+    }
+    placeholder.global_expressions = global_expressions;
+    for (_, constant) in placeholder.constants.iter_mut() {
+        constant.init = moved[constant.init.index()];
+    }
+    for (_, variable) in placeholder.global_variables.iter_mut() {
+        variable.init = variable.init.map(|init| moved[init.index()]);
+    }
+    #[cfg(feature = "naga-24")]
+    for entry_point in &mut placeholder.entry_points {
+        entry_point.workgroup_size_overrides = None;
+    }
+
+    let mut constants = Vec::new();
+    for (index, (_, override_)) in module.overrides.iter().enumerate() {
+        constants.push(placeholder.constants.append(
+            naga::Constant {
+                name: Some(placeholders.override_name(index)),
+                ty: override_.ty,
+                init: zero_values[index],
+            },
+            Span::UNDEFINED,
+        ));
+    }
+    for (index, &operand) in operands.iter().enumerate() {
+        let ty = match info[operand] {
+            naga::proc::TypeResolution::Handle(ty) => ty,
+            naga::proc::TypeResolution::Value(ref inner) => placeholder.types.insert(
+                naga::Type {
+                    name: None,
+                    inner: inner.clone(),
+                },
+                Span::UNDEFINED,
+            ),
+        };
+        placeholder.constants.append(
+            naga::Constant {
+                name: Some(placeholders.operand_name(index)),
+                ty,
+                init: moved[operand.index()],
+            },
+            Span::UNDEFINED,
+        );
+    }
+    placeholder.overrides = naga::Arena::new();
+
+    let replace = |expressions: &mut naga::Arena<naga::Expression>| {
+        for (_, expression) in expressions.iter_mut() {
+            if let naga::Expression::Override(handle) = *expression {
+                *expression = naga::Expression::Constant(constants[handle.index()]);
+            }
+        }
+    };
+    replace(&mut placeholder.global_expressions);
+    for (_, function) in placeholder.functions.iter_mut() {
+        replace(&mut function.expressions);
+    }
+    for entry_point in &mut placeholder.entry_points {
+        replace(&mut entry_point.function.expressions);
+    }
+
+    // The expressions that initialized overrides are left in the module, but can't be checked as constant expressions
+    let placeholder_info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all() - naga::valid::ValidationFlags::CONSTANTS,
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&placeholder)
+    .map_err(|e| format!("overrides could not be written: {}", e.as_inner()))?;
+    let source = naga::back::wgsl::write_string(&placeholder, &placeholder_info, flags)
+        .map_err(|e| e.to_string())?;
+
+    // Take out the operands as `naga` wrote them, then swap the placeholder declarations for the overrides
+    let mut lines = source.lines().map(str::to_owned).collect::<Vec<_>>();
+    for index in 0..operands.len() {
+        let name = placeholders.operand_name(index);
+        let prefix = format!("const {name}: ");
+        let operand = lines
+            .iter()
+            .position(|line| line.starts_with(&prefix))
+            .map(|position| lines.remove(position))
+            .and_then(|line| Some(line.split_once(" = ")?.1.strip_suffix(';')?.to_owned()))
+            .ok_or("an override's initializer could not be written")?;
+        for written in declarations
+            .iter_mut()
+            .chain(workgroup_sizes.iter_mut().flatten().flatten())
+        {
+            *written = written.replace(&name, &operand);
+        }
+    }
+    for (index, declaration) in declarations.into_iter().enumerate() {
+        let prefix = format!("const {}: ", placeholders.override_name(index));
+        let line = lines
+            .iter_mut()
+            .find(|line| line.starts_with(&prefix))
+            .ok_or("an override could not be written")?;
+        *line = declaration;
+    }
+    let mut source = lines.join("\n") + "\n";
+    for (index, (_, override_)) in module.overrides.iter().enumerate() {
+        source = source.replace(
+            &placeholders.override_name(index),
+            override_.name.as_deref().unwrap_or_default(),
+        );
+    }
+
+    // `naga` writes the fixed sizes, in the order of the compute entry points
+    if workgroup_sizes.iter().any(Option::is_some) {
+        let parts = source.split("@workgroup_size(").collect::<Vec<_>>();
+        if parts.len() != workgroup_sizes.len() + 1 {
+            return Err("a workgroup size could not be written".to_owned());
+        }
+        let mut written = parts[0].to_owned();
+        for (part, sizes) in parts[1..].iter().zip(&workgroup_sizes) {
+            written += "@workgroup_size(";
+            match sizes {
+                Some(sizes) => {
+                    let (_, rest) = part
+                        .split_once(')')
+                        .ok_or("a workgroup size could not be written")?;
+                    written += &sizes.join(", ");
+                    written += ")";
+                    written += rest;
+                }
+                None => written += part,
+            }
+        }
+        source = written;
+    }
+
+    // Check that the overrides came out as they went in
+    let written = naga::front::wgsl::parse_str(&source)
+        .map_err(|e| format!("the written overrides don't parse: {}", e.message()))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&written)
+    .map_err(|e| format!("the written overrides don't validate: {}", e.as_inner()))?;
+    let overrides = |module: &naga::Module| {
+        module
+            .overrides
+            .iter()
+            .map(|(_, override_)| (override_.name.clone(), override_.id))
+            .collect::<Vec<_>>()
+    };
+    if overrides(&written) != overrides(module) {
+        return Err("the overrides changed when written".to_owned());
+    }
+
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(source: &str) -> (naga::Module, naga::valid::ModuleInfo) {
+        let module = naga::front::wgsl::parse_str(source)
+            .unwrap_or_else(|e| panic!("{}\n{source}", e.emit_to_string(source)));
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{}\n{source}", e.emit_to_string(source)));
+        (module, info)
+    }
+
+    /// Writes the source, checking that the output parses and validates again.
+    fn rewrite(source: &str) -> String {
+        let (module, info) = validate(source);
+        let written = write_wgsl(&module, &info).unwrap_or_else(|e| panic!("{e}\n{source}"));
+        let (rewritten, _) = validate(&written);
+        assert_eq!(rewritten.overrides.len(), module.overrides.len());
+        written
+    }
+
+    #[test]
+    fn override_without_initializer() {
+        let source = rewrite(
+            "override scale: f32;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scale); }",
+        );
+        assert!(source.contains("override scale: f32;"), "{source}");
+    }
+
+    #[test]
+    fn override_with_literal_initializer() {
+        let source = rewrite(
+            "override scale: f32 = 2.0;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scale); }",
+        );
+        assert!(source.contains("override scale: f32 = 2f;"), "{source}");
+    }
+
+    #[test]
+    fn override_with_expression_initializer() {
+        let source = rewrite(
+            "override scale: f32 = 2.0 * 3.0 + 1.0;
+            override flipped: i32 = -4;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scale * f32(flipped)); }",
+        );
+        assert!(source.contains("override scale: f32"), "{source}");
+        assert!(source.contains("override flipped: i32"), "{source}");
+        assert!(!source.contains("wgslOil"), "{source}");
+    }
+
+    #[test]
+    fn override_with_id() {
+        let source = rewrite(
+            "@id(7) override scale: f32 = 1.0;
+            @id(3) override enabled: bool;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(select(0.0, scale, enabled)); }",
+        );
+        assert!(source.contains("@id(7) override scale: f32"), "{source}");
+        assert!(
+            source.contains("@id(3) override enabled: bool;"),
+            "{source}"
+        );
+
+        let (module, _) = validate(&source);
+        let ids = module
+            .overrides
+            .iter()
+            .map(|(_, override_)| (override_.name.as_deref().unwrap(), override_.id))
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [("scale", Some(7)), ("enabled", Some(3))]);
+    }
+
+    #[test]
+    fn override_in_another_initializer() {
+        let source = rewrite(
+            "override width: f32 = 4.0;
+            override height: f32;
+            override area: f32 = width * height;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(area); }",
+        );
+        assert!(
+            source.contains("override area: f32 = (width * height);"),
+            "{source}"
+        );
+        assert!(!source.contains("wgslOil"), "{source}");
+    }
+
+    #[test]
+    fn override_with_conversion() {
+        let source = rewrite(
+            "override count: u32 = 4u;
+            override scale: f32 = f32(count) * 0.5;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scale); }",
+        );
+        assert!(
+            source.contains("override scale: f32 = (f32(count) * 0.5f);"),
+            "{source}"
+        );
+        assert!(!source.contains("wgslOil"), "{source}");
+    }
+
+    #[test]
+    fn names_like_placeholders() {
+        let source = rewrite(
+            "const wgslOilOperand0Placeholder: f32 = 2.0;
+            override wgslOilOverride0Placeholder: f32;
+            override scaled: f32 = wgslOilOverride0Placeholder * wgslOilOperand0Placeholder;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scaled); }",
+        );
+        assert!(
+            source.contains("const wgslOilOperand0Placeholder: f32 = 2f;"),
+            "{source}"
+        );
+        assert!(
+            source.contains("override wgslOilOverride0Placeholder: f32;"),
+            "{source}"
+        );
+        assert!(
+            source.contains("override scaled: f32 = (wgslOilOverride0Placeholder * 2f);"),
+            "{source}"
+        );
+    }
+
+    #[test]
+    fn override_in_variable_initializer() {
+        let source = "override scale: f32 = 2.0;
+            var<private> scaled: f32 = scale * 2.0;
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(scaled); }";
+        let (module, info) = validate(source);
+        assert_eq!(
+            write_wgsl(&module, &info).unwrap_err(),
+            "the initializer of `scaled` can't be written"
+        );
+    }
+
+    #[test]
+    fn unsupported_initializer() {
+        let source = "override width: f32;
+            override clamped: f32 = max(width, 1.0);
+            @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(clamped); }";
+        let (module, info) = validate(source);
+        assert_eq!(
+            write_wgsl(&module, &info).unwrap_err(),
+            "the initializer of override `clamped` can't be written"
+        );
+    }
+
+    #[cfg(feature = "naga-24")]
+    #[test]
+    fn override_sized_workgroups() {
+        let source = rewrite(
+            "override size: u32 = 64;
+            @compute @workgroup_size(8, 8) fn fixed() {}
+            @compute @workgroup_size(size, size / 2u, 1) fn sized() {}",
+        );
+        assert!(source.contains("@workgroup_size(8, 8, 1)"), "{source}");
+        assert!(
+            source.contains("@workgroup_size(size, (size / 2u), 1)"),
+            "{source}"
+        );
+
+        let (module, _) = validate(&source);
+        let overridden = module
+            .entry_points
+            .iter()
+            .map(|entry_point| {
+                entry_point
+                    .workgroup_size_overrides
+                    .map(|sizes| sizes.map(|size| size.is_some()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(overridden, [None, Some([true, true, false])]);
+    }
+}
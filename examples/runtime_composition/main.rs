@@ -0,0 +1,20 @@
+use std::collections::HashMap;
+
+use include_wgsl_oil_runtime::ShaderDefValue;
+
+#[include_wgsl_oil::include_wgsl_oil(
+    path = "examples/runtime_composition/main.wgsl",
+    mode = runtime,
+    constants = SHADOW_SAMPLES = UInt(1)
+)]
+mod main_shader {}
+
+fn main() {
+    println!("Main source: {}", main_shader::SOURCE);
+
+    let defs = HashMap::from([("SHADOW_SAMPLES".to_owned(), ShaderDefValue::UInt(4))]);
+    match main_shader::compose(&defs) {
+        Ok(source) => println!("Main source with 4 shadow samples: {source}"),
+        Err(error) => eprintln!("Main shader failed to compose: {error}"),
+    }
+}
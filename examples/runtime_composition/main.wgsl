@@ -0,0 +1,10 @@
+#import shadows.wgsl::shadow_factor
+
+@group(0) @binding(0) var shadow_map: texture_depth_2d;
+@group(0) @binding(1) var shadow_sampler: sampler_comparison;
+
+@fragment
+fn fs_main(@location(0) shadow_position: vec3<f32>) -> @location(0) vec4<f32> {
+    let lit = shadow_factor(shadow_map, shadow_sampler, shadow_position);
+    return vec4(vec3(lit), 1.0);
+}
@@ -0,0 +1,14 @@
+const SHADOW_SAMPLES: u32 = #{SHADOW_SAMPLES}u;
+
+fn shadow_factor(
+    shadow_map: texture_depth_2d,
+    shadow_sampler: sampler_comparison,
+    position: vec3<f32>,
+) -> f32 {
+    var lit = 0.0;
+    for (var i = 0u; i < SHADOW_SAMPLES; i++) {
+        let offset = vec2(f32(i) - f32(SHADOW_SAMPLES - 1u) * 0.5) / 1024.0;
+        lit += textureSampleCompare(shadow_map, shadow_sampler, position.xy + offset, position.z);
+    }
+    return lit / f32(SHADOW_SAMPLES);
+}
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use naga_oil::compose::{
+    ComposableModuleDescriptor, Composer, NagaModuleDescriptor, ShaderLanguage, ShaderType,
+};
+
+pub use naga_oil::compose::ShaderDefValue;
+
+/// An error composing a shader at runtime, with the message given by `naga_oil` or `naga`.
+#[derive(Clone, Debug)]
+pub struct ComposeError {
+    message: String,
+}
+
+impl ComposeError {
+    pub(crate) fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ComposeError {}
+
+/// Composes a shader embedded with `mode = runtime` with the given shader defs, giving the composed shader as WGSL.
+/// `modules` are the `(name, source)` pairs of the modules that the shader imports, in the order they need to be
+/// added to the composer, and `source` is the shader itself, reported in errors as `path`.
+pub fn compose(
+    path: &str,
+    modules: &[(&str, &str)],
+    source: &str,
+    shader_defs: HashMap<String, ShaderDefValue>,
+) -> Result<String, ComposeError> {
+    let mut composer = Composer::default();
+    composer.capabilities = naga::valid::Capabilities::all();
+    composer.validate = true;
+
+    for (name, module_source) in modules {
+        let res = composer.add_composable_module(ComposableModuleDescriptor {
+            source: module_source,
+            file_path: name,
+            language: ShaderLanguage::Wgsl,
+            as_name: Some((*name).to_owned()),
+            additional_imports: &[],
+            shader_defs: shader_defs.clone(),
+        });
+        if let Err(e) = res {
+            return Err(ComposeError::new(e.emit_to_string(&composer)));
+        }
+    }
+
+    let module = composer
+        .make_naga_module(NagaModuleDescriptor {
+            source,
+            file_path: path,
+            shader_type: ShaderType::Wgsl,
+            shader_defs,
+            additional_imports: &[],
+        })
+        .map_err(|e| ComposeError::new(e.emit_to_string(&composer)))?;
+
+    // The composer has already validated the module, reporting errors against the right files
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| ComposeError::new(e.emit_to_string(source)))?;
+
+    naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+        .map_err(|e| ComposeError::new(e.to_string()))
+}
@@ -0,0 +1,7 @@
+/// Decompresses a shader embedded with `compress_source`, which the generated `decompressed_source` function calls the
+/// first time the shader is used.
+pub fn decompress_source(bytes: &[u8]) -> String {
+    let bytes = miniz_oxide::inflate::decompress_to_vec(bytes)
+        .expect("shaders are compressed with DEFLATE while compiling");
+    String::from_utf8(bytes).expect("shaders are compressed from UTF-8")
+}
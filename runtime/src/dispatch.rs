@@ -0,0 +1,14 @@
+/// Gives the number of workgroups of `workgroup_size` invocations needed to cover `elements` invocations.
+pub const fn workgroup_count(elements: u32, workgroup_size: u32) -> u32 {
+    elements.div_ceil(workgroup_size)
+}
+
+/// Gives the number of workgroups to dispatch in each dimension to cover a grid of `elements` invocations, using the
+/// workgroup size of a compute entry point.
+pub const fn dispatch_size(elements: [u32; 3], workgroup_size: [u32; 3]) -> [u32; 3] {
+    [
+        workgroup_count(elements[0], workgroup_size[0]),
+        workgroup_count(elements[1], workgroup_size[1]),
+        workgroup_count(elements[2], workgroup_size[2]),
+    ]
+}
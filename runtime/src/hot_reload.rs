@@ -0,0 +1,183 @@
+use std::{borrow::Cow, collections::HashMap, fs};
+
+use crate::{compose, ComposeError, ShaderDefValue};
+
+/// A file that a shader is composed from, as embedded for the generated `load_source` function.
+pub struct ShaderFile {
+    /// The name that the module is added to the composer as, or the path of the shader within its crate.
+    pub name: &'static str,
+    /// The absolute path of the file, on the machine that compiled the shader.
+    pub path: &'static str,
+    /// The name that each import by path in the file was renamed to, as `(requested path, name)` pairs.
+    pub imports: &'static [(&'static str, &'static str)],
+    /// The module's source as it was composed, for modules which aren't files on disk, such as the built-in library.
+    pub source: Option<&'static str>,
+}
+
+/// Everything needed to read a shader and the files it imports from disk again and recompose them.
+pub struct ShaderFiles {
+    /// The files of the modules that the shader imports, in the order they need to be added to the composer.
+    pub modules: &'static [ShaderFile],
+    /// The shader file itself.
+    pub root: ShaderFile,
+    /// The attribute marking exported items, without the `@`, or `None` if exports are disabled.
+    pub export_directive: Option<&'static str>,
+    /// The shader defs that the shader is composed with.
+    pub shader_defs: &'static [(&'static str, ShaderDefValue)],
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Replaces export attributes with spaces, as they are removed while compiling. Comments are skipped.
+fn strip_exports(source: &str, directive: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if bytes[i..].starts_with(b"/*") {
+            // Block comments nest in WGSL
+            let mut depth = 0;
+            while i < bytes.len() {
+                if bytes[i..].starts_with(b"/*") {
+                    depth += 1;
+                    i += 2;
+                } else if bytes[i..].starts_with(b"*/") {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else if bytes[i] == b'@'
+            && bytes[i + 1..].starts_with(directive.as_bytes())
+            && !bytes
+                .get(i + 1 + directive.len())
+                .is_some_and(|byte| is_ident_byte(*byte))
+        {
+            let start = i;
+            i += 1 + directive.len();
+            let mut end = i;
+            while end < bytes.len() && bytes[end].is_ascii_whitespace() {
+                end += 1;
+            }
+            if bytes.get(end) == Some(&b'(') {
+                // The argument list may contain string literals
+                let mut depth = 0;
+                let mut in_string = false;
+                while end < bytes.len() {
+                    match bytes[end] {
+                        b'"' => in_string = !in_string,
+                        b'(' if !in_string => depth += 1,
+                        b')' if !in_string => {
+                            depth -= 1;
+                            if depth == 0 {
+                                end += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    end += 1;
+                }
+                i = end;
+            }
+            for byte in &mut out[start..i] {
+                if *byte != b'\n' {
+                    *byte = b' ';
+                }
+            }
+        } else {
+            i += 1;
+        }
+    }
+    String::from_utf8(out).expect("only ASCII bytes are replaced")
+}
+
+/// Renames the imports by path in a file as they were renamed while compiling, right aligned so that `naga_oil`
+/// parses imports of items the same way.
+fn rename_imports(file: &ShaderFile, source: &str) -> Result<String, ComposeError> {
+    let mut out = String::with_capacity(source.len());
+    for line in source.split_inclusive('\n') {
+        let directive = line.trim_start();
+        let import = directive
+            .strip_prefix('#')
+            .map(str::trim_start)
+            .and_then(|rest| rest.strip_prefix("import"))
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(str::trim_start)
+            .and_then(|rest| {
+                let path = rest.split(char::is_whitespace).next()?;
+                Some(&path[..path.find(".wgsl")? + ".wgsl".len()])
+            });
+        let Some(import) = import else {
+            out.push_str(line);
+            continue;
+        };
+
+        let Some((_, name)) = file.imports.iter().find(|(path, _)| *path == import) else {
+            return Err(ComposeError::new(format!(
+                "`{}` imports `{import}`, which it didn't import when it was compiled - rebuild to import new files",
+                file.path
+            )));
+        };
+        let renamed = format!("{name:>len$}", len = import.len());
+        out.push_str(&line.replacen(import, &renamed, 1));
+    }
+    Ok(out)
+}
+
+fn read(file: &ShaderFile, export_directive: Option<&str>) -> Result<String, ComposeError> {
+    if let Some(source) = file.source {
+        return Ok(source.to_owned());
+    }
+    let source = fs::read_to_string(file.path)
+        .map_err(|e| ComposeError::new(format!("could not read `{}`: {e}", file.path)))?;
+    let source = match export_directive {
+        Some(directive) => strip_exports(&source, directive),
+        None => source,
+    };
+    rename_imports(file, &source)
+}
+
+/// Reads a shader and the files it imports from disk again and recomposes them, so that edits are picked up without
+/// recompiling. New imports by path, or files added to `includes`, are only found by recompiling.
+pub fn recompose(files: &ShaderFiles) -> Result<String, ComposeError> {
+    let modules = files
+        .modules
+        .iter()
+        .map(|file| Ok((file.name, read(file, files.export_directive)?)))
+        .collect::<Result<Vec<_>, ComposeError>>()?;
+    let modules = modules
+        .iter()
+        .map(|(name, source)| (*name, source.as_str()))
+        .collect::<Vec<_>>();
+    let source = read(&files.root, files.export_directive)?;
+    let shader_defs = files
+        .shader_defs
+        .iter()
+        .map(|(name, value)| ((*name).to_owned(), *value))
+        .collect::<HashMap<_, _>>();
+
+    compose(files.root.name, &modules, &source, shader_defs)
+}
+
+/// Recomposes a shader from the files on disk, as `recompose` does, or if that fails, prints the error and gives
+/// `default`, so that a mistake while editing a shader doesn't stop the application.
+pub fn load_source(files: &ShaderFiles, default: &'static str) -> Cow<'static, str> {
+    match recompose(files) {
+        Ok(source) => Cow::Owned(source),
+        Err(e) => {
+            eprintln!("could not reload `{}`: {e}", files.root.name);
+            Cow::Borrowed(default)
+        }
+    }
+}
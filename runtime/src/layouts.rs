@@ -0,0 +1,43 @@
+use std::{collections::HashMap, sync::Mutex};
+
+/// Caches the bind group layouts created for shaders, keyed by the hash of the shader's source and the group index, so
+/// that pipelines built from the same shader share layouts. Generic over the layout type, e.g. `wgpu::BindGroupLayout`.
+pub struct LayoutCache<T> {
+    layouts: Mutex<HashMap<(u64, u32), T>>,
+}
+
+impl<T> Default for LayoutCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> LayoutCache<T> {
+    pub fn new() -> Self {
+        Self {
+            layouts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gives the layout cached for `group` of the shader with the given source hash, creating it if this is the first
+    /// time it has been requested.
+    pub fn get_or_create(&self, source_hash: u64, group: u32, create: impl FnOnce() -> T) -> T
+    where
+        T: Clone,
+    {
+        self.layouts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((source_hash, group))
+            .or_insert_with(create)
+            .clone()
+    }
+
+    /// Removes every layout cached for the shader with the given source hash, e.g. once it has been replaced.
+    pub fn evict(&self, source_hash: u64) {
+        self.layouts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(hash, _), _| *hash != source_hash);
+    }
+}
@@ -0,0 +1,37 @@
+//! Runtime support for the code generated by [`include-wgsl-oil`](https://crates.io/crates/include-wgsl-oil), enabled
+//! with its `runtime` feature, along with small helpers for working with the reflected data.
+
+// Backend selection for `compose`, matching `include-wgsl-oil`'s: if both are enabled `naga-24` is used
+#[cfg(all(feature = "compose", not(any(feature = "naga-24", feature = "naga-23"))))]
+compile_error!("the `compose` feature needs one of the `naga-24` or `naga-23` features");
+
+#[cfg(all(feature = "compose", feature = "naga-23", not(feature = "naga-24")))]
+extern crate naga_23 as naga;
+#[cfg(all(feature = "compose", feature = "naga-24"))]
+extern crate naga_24 as naga;
+#[cfg(all(feature = "compose", feature = "naga-23", not(feature = "naga-24")))]
+extern crate naga_oil_16 as naga_oil;
+#[cfg(all(feature = "compose", feature = "naga-24"))]
+extern crate naga_oil_17 as naga_oil;
+
+#[cfg(feature = "compose")]
+mod compose;
+#[cfg(feature = "compress")]
+mod compress;
+mod dispatch;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod layouts;
+mod source;
+mod stages;
+
+#[cfg(feature = "compose")]
+pub use compose::{compose, ComposeError, ShaderDefValue};
+#[cfg(feature = "compress")]
+pub use compress::decompress_source;
+pub use dispatch::{dispatch_size, workgroup_count};
+#[cfg(feature = "hot-reload")]
+pub use hot_reload::{load_source, recompose, ShaderFile, ShaderFiles};
+pub use layouts::LayoutCache;
+pub use source::{clear_source, set_source, source};
+pub use stages::{visibility, Stages};
@@ -0,0 +1,54 @@
+use std::{borrow::Cow, collections::HashMap, sync::RwLock};
+
+/// Replacement sources registered at runtime, keyed by the module path of the shader they replace.
+static SOURCES: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+
+/// Replaces the source returned by the generated `source()` function of the shader module at `shader`, e.g.
+/// `my_crate::shaders::my_shader`, so that tools such as shader editors can substitute a recomposed shader.
+///
+/// Replacements only take effect in debug builds, where `source()` checks for them. Replacing a shader again frees the
+/// previous replacement.
+pub fn set_source(shader: &str, source: impl Into<String>) {
+    SOURCES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(HashMap::new)
+        .insert(shader.to_owned(), source.into());
+}
+
+/// Removes any replacement registered for the shader module at `shader`, so that the composed source is used again.
+pub fn clear_source(shader: &str) {
+    if let Some(sources) = SOURCES.write().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        sources.remove(shader);
+    }
+}
+
+/// Gives a copy of the replacement registered for the shader module at `shader`, or `default` if there is none.
+pub fn source(shader: &str, default: &'static str) -> Cow<'static, str> {
+    SOURCES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|sources| sources.get(shader).cloned())
+        .map_or(Cow::Borrowed(default), Cow::Owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaced_source() {
+        let shader = "tests::replaced_source";
+        assert_eq!(source(shader, "default"), "default");
+
+        set_source(shader, "first");
+        assert_eq!(source(shader, "default"), "first");
+        set_source(shader, "second");
+        assert_eq!(source(shader, "default"), "second");
+        assert_eq!(source("tests::other_shader", "default"), "default");
+
+        clear_source(shader);
+        assert_eq!(source(shader, "default"), "default");
+    }
+}
@@ -0,0 +1,67 @@
+use std::ops::{BitOr, BitOrAssign};
+
+/// A set of shader stages, such as the stages a binding must be visible to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Stages(u8);
+
+impl Stages {
+    pub const NONE: Self = Self(0);
+    pub const VERTEX: Self = Self(1 << 0);
+    pub const FRAGMENT: Self = Self(1 << 1);
+    pub const COMPUTE: Self = Self(1 << 2);
+    pub const VERTEX_FRAGMENT: Self = Self(Self::VERTEX.0 | Self::FRAGMENT.0);
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Stages {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for Stages {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+#[cfg(feature = "wgpu")]
+impl From<Stages> for wgpu::ShaderStages {
+    fn from(stages: Stages) -> Self {
+        let mut visibility = wgpu::ShaderStages::NONE;
+        if stages.contains(Stages::VERTEX) {
+            visibility |= wgpu::ShaderStages::VERTEX;
+        }
+        if stages.contains(Stages::FRAGMENT) {
+            visibility |= wgpu::ShaderStages::FRAGMENT;
+        }
+        if stages.contains(Stages::COMPUTE) {
+            visibility |= wgpu::ShaderStages::COMPUTE;
+        }
+        visibility
+    }
+}
+
+/// Gives the stages a binding must be visible to, given the stages of every entry point that uses it.
+pub const fn visibility(used_by: &[Stages]) -> Stages {
+    let mut visibility = Stages::NONE;
+    let mut i = 0;
+    while i < used_by.len() {
+        visibility = visibility.union(used_by[i]);
+        i += 1;
+    }
+    visibility
+}